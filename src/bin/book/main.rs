@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::process;
+
+use chess_engine::{
+    encode_book, encode_book_move, get_polyglot_key, parse_pgn_games, BookEntry, Colour,
+};
+
+const USAGE: &str = "Usage: book OUTPUT PGN... [--min-games N] [--min-score PERCENT] [--max-ply N]
+  OUTPUT               the polyglot book to write
+  PGN                  game collections to build it from
+  --min-games N        games a move must have been played in to be kept
+                       (default 3)
+  --min-score PERCENT  score a move must have made for the side playing it,
+                       counting a draw as half, to be kept (default 0)
+  --max-ply N          moves into each game to stop at, counting both sides
+                       (default 30)";
+
+const DEFAULT_MIN_GAMES: u32 = 3;
+const DEFAULT_MAX_PLY: usize = 30;
+
+struct Arguments {
+    output: String,
+    inputs: Vec<String>,
+    min_games: u32,
+    min_score: f64,
+    max_ply: usize,
+}
+
+// how a move has done over the games it was played in
+#[derive(Default)]
+struct MoveStatistics {
+    games: u32,
+    // two for a win and one for a draw, from the side that played it
+    points: u32,
+}
+
+fn parse_number<T: std::str::FromStr>(value: Option<&String>) -> Result<T, String> {
+    value
+        .and_then(|value| value.parse().ok())
+        .ok_or(USAGE.to_string())
+}
+
+fn parse_arguments(arguments: &[String]) -> Result<Arguments, String> {
+    let mut paths = Vec::new();
+    let mut min_games = DEFAULT_MIN_GAMES;
+    let mut min_score = 0.0;
+    let mut max_ply = DEFAULT_MAX_PLY;
+
+    let mut arguments = arguments.iter();
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "--min-games" => min_games = parse_number(arguments.next())?,
+            "--min-score" => min_score = parse_number(arguments.next())?,
+            "--max-ply" => max_ply = parse_number(arguments.next())?,
+            "--help" | "-h" => return Err(USAGE.to_string()),
+            _ => paths.push(argument.clone()),
+        }
+    }
+    match paths.split_first() {
+        Some((output, inputs)) if !inputs.is_empty() && (0.0..=100.0).contains(&min_score) => {
+            Ok(Arguments {
+                output: output.clone(),
+                inputs: inputs.to_vec(),
+                min_games,
+                min_score,
+                max_ply,
+            })
+        }
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+// points for white and for black, or None for a game that was not finished
+fn get_result_points(result: &str) -> Option<(u32, u32)> {
+    match result {
+        "1-0" => Some((2, 0)),
+        "0-1" => Some((0, 2)),
+        "1/2-1/2" => Some((1, 1)),
+        _ => None,
+    }
+}
+
+// scales the points down to fit the sixteen bits a book has for a weight,
+// keeping any move that scored at all above zero
+fn get_weights(statistics: &[(u64, u16, MoveStatistics)]) -> Vec<u16> {
+    let most_points = statistics
+        .iter()
+        .map(|(_, _, move_statistics)| move_statistics.points)
+        .max()
+        .unwrap_or(0);
+    let scale = (most_points as f64 / u16::MAX as f64).max(1.0);
+    statistics
+        .iter()
+        .map(|(_, _, move_statistics)| match move_statistics.points {
+            0 => 0,
+            points => ((points as f64 / scale) as u16).max(1),
+        })
+        .collect()
+}
+
+fn run() -> Result<(), Box<dyn Error>> {
+    let arguments: Vec<String> = env::args().skip(1).collect();
+    let arguments = parse_arguments(&arguments)?;
+
+    let mut statistics: HashMap<(u64, u16), MoveStatistics> = HashMap::new();
+    let mut games_read = 0;
+    let mut games_used = 0;
+    let mut games_skipped = 0;
+    for path in &arguments.inputs {
+        let text = fs::read_to_string(path).map_err(|e| format!("Could not read '{path}': {e}"))?;
+        for (index, game) in parse_pgn_games(&text).into_iter().enumerate() {
+            games_read += 1;
+            let game = match game {
+                Ok(game) => game,
+                Err(e) => {
+                    eprintln!("Skipping game {} in '{path}': {e}", index + 1);
+                    games_skipped += 1;
+                    continue;
+                }
+            };
+            let Some((white_points, black_points)) = get_result_points(&game.result) else {
+                continue;
+            };
+            games_used += 1;
+            let mut position = game.start_position;
+            for mv in game.moves.iter().take(arguments.max_ply) {
+                let points = match position.get_turn() {
+                    Colour::White => white_points,
+                    Colour::Black => black_points,
+                };
+                let move_statistics = statistics
+                    .entry((get_polyglot_key(&position), encode_book_move(mv)))
+                    .or_default();
+                move_statistics.games += 1;
+                move_statistics.points += points;
+                position.make_move(mv);
+            }
+        }
+    }
+
+    let mut kept: Vec<(u64, u16, MoveStatistics)> = statistics
+        .into_iter()
+        .filter(|(_, move_statistics)| {
+            let score = 50.0 * move_statistics.points as f64 / move_statistics.games as f64;
+            move_statistics.games >= arguments.min_games && score >= arguments.min_score
+        })
+        .map(|((key, mv), move_statistics)| (key, mv, move_statistics))
+        .collect();
+    // books are searched by key, and the best move of each position goes first
+    kept.sort_by(|a, b| {
+        a.0.cmp(&b.0)
+            .then(b.2.points.cmp(&a.2.points))
+            .then(a.1.cmp(&b.1))
+    });
+
+    let entries: Vec<BookEntry> = kept
+        .iter()
+        .zip(get_weights(&kept))
+        .map(|((key, mv, _), weight)| BookEntry {
+            key: *key,
+            mv: *mv,
+            weight,
+            learn: 0,
+        })
+        .collect();
+    fs::write(&arguments.output, encode_book(&entries))
+        .map_err(|e| format!("Could not write '{}': {e}", arguments.output))?;
+    println!(
+        "Read {games_read} games, skipped {games_skipped} that could not be parsed, used \
+         {games_used} with a result, and wrote {} moves to {}",
+        entries.len(),
+        arguments.output
+    );
+    Ok(())
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("{e}");
+        process::exit(1);
+    }
+}
@@ -0,0 +1,160 @@
+use std::env;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::process;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Instant;
+
+use chess_engine::search::SearchOptions;
+use chess_engine::{encode_record, format_record, play_game, DatagenSettings, Random};
+
+const USAGE: &str =
+    "Usage: datagen OUTPUT [--games N] [--nodes N] [--random-plies N] [--seed N] [--threads N]
+  OUTPUT            writes OUTPUT.bin with the compact records and OUTPUT.txt
+                    with one 'FEN | score | result' per line, both of which
+                    the tune tool reads
+  --games N         games to play in total (default 100)
+  --nodes N         nodes searched for every move (default 5000)
+  --random-plies N  random moves to start each game with (default 8)
+  --seed N          seed for the openings, the same seed and settings giving
+                    the same games (default from the clock)
+  --threads N       games played at once (default 1)";
+
+const DEFAULT_GAMES: usize = 100;
+const DEFAULT_NODES: u64 = 5000;
+const DEFAULT_RANDOM_PLIES: usize = 8;
+// megabytes, which is plenty at the node counts used here and keeps the
+// table quick to clear before every move
+const DATAGEN_HASH_SIZE: usize = 1;
+
+struct Arguments {
+    output: String,
+    games: usize,
+    settings: DatagenSettings,
+    seed: Option<u64>,
+    threads: usize,
+}
+
+fn parse_number<T: std::str::FromStr>(value: Option<&String>) -> Result<T, String> {
+    value
+        .and_then(|value| value.parse().ok())
+        .ok_or(USAGE.to_string())
+}
+
+fn parse_arguments(arguments: &[String]) -> Result<Arguments, String> {
+    let mut outputs = Vec::new();
+    let mut games = DEFAULT_GAMES;
+    let mut settings = DatagenSettings {
+        nodes: DEFAULT_NODES,
+        random_plies: DEFAULT_RANDOM_PLIES,
+    };
+    let mut seed = None;
+    let mut threads = 1;
+
+    let mut arguments = arguments.iter();
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "--games" => games = parse_number(arguments.next())?,
+            "--nodes" => settings.nodes = parse_number(arguments.next())?,
+            "--random-plies" => settings.random_plies = parse_number(arguments.next())?,
+            "--seed" => seed = Some(parse_number(arguments.next())?),
+            "--threads" => threads = parse_number(arguments.next())?,
+            "--help" | "-h" => return Err(USAGE.to_string()),
+            _ => outputs.push(argument.clone()),
+        }
+    }
+    match outputs.as_slice() {
+        [output] if threads > 0 => Ok(Arguments {
+            output: output.clone(),
+            games,
+            settings,
+            seed,
+            threads,
+        }),
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+fn create_file(path: &str) -> Result<BufWriter<File>, String> {
+    File::create(path)
+        .map(BufWriter::new)
+        .map_err(|e| format!("Could not create '{path}': {e}"))
+}
+
+fn run() -> Result<(), Box<dyn Error>> {
+    let arguments: Vec<String> = env::args().skip(1).collect();
+    let arguments = parse_arguments(&arguments)?;
+    let seed = arguments
+        .seed
+        .unwrap_or_else(|| Random::from_clock().next_u64());
+    println!("Seed {seed}");
+
+    let binary_path = format!("{}.bin", arguments.output);
+    let text_path = format!("{}.txt", arguments.output);
+    let mut binary_file = create_file(&binary_path)?;
+    let mut text_file = create_file(&text_path)?;
+
+    let start_time = Instant::now();
+    let mut games_played = 0;
+    let mut positions_written = 0;
+    thread::scope(|scope| -> Result<(), Box<dyn Error>> {
+        let (sender, receiver) = mpsc::channel();
+        for thread_index in 0..arguments.threads {
+            let sender = sender.clone();
+            let settings = &arguments.settings;
+            // thread n plays games n, n + threads and so on
+            let games =
+                (arguments.games + arguments.threads - 1 - thread_index) / arguments.threads;
+            scope.spawn(move || {
+                let mut random = Random::new(&seed.wrapping_add(thread_index as u64));
+                let search_options = SearchOptions {
+                    hash_size: DATAGEN_HASH_SIZE,
+                    ..SearchOptions::default()
+                };
+                for _ in 0..games {
+                    if sender
+                        .send(play_game(&mut random, settings, &search_options))
+                        .is_err()
+                    {
+                        return;
+                    }
+                }
+            });
+        }
+        // the receiver only runs dry once every thread has finished
+        drop(sender);
+
+        for records in receiver {
+            for record in &records {
+                if let Some(bytes) = encode_record(record) {
+                    binary_file.write_all(&bytes)?;
+                    writeln!(text_file, "{}", format_record(record))?;
+                    positions_written += 1;
+                }
+            }
+            games_played += 1;
+            println!(
+                "Game {games_played} of {}: {} positions, {positions_written} in total, {:.0}s",
+                arguments.games,
+                records.len(),
+                start_time.elapsed().as_secs_f64()
+            );
+        }
+        Ok(())
+    })
+    .map_err(|e| format!("Could not write the output: {e}"))?;
+
+    binary_file.flush()?;
+    text_file.flush()?;
+    println!("Wrote {positions_written} positions to {binary_path} and {text_path}");
+    Ok(())
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("{e}");
+        process::exit(1);
+    }
+}
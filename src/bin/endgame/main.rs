@@ -0,0 +1,82 @@
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::process;
+use std::time::Instant;
+
+use chess_engine::{
+    generate_table, get_all_table_names, get_subtable_names, get_table_name, get_table_path,
+    parse_table_name, EndgameTableError, EndgameTables,
+};
+
+const USAGE: &str = "Usage: endgame DIRECTORY TABLE...
+  DIRECTORY  where to write the tables, reading any already there instead of
+             generating them again
+  TABLE      endings to generate, like KQvK or KBNvK, or 'all' for every one
+             of three or four pieces. the tables their captures and
+             promotions lead into are generated first";
+
+// generates a table after every table it leads into, unless it is already
+// among the ones loaded
+fn generate(name: &str, directory: &str, tables: &mut EndgameTables) -> Result<(), Box<dyn Error>> {
+    if tables.contains(name) {
+        return Ok(());
+    }
+    let pieces =
+        parse_table_name(name).ok_or(EndgameTableError::InvalidMaterial(name.to_string()))?;
+    for subtable in get_subtable_names(&pieces) {
+        generate(&subtable, directory, tables)?;
+    }
+
+    let start = Instant::now();
+    let table = generate_table(&pieces, tables)?;
+    let path = get_table_path(directory, name);
+    fs::write(&path, table.to_bytes()).map_err(|e| format!("Could not write '{path}': {e}"))?;
+    let longest_mate = match table.get_longest_mate() {
+        Some(plies) => format!("longest mate in {}", plies.div_ceil(2)),
+        None => "no mates".to_string(),
+    };
+    println!(
+        "{name}: {longest_mate}, written to {path} in {:.1}s",
+        start.elapsed().as_secs_f64()
+    );
+    tables.insert(table);
+    Ok(())
+}
+
+fn run() -> Result<(), Box<dyn Error>> {
+    let arguments: Vec<String> = env::args().skip(1).collect();
+    let Some((directory, requested)) = arguments.split_first() else {
+        return Err(USAGE.into());
+    };
+    if requested.is_empty() || requested.iter().any(|argument| argument.starts_with('-')) {
+        return Err(USAGE.into());
+    }
+
+    let mut names = Vec::new();
+    for argument in requested {
+        match argument.as_str() {
+            "all" => names.extend(get_all_table_names()),
+            name => {
+                let pieces = parse_table_name(name)
+                    .ok_or(EndgameTableError::InvalidMaterial(name.to_string()))?;
+                names.push(get_table_name(&pieces));
+            }
+        }
+    }
+
+    fs::create_dir_all(directory).map_err(|e| format!("Could not create '{directory}': {e}"))?;
+    let mut tables = EndgameTables::open(directory)?;
+    for name in &names {
+        generate(name, directory, &mut tables)?;
+    }
+    println!("{} tables in {directory}", tables.len());
+    Ok(())
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("{e}");
+        process::exit(1);
+    }
+}
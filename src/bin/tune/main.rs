@@ -0,0 +1,130 @@
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::process;
+use std::time::Instant;
+
+use chess_engine::{
+    compute_error, find_scaling, parse_binary_dataset, parse_dataset, parse_parameters, tune,
+    Parameters, TuningPosition,
+};
+
+const USAGE: &str = "Usage: tune DATASET OUTPUT [--start FILE] [--passes N]
+  DATASET        positions with game results, one 'FEN RESULT' per line,
+                 or the .bin records written by datagen
+  OUTPUT         where to write the tuned parameters after every pass, for
+                 'params FILE' in the terminal or the EvalParameters option
+  --start FILE   tune on from an earlier parameter file instead of the
+                 built-in weights
+  --passes N     stop after N passes over every weight (default 100)";
+
+const DEFAULT_PASSES: usize = 100;
+
+struct Arguments {
+    dataset: String,
+    output: String,
+    start: Option<String>,
+    passes: usize,
+}
+
+fn parse_arguments(arguments: &[String]) -> Result<Arguments, String> {
+    let mut files = Vec::new();
+    let mut start = None;
+    let mut passes = DEFAULT_PASSES;
+
+    let mut arguments = arguments.iter();
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "--start" => start = Some(arguments.next().ok_or(USAGE)?.clone()),
+            "--passes" => {
+                passes = arguments
+                    .next()
+                    .and_then(|count| count.parse().ok())
+                    .ok_or(USAGE)?;
+            }
+            "--help" | "-h" => return Err(USAGE.to_string()),
+            _ => files.push(argument.clone()),
+        }
+    }
+    match files.as_slice() {
+        [dataset, output] => Ok(Arguments {
+            dataset: dataset.clone(),
+            output: output.clone(),
+            start,
+            passes,
+        }),
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+fn read_file(path: &str) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| format!("Could not read '{path}': {e}"))
+}
+
+fn read_dataset(path: &str) -> Result<Vec<TuningPosition>, String> {
+    let positions = match path.ends_with(".bin") {
+        true => {
+            let bytes = fs::read(path).map_err(|e| format!("Could not read '{path}': {e}"))?;
+            parse_binary_dataset(&bytes)
+        }
+        false => parse_dataset(&read_file(path)?),
+    };
+    positions.map_err(|e| format!("In '{path}': {e}"))
+}
+
+fn run() -> Result<(), Box<dyn Error>> {
+    let arguments: Vec<String> = env::args().skip(1).collect();
+    let arguments = parse_arguments(&arguments)?;
+
+    let start = match &arguments.start {
+        Some(path) => {
+            parse_parameters(&read_file(path)?).map_err(|e| format!("In '{path}': {e}"))?
+        }
+        None => Parameters::default(),
+    };
+    let positions = read_dataset(&arguments.dataset)?;
+    if positions.is_empty() {
+        return Err(format!("No positions found in '{}'.", arguments.dataset).into());
+    }
+    println!("Loaded {} positions", positions.len());
+
+    let scaling = find_scaling(&positions, &start);
+    println!(
+        "Scaling {scaling:.4}, starting error {:.6}",
+        compute_error(&positions, &start, &scaling)
+    );
+
+    let start_time = Instant::now();
+    let mut write_error = None;
+    tune(
+        &positions,
+        &start,
+        &scaling,
+        &arguments.passes,
+        &mut |progress, parameters| {
+            println!(
+                "Pass {}: error {:.6}, {} weights changed, {:.0}s",
+                progress.pass,
+                progress.error,
+                progress.changed,
+                start_time.elapsed().as_secs_f64()
+            );
+            // written every pass so that stopping early loses little
+            if let Err(e) = fs::write(&arguments.output, parameters.to_string()) {
+                write_error = Some(e);
+            }
+        },
+    );
+    if let Some(e) = write_error {
+        return Err(format!("Could not write '{}': {e}", arguments.output).into());
+    }
+    println!("Wrote tuned parameters to {}", arguments.output);
+    Ok(())
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("{e}");
+        process::exit(1);
+    }
+}
@@ -0,0 +1,605 @@
+use std::fs;
+use std::io::{self, BufRead};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use chess_engine::moves::Move;
+use chess_engine::notation::{
+    fen_to_position, line_to_san, move_to_uci, position_to_fen, uci_to_move,
+};
+use chess_engine::position::{get_starting_position, Position};
+use chess_engine::search::{
+    format_score, search_until_stopped, Evaluator, SearchLimits, SearchLine, SearchOptions,
+    SearchResult, SearchSignals, DEFAULT_HASH_SIZE, MATE_SCORE, MATE_THRESHOLD, MAX_DEPTH,
+    MAX_MULTI_PV, MAX_THREADS,
+};
+use chess_engine::{
+    find_mate, parse_parameters, print_board, trace_evaluation, Book, BookSelection, Clock, Colour,
+    Game, Network, Parameters, ProtocolError, Random, RenderOptions, Tablebases, Theme,
+    TranspositionTable,
+};
+
+const ENGINE_NAME: &str = "chess_engine";
+const ENGINE_AUTHOR: &str = "Jack O'Keefe";
+// in megabytes
+const MIN_HASH_SIZE: usize = 1;
+const MAX_HASH_SIZE: usize = 4096;
+// in milliseconds
+const DEFAULT_MOVE_OVERHEAD: u64 = 30;
+const MAX_MOVE_OVERHEAD: u64 = 5000;
+// the longest mate 'go mate' looks for, far beyond what it can prove in
+// reasonable time anyway
+const MAX_MATE_MOVES: u32 = 100;
+
+struct Engine {
+    position: Position,
+    // hashes of the positions before this one in the game, for spotting
+    // repetitions
+    history: Vec<u64>,
+    chess960: bool,
+    search_options: SearchOptions,
+    // kept between searches, and only cleared by a new game or a new size
+    table: Arc<TranspositionTable>,
+    // kept while UseNNUE is off so that turning it back on needs no reload
+    network: Option<Arc<Network>>,
+    use_network: bool,
+    move_overhead: Duration,
+    // kept while OwnBook is off, like the network
+    book: Option<Book>,
+    own_book: bool,
+    book_selection: BookSelection,
+    random: Random,
+    signals: Arc<SearchSignals>,
+    search_thread: Option<JoinHandle<()>>,
+}
+
+fn format_uci_score(score: &i32) -> String {
+    if score.abs() >= MATE_THRESHOLD {
+        let plies = MATE_SCORE - score.abs();
+        let moves = (plies + 1) / 2;
+        match *score > 0 {
+            true => format!("mate {moves}"),
+            false => format!("mate -{moves}"),
+        }
+    } else {
+        format!("cp {score}")
+    }
+}
+
+// one line for each ranked move of a multipv search, numbered from 1
+fn print_info(position: &Position, result: &SearchResult, chess960: &bool) {
+    let millis = result.elapsed.as_millis().max(1);
+    let nps = result.nodes as u128 * 1000 / millis;
+
+    for (index, line) in result.lines.iter().enumerate() {
+        // each move of the pv is written from the position it is played in
+        let mut pv_position = *position;
+        let mut pv = Vec::new();
+        for mv in &line.pv {
+            pv.push(move_to_uci(&pv_position, mv, chess960));
+            pv_position.make_move(mv);
+        }
+        let multi_pv = match result.lines.len() {
+            1 => String::new(),
+            _ => format!(" multipv {}", index + 1),
+        };
+
+        println!(
+            "info depth {}{} score {} nodes {} nps {} time {} pv {}",
+            result.depth,
+            multi_pv,
+            format_uci_score(&line.score),
+            result.nodes,
+            nps,
+            result.elapsed.as_millis(),
+            pv.join(" ")
+        );
+    }
+}
+
+// what a 'go' command asks for
+struct GoCommand {
+    limits: SearchLimits,
+    // the move is held back until a 'stop'
+    infinite: bool,
+    // thinking on the opponent's time, the move held back until a
+    // 'ponderhit' or 'stop'
+    ponder: bool,
+    // look for a forced mate in this many moves instead
+    mate: Option<u32>,
+}
+
+fn parse_go(
+    words: &[&str],
+    turn: &Colour,
+    move_overhead: &Duration,
+) -> Result<GoCommand, ProtocolError> {
+    let mut limits = SearchLimits::default();
+    let mut infinite = false;
+    let mut ponder = false;
+    let mut mate = None;
+    let mut time_left = None;
+    let mut increment = Duration::ZERO;
+    let mut moves_to_go = None;
+
+    let mut index = 0;
+    while index < words.len() {
+        let value = words.get(index + 1);
+        let number = || -> Result<u64, ProtocolError> {
+            match value.map(|value| (value, value.parse::<u64>())) {
+                Some((_, Ok(number))) => Ok(number),
+                Some((value, Err(_))) => Err(ProtocolError::InvalidNumber(value.to_string())),
+                None => Err(ProtocolError::MissingArgument(
+                    "a number after a go parameter",
+                )),
+            }
+        };
+        match (words[index], turn) {
+            ("infinite", _) => {
+                infinite = true;
+                index += 1;
+                continue;
+            }
+            ("ponder", _) => {
+                ponder = true;
+                index += 1;
+                continue;
+            }
+            ("depth", _) => limits.depth = Some(number()?.min(u8::MAX as u64) as u8),
+            ("nodes", _) => limits.nodes = Some(number()?),
+            ("movetime", _) => limits.movetime = Some(Duration::from_millis(number()?)),
+            ("mate", _) => mate = Some(number()?.clamp(1, MAX_MATE_MOVES as u64) as u32),
+            ("wtime", Colour::White) | ("btime", Colour::Black) => {
+                time_left = Some(Duration::from_millis(number()?))
+            }
+            ("winc", Colour::White) | ("binc", Colour::Black) => {
+                increment = Duration::from_millis(number()?)
+            }
+            ("movestogo", _) => moves_to_go = Some(number()? as u32),
+            ("wtime" | "btime" | "winc" | "binc", _) => (),
+            (parameter, _) => return Err(ProtocolError::UnknownParameter(parameter.to_string())),
+        }
+        index += 2;
+    }
+
+    // a fixed movetime wins over the clock
+    if let (Some(remaining), None) = (time_left, limits.movetime) {
+        limits.clock = Some(Clock {
+            remaining,
+            increment,
+            moves_to_go,
+            move_overhead: *move_overhead,
+        });
+    }
+    Ok(GoCommand {
+        limits,
+        infinite,
+        ponder,
+        mate,
+    })
+}
+
+// a proven mate is given as the result of a search, with the line in SAN
+// for the GUI's log
+fn search_for_mate(
+    position: &Position,
+    moves: &u32,
+    signals: &SearchSignals,
+    chess960: &bool,
+) -> Option<SearchResult> {
+    let mate = find_mate(position, moves, &None, signals);
+    let Some(line) = mate.line else {
+        if !mate.stopped {
+            println!("info string no forced mate in {moves}");
+        }
+        return None;
+    };
+
+    let score = MATE_SCORE - line.len() as i32;
+    let result = SearchResult {
+        best_move: line.first().copied(),
+        score,
+        depth: line.len().min(u8::MAX as usize) as u8,
+        nodes: mate.nodes,
+        elapsed: mate.elapsed,
+        pv: line.clone(),
+        ponder_move: line.get(1).copied(),
+        lines: vec![SearchLine {
+            score,
+            pv: line.clone(),
+        }],
+    };
+    print_info(position, &result, chess960);
+    println!(
+        "info string mate in {}: {}",
+        line.len().div_ceil(2),
+        line_to_san(position, &line)
+    );
+    Some(result)
+}
+
+fn parse_check(name: &str, value: &str) -> Result<bool, ProtocolError> {
+    match value {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(ProtocolError::InvalidOptionValue(
+            name.to_string(),
+            value.to_string(),
+        )),
+    }
+}
+
+fn parse_position(words: &[&str]) -> Result<Game, ProtocolError> {
+    let moves_index = words.iter().position(|word| *word == "moves");
+    let (setup, moves) = match moves_index {
+        Some(index) => (&words[..index], &words[index + 1..]),
+        None => (words, &[][..]),
+    };
+
+    let position = match setup.first() {
+        Some(&"startpos") => get_starting_position(),
+        Some(&"fen") => fen_to_position(&setup[1..].join(" "))?,
+        _ => {
+            return Err(ProtocolError::MissingArgument(
+                "'startpos' or 'fen' after 'position'",
+            ))
+        }
+    };
+    let mut game = Game::new(&position);
+    for text in moves {
+        let mv = match uci_to_move(game.get_position(), text) {
+            Ok(mv) => mv,
+            Err(error) => return Err(ProtocolError::InvalidMove(text.to_string(), error)),
+        };
+        game.make_move(&mv);
+    }
+    Ok(game)
+}
+
+impl Engine {
+    fn stop_search(&mut self) {
+        self.signals.stop.store(true, Ordering::Relaxed);
+        if let Some(search_thread) = self.search_thread.take() {
+            let _ = search_thread.join();
+        }
+    }
+
+    fn go(&mut self, words: &[&str]) -> Result<(), ProtocolError> {
+        self.stop_search();
+        let GoCommand {
+            limits,
+            infinite,
+            ponder,
+            mate,
+        } = parse_go(words, &self.position.get_turn(), &self.move_overhead)?;
+
+        // a book move needs no search, but one asked to search for a while or
+        // for a mate still does
+        if !infinite && !ponder && mate.is_none() {
+            if let Some(mv) = self.get_book_move() {
+                println!(
+                    "bestmove {}",
+                    move_to_uci(&self.position, &mv, &self.chess960)
+                );
+                return Ok(());
+            }
+        }
+
+        self.signals = Arc::new(SearchSignals::default());
+        self.signals.ponder.store(ponder, Ordering::Relaxed);
+        let signals = Arc::clone(&self.signals);
+        let position = self.position;
+        let history = self.history.clone();
+        let chess960 = self.chess960;
+        let search_options = self.search_options.clone();
+        let table = Arc::clone(&self.table);
+
+        self.search_thread = Some(thread::spawn(move || {
+            let proven_mate =
+                mate.and_then(|moves| search_for_mate(&position, &moves, &signals, &chess960));
+            // without a proven mate, the search goes on as normal to as many
+            // plies as the mate was asked for in, so that there is still a
+            // move to play
+            let limits = match mate {
+                Some(moves) => SearchLimits {
+                    depth: limits
+                        .depth
+                        .or(Some((2 * moves - 1).min(MAX_DEPTH as u32) as u8)),
+                    ..limits
+                },
+                None => limits,
+            };
+            let result = match proven_mate {
+                Some(result) => result,
+                None => search_until_stopped(
+                    &position,
+                    &history,
+                    &limits,
+                    &search_options,
+                    &table,
+                    &signals,
+                    &mut |result| print_info(&position, result, &chess960),
+                ),
+            };
+            // an infinite search must not report its move until told to stop,
+            // nor a ponder search until the opponent has played the move
+            while (infinite || signals.ponder.load(Ordering::Relaxed))
+                && !signals.stop.load(Ordering::Relaxed)
+            {
+                thread::sleep(Duration::from_millis(5));
+            }
+            match (result.best_move, result.ponder_move) {
+                (Some(mv), Some(ponder_move)) => {
+                    let mut next_position = position;
+                    next_position.make_move(&mv);
+                    println!(
+                        "bestmove {} ponder {}",
+                        move_to_uci(&position, &mv, &chess960),
+                        move_to_uci(&next_position, &ponder_move, &chess960)
+                    );
+                }
+                (Some(mv), None) => {
+                    println!("bestmove {}", move_to_uci(&position, &mv, &chess960))
+                }
+                (None, _) => println!("bestmove 0000"),
+            }
+        }));
+        Ok(())
+    }
+
+    fn get_book_move(&mut self) -> Option<Move> {
+        match (&self.book, self.own_book) {
+            (Some(book), true) => {
+                book.choose_move(&self.position, &self.book_selection, &mut self.random)
+            }
+            _ => None,
+        }
+    }
+
+    fn set_option(&mut self, words: &[&str]) -> Result<(), ProtocolError> {
+        let value_index = words.iter().position(|word| *word == "value");
+        let name = match (words.first(), value_index) {
+            (Some(&"name"), Some(index)) => words[1..index].join(" "),
+            (Some(&"name"), None) => words[1..].join(" "),
+            _ => return Err(ProtocolError::MissingArgument("'name' after 'setoption'")),
+        };
+        let value = value_index.map(|index| words[index + 1..].join(" "));
+
+        let value = value.unwrap_or_default();
+        match (name.to_lowercase().as_str(), value.as_str()) {
+            ("uci_chess960", "true") => self.chess960 = true,
+            ("uci_chess960", "false") => self.chess960 = false,
+            ("uci_chess960", _) => return Err(ProtocolError::InvalidOptionValue(name, value)),
+            ("evalparameters", "" | "<empty>") => {
+                self.search_options.parameters = Parameters::default()
+            }
+            ("evalparameters", path) => {
+                let text = fs::read_to_string(path)
+                    .map_err(|_| ProtocolError::InvalidOptionValue(name.clone(), value.clone()))?;
+                self.search_options.parameters = parse_parameters(&text)?;
+            }
+            ("hash", size) => {
+                self.search_options.hash_size = match size.parse() {
+                    Ok(size @ MIN_HASH_SIZE..=MAX_HASH_SIZE) => size,
+                    _ => return Err(ProtocolError::InvalidOptionValue(name, value)),
+                };
+                self.stop_search();
+                self.table = Arc::new(TranspositionTable::new(&self.search_options.hash_size));
+            }
+            // the GUI decides when to ponder, so this only has to be a valid
+            // setting
+            ("ponder", _) => {
+                parse_check(&name, &value)?;
+            }
+            ("threads", count) => {
+                self.search_options.threads = match count.parse() {
+                    Ok(count @ 1..=MAX_THREADS) => count,
+                    _ => return Err(ProtocolError::InvalidOptionValue(name, value)),
+                }
+            }
+            ("multipv", count) => {
+                self.search_options.multi_pv = match count.parse() {
+                    Ok(count @ 1..=MAX_MULTI_PV) => count,
+                    _ => return Err(ProtocolError::InvalidOptionValue(name, value)),
+                }
+            }
+            ("move overhead", milliseconds) => {
+                self.move_overhead = match milliseconds.parse() {
+                    Ok(milliseconds @ 0..=MAX_MOVE_OVERHEAD) => Duration::from_millis(milliseconds),
+                    _ => return Err(ProtocolError::InvalidOptionValue(name, value)),
+                }
+            }
+            ("pvs", _) => {
+                self.search_options.principal_variation_search = parse_check(&name, &value)?
+            }
+            ("aspirationwindows", _) => {
+                self.search_options.aspiration_windows = parse_check(&name, &value)?
+            }
+            ("checkextensions", _) => {
+                self.search_options.check_extensions = parse_check(&name, &value)?
+            }
+            ("singularextensions", _) => {
+                self.search_options.singular_extensions = parse_check(&name, &value)?
+            }
+            ("usennue", "true") => {
+                self.use_network = true;
+                self.update_evaluator()?;
+            }
+            ("usennue", "false") => {
+                self.use_network = false;
+                self.update_evaluator()?;
+            }
+            ("usennue", _) => return Err(ProtocolError::InvalidOptionValue(name, value)),
+            ("evalfile", "" | "<empty>") => {
+                self.network = None;
+                self.update_evaluator()?;
+            }
+            ("evalfile", path) => {
+                let bytes = fs::read(path)
+                    .map_err(|_| ProtocolError::InvalidOptionValue(name.clone(), value.clone()))?;
+                self.network = Some(Arc::new(Network::from_bytes(&bytes)?));
+                self.update_evaluator()?;
+            }
+            ("ownbook", _) => self.own_book = parse_check(&name, &value)?,
+            ("bookfile", "" | "<empty>") => self.book = None,
+            ("bookfile", path) => {
+                let bytes = fs::read(path)
+                    .map_err(|_| ProtocolError::InvalidOptionValue(name.clone(), value.clone()))?;
+                self.book = Some(Book::from_bytes(&bytes)?);
+            }
+            ("bookselection", "Random") => self.book_selection = BookSelection::WeightedRandom,
+            ("bookselection", "Best") => self.book_selection = BookSelection::BestMove,
+            ("bookselection", _) => return Err(ProtocolError::InvalidOptionValue(name, value)),
+            ("syzygypath", "" | "<empty>") => self.search_options.tablebases = None,
+            // missing directories and tables are passed over, so the engine
+            // plays on without them
+            ("syzygypath", paths) => {
+                let tablebases = Tablebases::open(paths);
+                println!(
+                    "info string found {} tablebases of up to {} pieces",
+                    tablebases.len(),
+                    tablebases.get_max_pieces()
+                );
+                self.search_options.tablebases = match tablebases.is_empty() {
+                    true => None,
+                    false => Some(Arc::new(tablebases)),
+                };
+            }
+            _ => return Err(ProtocolError::UnknownOption(name)),
+        }
+        Ok(())
+    }
+
+    // the network is only used once one is loaded and UseNNUE is on, in
+    // whichever order the two options arrive
+    fn update_evaluator(&mut self) -> Result<(), ProtocolError> {
+        self.search_options.evaluator = match (&self.network, self.use_network) {
+            (Some(network), true) => Evaluator::Network(Arc::clone(network)),
+            _ => Evaluator::Handcrafted,
+        };
+        if self.use_network && self.network.is_none() {
+            return Err(ProtocolError::MissingArgument(
+                "a network from the EvalFile option to use NNUE",
+            ));
+        }
+        Ok(())
+    }
+
+    // returns false once the engine should exit
+    fn handle_command(&mut self, line: &str) -> Result<bool, ProtocolError> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let (command, arguments) = match words.split_first() {
+            Some((command, arguments)) => (*command, arguments),
+            None => return Ok(true),
+        };
+
+        match command {
+            "uci" => {
+                println!("id name {ENGINE_NAME}");
+                println!("id author {ENGINE_AUTHOR}");
+                println!("option name UCI_Chess960 type check default false");
+                println!("option name EvalParameters type string default <empty>");
+                println!("option name EvalFile type string default <empty>");
+                println!("option name UseNNUE type check default false");
+                println!("option name OwnBook type check default false");
+                println!("option name BookFile type string default <empty>");
+                println!("option name BookSelection type combo default Random var Random var Best");
+                println!("option name SyzygyPath type string default <empty>");
+                println!(
+                    "option name Hash type spin default {DEFAULT_HASH_SIZE} min {MIN_HASH_SIZE} max {MAX_HASH_SIZE}"
+                );
+                println!("option name Ponder type check default false");
+                println!("option name Threads type spin default 1 min 1 max {MAX_THREADS}");
+                println!("option name MultiPV type spin default 1 min 1 max {MAX_MULTI_PV}");
+                println!(
+                    "option name Move Overhead type spin default {DEFAULT_MOVE_OVERHEAD} min 0 max {MAX_MOVE_OVERHEAD}"
+                );
+                println!("option name PVS type check default true");
+                println!("option name AspirationWindows type check default true");
+                println!("option name CheckExtensions type check default true");
+                println!("option name SingularExtensions type check default true");
+                println!("uciok");
+            }
+            "isready" => println!("readyok"),
+            "setoption" => self.set_option(arguments)?,
+            "ucinewgame" => {
+                self.stop_search();
+                self.table.clear();
+                self.position = get_starting_position();
+                self.history.clear();
+            }
+            "position" => {
+                self.stop_search();
+                let game = parse_position(arguments)?;
+                self.position = *game.get_position();
+                self.history = game.get_history();
+            }
+            "go" => self.go(arguments)?,
+            "stop" => self.stop_search(),
+            // the expected move was played, so the clock is now running
+            "ponderhit" => self.signals.ponder.store(false, Ordering::Relaxed),
+            "quit" => {
+                self.stop_search();
+                return Ok(false);
+            }
+            // not part of UCI, but handy when driving the engine by hand
+            "d" => {
+                let options = RenderOptions {
+                    theme: Theme::Plain,
+                    ..RenderOptions::default()
+                };
+                print_board(&self.position, &0, &0, &[], &options);
+                println!("Fen: {}", position_to_fen(&self.position));
+            }
+            "eval" => {
+                println!(
+                    "{}",
+                    trace_evaluation(&self.position, &self.search_options.parameters)
+                );
+                if let Evaluator::Network(network) = &self.search_options.evaluator {
+                    let score = match self.position.get_turn() {
+                        Colour::White => network.evaluate_position(&self.position),
+                        Colour::Black => -network.evaluate_position(&self.position),
+                    };
+                    println!("Network score {} (from white's side)", format_score(&score));
+                }
+            }
+            _ => return Err(ProtocolError::UnknownCommand(command.to_string())),
+        }
+        Ok(true)
+    }
+}
+
+fn main() {
+    let mut engine = Engine {
+        position: get_starting_position(),
+        history: Vec::new(),
+        chess960: false,
+        search_options: SearchOptions::default(),
+        table: Arc::new(TranspositionTable::new(&DEFAULT_HASH_SIZE)),
+        network: None,
+        use_network: false,
+        move_overhead: Duration::from_millis(DEFAULT_MOVE_OVERHEAD),
+        book: None,
+        own_book: false,
+        book_selection: BookSelection::default(),
+        random: Random::from_clock(),
+        signals: Arc::new(SearchSignals::default()),
+        search_thread: None,
+    };
+
+    for line in io::stdin().lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        match engine.handle_command(line.trim()) {
+            Ok(true) => (),
+            Ok(false) => return,
+            Err(e) => println!("info string {e}"),
+        }
+    }
+    engine.stop_search();
+}
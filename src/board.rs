@@ -1,16 +1,64 @@
-use std::collections::HashSet;
-use std::fmt::format;
-use std::hash::Hash;
-use std::{default, io};
+use std::io;
 
-use crate::move_generation::generate_moves;
+use crate::move_generation::generate_legal_moves;
 use crate::pieces::{Colour, Piece};
-use crate::position::{Position, DARK_SQUARES, LIGHT_SQUARES};
-use crate::utils::{algebraic_to_index, bit_scan, index_to_bitboard};
+use crate::position::{Position, DARK_SQUARES};
+use crate::utils::{bit_scan, index_to_bitboard};
 
 pub const FILES: [char; 8] = ['a', 'b', 'c', 'd', 'e', 'f', 'g', 'h'];
 pub const RANKS: [char; 8] = ['1', '2', '3', '4', '5', '6', '7', '8'];
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Theme {
+    Brown,
+    Blue,
+    // for terminals without true colour support
+    Ansi16,
+    // no escape codes at all, for logs and files
+    Plain,
+}
+
+impl Theme {
+    pub fn iter() -> &'static [Theme] {
+        &[Theme::Brown, Theme::Blue, Theme::Ansi16, Theme::Plain]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Theme::Brown => "brown",
+            Theme::Blue => "blue",
+            Theme::Ansi16 => "16",
+            Theme::Plain => "plain",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Theme> {
+        Theme::iter()
+            .iter()
+            .find(|theme| theme.name() == name)
+            .copied()
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct RenderOptions {
+    pub flipped: bool,
+    pub theme: Theme,
+    pub unicode_pieces: bool,
+    pub show_coordinates: bool,
+}
+
+impl Default for RenderOptions {
+    fn default() -> RenderOptions {
+        RenderOptions {
+            flipped: false,
+            theme: Theme::Brown,
+            unicode_pieces: false,
+            show_coordinates: true,
+        }
+    }
+}
+
 enum SquareShade {
     Dark,
     Light,
@@ -44,60 +92,132 @@ fn determine_square_highlight(
 }
 
 fn determine_square_colour(
-    square: &u64,
-    selected_square: &u64,
-    last_moved_squares: &u64,
+    square_highlight: &SquareHighlight,
+    square_shade: &SquareShade,
+    theme: &Theme,
 ) -> &'static str {
-    let square_highlight: SquareHighlight =
-        determine_square_highlight(square, selected_square, last_moved_squares);
-    let square_shade: SquareShade = determine_square_shade(square);
+    match (theme, square_highlight, square_shade) {
+        (Theme::Brown, SquareHighlight::Selected, SquareShade::Dark) => "\x1b[48;2;100;110;64m",
+        (Theme::Brown, SquareHighlight::Selected, SquareShade::Light) => "\x1b[48;2;130;151;105m",
+        (Theme::Brown, SquareHighlight::LastMoved, SquareShade::Dark) => "\x1b[48;2;170;162;58m",
+        (Theme::Brown, SquareHighlight::LastMoved, SquareShade::Light) => "\x1b[48;2;205;210;106m",
+        (Theme::Brown, SquareHighlight::Default, SquareShade::Dark) => "\x1b[48;2;181;136;99m",
+        (Theme::Brown, SquareHighlight::Default, SquareShade::Light) => "\x1b[48;2;240;217;181m",
+        (Theme::Blue, SquareHighlight::Selected, SquareShade::Dark) => "\x1b[48;2;86;125;70m",
+        (Theme::Blue, SquareHighlight::Selected, SquareShade::Light) => "\x1b[48;2;123;158;102m",
+        (Theme::Blue, SquareHighlight::LastMoved, SquareShade::Dark) => "\x1b[48;2;147;177;96m",
+        (Theme::Blue, SquareHighlight::LastMoved, SquareShade::Light) => "\x1b[48;2;195;216;135m",
+        (Theme::Blue, SquareHighlight::Default, SquareShade::Dark) => "\x1b[48;2;140;162;173m",
+        (Theme::Blue, SquareHighlight::Default, SquareShade::Light) => "\x1b[48;2;222;227;230m",
+        // black text so the pieces stay readable on the light backgrounds
+        (Theme::Ansi16, SquareHighlight::Selected, SquareShade::Dark) => "\x1b[30;42m",
+        (Theme::Ansi16, SquareHighlight::Selected, SquareShade::Light) => "\x1b[30;102m",
+        (Theme::Ansi16, SquareHighlight::LastMoved, SquareShade::Dark) => "\x1b[30;46m",
+        (Theme::Ansi16, SquareHighlight::LastMoved, SquareShade::Light) => "\x1b[30;106m",
+        (Theme::Ansi16, SquareHighlight::Default, SquareShade::Dark) => "\x1b[30;43m",
+        (Theme::Ansi16, SquareHighlight::Default, SquareShade::Light) => "\x1b[30;47m",
+        (Theme::Plain, _, _) => "",
+    }
+}
 
-    match (square_highlight, square_shade) {
-        (SquareHighlight::Selected, SquareShade::Dark) => "\x1b[48;2;100;110;64m",
-        (SquareHighlight::Selected, SquareShade::Light) => "\x1b[48;2;130;151;105m",
-        (SquareHighlight::LastMoved, SquareShade::Dark) => "\x1b[48;2;170;162;58m",
-        (SquareHighlight::LastMoved, SquareShade::Light) => "\x1b[48;2;205;210;106m",
-        (SquareHighlight::Default, SquareShade::Dark) => "\x1b[48;2;181;136;99m",
-        (SquareHighlight::Default, SquareShade::Light) => "\x1b[48;2;240;217;181m",
+fn determine_move_marker_colour(theme: &Theme) -> &'static str {
+    match theme {
+        Theme::Brown => "\x1b[38;2;100;110;64m",
+        Theme::Blue => "\x1b[38;2;60;90;120m",
+        Theme::Ansi16 => "\x1b[32m",
+        Theme::Plain => "",
     }
 }
 
-fn determine_piece_str(piece_str: &str, square: &u64, move_squares: &u64) -> String {
+fn determine_piece_str(piece_str: &str, square: &u64, move_squares: &u64, theme: &Theme) -> String {
     let is_a_move_square = square & move_squares != 0;
     let is_piece_on_square = piece_str != " ";
+    let marker_colour = determine_move_marker_colour(theme);
 
     match (is_a_move_square, is_piece_on_square) {
-        (true, true) => format!("\x1b[38;2;100;110;64m[{piece_str}]\x1b[0m"),
-        (true, false) => "\x1b[38;2;100;110;64m ● \x1b[0m".to_string(),
+        (true, true) => format!("{marker_colour}[{piece_str}]\x1b[0m"),
+        (true, false) => format!("{marker_colour} ● \x1b[0m"),
         (false, true) => format!(" {piece_str} "),
         (false, false) => "   ".to_string(),
     }
 }
 
-pub fn print_board(position: &Position, selected_square: &u64, move_squares: &u64) {
+// without colours the highlights are drawn around the piece instead
+fn determine_plain_square_str(
+    piece_str: &str,
+    square_highlight: &SquareHighlight,
+    is_a_move_square: &bool,
+) -> String {
+    let is_piece_on_square = piece_str != " ";
+    let piece_str = match is_piece_on_square {
+        true => piece_str,
+        false => ".",
+    };
+    match (square_highlight, is_a_move_square, is_piece_on_square) {
+        (_, true, true) => format!("[{piece_str}]"),
+        (_, true, false) => " * ".to_string(),
+        (SquareHighlight::Selected, false, _) => format!("<{piece_str}>"),
+        (SquareHighlight::LastMoved, false, _) => format!("({piece_str})"),
+        (SquareHighlight::Default, false, _) => format!(" {piece_str} "),
+    }
+}
+
+// the window of the move list shown beside the board, kept on the current ply
+fn get_visible_move_list(move_list: &[String]) -> &[String] {
+    let focus = move_list
+        .iter()
+        .position(|line| line.contains('['))
+        .unwrap_or(move_list.len().saturating_sub(1));
+    let start = (focus + 1).saturating_sub(RANKS.len());
+    let end = move_list.len().min(start + RANKS.len());
+    &move_list[start..end]
+}
+
+pub fn print_board(
+    position: &Position,
+    selected_square: &u64,
+    move_squares: &u64,
+    move_list: &[String],
+    options: &RenderOptions,
+) {
     let mut board = [" "; 64];
+    let visible_move_list = get_visible_move_list(move_list);
 
     for piece in Piece::iter() {
         let bitboard = &position.get_bitboard(piece);
-        fill_board(&mut board, bitboard, piece);
+        fill_board(&mut board, bitboard, piece, &options.unicode_pieces);
     }
 
-    // clear terminal using special char
+    // clear terminal using special char, unless writing plain text for logs
     // https://rosettacode.org/wiki/Terminal_control/Clear_the_screen#Rust
-    print!("{}[2J", 27 as char);
+    if options.theme != Theme::Plain {
+        print!("{}[2J", 27 as char);
+    }
+
+    // seen from black's side both the ranks and the files run backwards
+    let mut files = FILES;
+    let mut ranks = RANKS;
+    match options.flipped {
+        true => files.reverse(),
+        false => ranks.reverse(),
+    }
 
     // offset to get file names aligned
-    print!("\n     ");
-    for file in FILES {
-        print!(" {file} ")
+    if options.show_coordinates {
+        print!("\n     ");
+        for file in files {
+            print!(" {file} ")
+        }
+        println!();
     }
-    println!();
 
-    for rank in RANKS.iter().rev() {
-        print!("\n{rank}    ");
-        for file in FILES {
-            let algebraic: String = format!("{}{}", file, rank);
-            let index = algebraic_to_index(&algebraic).unwrap();
+    for (row, rank) in ranks.iter().enumerate() {
+        match options.show_coordinates {
+            true => print!("\n{rank}    "),
+            false => print!("\n     "),
+        }
+        for file in files {
+            let index = (*rank as u8 - b'1') as usize * 8 + (file as u8 - b'a') as usize;
             let current_square = index_to_bitboard(&index);
 
             // second answer to this s/o
@@ -105,16 +225,33 @@ pub fn print_board(position: &Position, selected_square: &u64, move_squares: &u6
             // https://en.wikipedia.org/wiki/ANSI_escape_code#Colors
             let ansi_closing = "\x1b[0m";
 
-            let bg_colour_ansi = determine_square_colour(
+            let square_highlight = determine_square_highlight(
                 &current_square,
                 selected_square,
                 &position.last_moved_squares,
             );
-            let piece_str = determine_piece_str(board[index], &current_square, move_squares);
+            if options.theme == Theme::Plain {
+                let is_a_move_square = current_square & move_squares != 0;
+                let display_string =
+                    determine_plain_square_str(board[index], &square_highlight, &is_a_move_square);
+                print!("{}", display_string);
+                continue;
+            }
+
+            let bg_colour_ansi = determine_square_colour(
+                &square_highlight,
+                &determine_square_shade(&current_square),
+                &options.theme,
+            );
+            let piece_str =
+                determine_piece_str(board[index], &current_square, move_squares, &options.theme);
 
             let display_string = format!("{bg_colour_ansi}{piece_str}{ansi_closing}");
             print!("{}", display_string);
         }
+        if let Some(line) = visible_move_list.get(row) {
+            print!("    {line}");
+        }
     }
     println!();
     println!();
@@ -122,83 +259,40 @@ pub fn print_board(position: &Position, selected_square: &u64, move_squares: &u6
         Colour::White => "WHITE",
         Colour::Black => "BLACK",
     };
-    println!("           {to_move} TO MOVE");
+    let winner = match position.turn {
+        Colour::White => "BLACK",
+        Colour::Black => "WHITE",
+    };
+    if !generate_legal_moves(position).is_empty() {
+        println!("           {to_move} TO MOVE");
+    } else if position.is_in_check(&position.turn) {
+        println!("        CHECKMATE, {winner} WINS");
+    } else {
+        println!("             STALEMATE");
+    }
     println!();
 }
 
-pub fn fill_board(board: &mut [&str; 64], bitboard: &u64, piece: &Piece) {
+pub fn fill_board(board: &mut [&str; 64], bitboard: &u64, piece: &Piece, unicode_pieces: &bool) {
     let indicies_to_fill = bit_scan(bitboard);
     for index in indicies_to_fill {
-        board[index] = piece.str();
+        board[index] = match unicode_pieces {
+            true => piece.glyph(),
+            false => piece.str(),
+        };
     }
 }
 
-pub fn get_input(prompt: &str) -> String {
+// returns None once input has run out, e.g. at the end of a piped script, or
+// can no longer be read
+pub fn get_input(prompt: &str) -> Option<String> {
     let mut input: String;
     println!("{}", prompt);
     input = String::new();
 
-    io::stdin()
-        .read_line(&mut input)
-        .expect("Failed to read input");
-    input.trim().to_string()
-}
-
-pub fn ask_for_piece_selection(position: &mut Position) {
-    loop {
-        let input = get_input("Select a piece to move");
-
-        match algebraic_to_index(&input) {
-            Ok(index) => match position.get_piece_with_colour_at(&index_to_bitboard(&index), &position.turn) {
-                Some(piece) => {
-                    let square: u64 = 1 << index;
-                    let moves: u64 = generate_moves(position, &square).clone();
-
-                    print_board(position, &square, &moves);
-                    ask_for_move(position, &square, &moves);
-
-                    break;
-                }
-                None => {
-                    print_board(position, &0, &0);
-                    println!("No piece on square {}", &input);
-                }
-            },
-            Err(e) => {
-                print_board(position, &0, &0);
-                println!("{e}");
-            }
-        }
-    }
-}
-
-pub fn ask_for_move(position: &mut Position, root_square: &u64, valid_moves: &u64) {
-    loop {
-        let input = get_input("Which square do you want to move it to? ('q' to cancel)");
-
-        if input == "q" {
-            print_board(position, &0, &0);
-            ask_for_piece_selection(position);
-        }
-
-        match algebraic_to_index(&input) {
-            Ok(index) => {
-                let square: u64 = index_to_bitboard(&index);
-                if square & valid_moves != 0 {
-                    position.move_piece(root_square, &square);
-
-                    print_board(position, &0, &0);
-                    ask_for_piece_selection(position);
-
-                    break;
-                } else {
-                    print_board(position, root_square, valid_moves);
-                    println!("'{}' is not a valid move.", &input);
-                }
-            }
-            Err(e) => {
-                print_board(position, root_square, valid_moves);
-            }
-        }
+    match io::stdin().read_line(&mut input) {
+        Ok(0) | Err(_) => return None,
+        Ok(_) => (),
     }
+    Some(input.trim().to_string())
 }
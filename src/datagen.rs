@@ -0,0 +1,362 @@
+use crate::game::count_repetitions;
+use crate::move_generation::generate_legal_moves;
+use crate::notation::position_to_fen;
+use crate::pieces::{Class, Colour, Piece};
+use crate::position::{get_empty_position, get_starting_position, Position};
+use crate::random::Random;
+use crate::search::{is_capture, search, SearchLimits, SearchOptions, MATE_THRESHOLD};
+use crate::utils::{bitboard_to_index, index_to_bitboard, pop_lsb};
+use crate::zobrist::get_hash;
+
+// every record takes this many bytes in the binary format:
+//   u64        occupied squares
+//   16 bytes   a nibble for each occupied square from a1 up, the index of
+//              the piece in Piece::iter, or 12 and 13 for a white or black
+//              rook that can still castle
+//   u8         1 if black is to move
+//   u8         en passant square, or 64 for none
+//   u8         halfmove clock
+//   u16        fullmove number
+//   i16        search score in centipawns from white's side
+//   u8         result, 0 for a black win, 1 for a draw and 2 for a white win
+// with numbers little endian
+pub const RECORD_SIZE: usize = 32;
+const WHITE_CASTLING_ROOK: u8 = 12;
+const BLACK_CASTLING_ROOK: u8 = 13;
+const NO_EN_PASSANT_SQUARE: u8 = 64;
+
+// a game is given to the side this far ahead for this many plies in a row
+const WIN_ADJUDICATION_SCORE: i32 = 2000;
+const WIN_ADJUDICATION_PLIES: u32 = 6;
+const MAX_GAME_PLIES: usize = 400;
+// openings that leave one side this far ahead are thrown away
+const MAX_OPENING_SCORE: i32 = 400;
+
+pub struct TrainingRecord {
+    pub position: Position,
+    // from white's side, like the result
+    pub score: i32,
+    // 1 for a white win, 0.5 for a draw and 0 for a black win
+    pub result: f64,
+}
+
+pub struct DatagenSettings {
+    pub nodes: u64,
+    pub random_plies: usize,
+}
+
+// None only for a position with more than the 32 pieces a game can have
+pub fn encode_record(record: &TrainingRecord) -> Option<[u8; RECORD_SIZE]> {
+    let position = &record.position;
+    let occupancy = position.get_occupancy();
+    if occupancy.count_ones() > 32 {
+        return None;
+    }
+
+    let mut bytes = [0; RECORD_SIZE];
+    bytes[0..8].copy_from_slice(&occupancy.to_le_bytes());
+    let mut squares = occupancy;
+    let mut count = 0;
+    while squares != 0 {
+        let square = pop_lsb(&mut squares);
+        let piece = position.get_piece_at(&square)?;
+        let code = match square & position.castling_rights != 0 {
+            true => match piece.colour() {
+                Colour::White => WHITE_CASTLING_ROOK,
+                Colour::Black => BLACK_CASTLING_ROOK,
+            },
+            false => Piece::iter().iter().position(|other| other == piece)? as u8,
+        };
+        bytes[8 + count / 2] |= code << (4 * (count % 2));
+        count += 1;
+    }
+
+    bytes[24] = (position.turn == Colour::Black) as u8;
+    bytes[25] = match position.en_passant_square {
+        0 => NO_EN_PASSANT_SQUARE,
+        square => bitboard_to_index(&square) as u8,
+    };
+    bytes[26] = position.halfmove_clock.min(u8::MAX as u32) as u8;
+    bytes[27..29]
+        .copy_from_slice(&(position.fullmove_number.min(u16::MAX as u32) as u16).to_le_bytes());
+    let score = record.score.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+    bytes[29..31].copy_from_slice(&score.to_le_bytes());
+    bytes[31] = (record.result * 2.0).round() as u8;
+    Some(bytes)
+}
+
+// None if the bytes do not make up a valid position
+pub fn decode_record(bytes: &[u8; RECORD_SIZE]) -> Option<TrainingRecord> {
+    let mut position = get_empty_position();
+    let occupancy = u64::from_le_bytes(bytes[0..8].try_into().ok()?);
+    if occupancy.count_ones() > 32 {
+        return None;
+    }
+    let mut squares = occupancy;
+    let mut count = 0;
+    while squares != 0 {
+        let square = pop_lsb(&mut squares);
+        let code = (bytes[8 + count / 2] >> (4 * (count % 2))) & 0b1111;
+        let piece = match code {
+            WHITE_CASTLING_ROOK => Piece::WhiteRook,
+            BLACK_CASTLING_ROOK => Piece::BlackRook,
+            _ => *Piece::iter().get(code as usize)?,
+        };
+        if code == WHITE_CASTLING_ROOK || code == BLACK_CASTLING_ROOK {
+            position.castling_rights |= square;
+        }
+        position.insert_piece_at_square(&piece, &square);
+        count += 1;
+    }
+
+    position.turn = match bytes[24] {
+        0 => Colour::White,
+        1 => Colour::Black,
+        _ => return None,
+    };
+    position.en_passant_square = match bytes[25] {
+        NO_EN_PASSANT_SQUARE => 0b0,
+        index @ 0..=63 => index_to_bitboard(&(index as usize)),
+        _ => return None,
+    };
+    position.halfmove_clock = bytes[26] as u32;
+    position.fullmove_number = u16::from_le_bytes([bytes[27], bytes[28]]) as u32;
+    position.validate().ok()?;
+
+    let result = match bytes[31] {
+        0 => 0.0,
+        1 => 0.5,
+        2 => 1.0,
+        _ => return None,
+    };
+    Some(TrainingRecord {
+        position,
+        score: i16::from_le_bytes([bytes[29], bytes[30]]) as i32,
+        result,
+    })
+}
+
+// 'FEN | score | result', which the tuner also reads
+pub fn format_record(record: &TrainingRecord) -> String {
+    format!(
+        "{} | {} | {:.1}",
+        position_to_fen(&record.position),
+        record.score,
+        record.result
+    )
+}
+
+// kings alone, or with a single knight or bishop between them
+fn is_insufficient_material(position: &Position) -> bool {
+    let has_pawns_or_heavy_pieces = Piece::iter().iter().any(|piece| {
+        matches!(piece.class(), Class::Pawn | Class::Rook | Class::Queen)
+            && position.get_bitboard(piece) != 0
+    });
+    !has_pawns_or_heavy_pieces && position.get_occupancy().count_ones() <= 3
+}
+
+// random moves from the starting position, tried again until they give a
+// position that is still playable and not already lost for either side
+fn play_random_opening(
+    random: &mut Random,
+    settings: &DatagenSettings,
+    search_options: &SearchOptions,
+) -> Position {
+    loop {
+        let mut position = get_starting_position();
+        for _ in 0..settings.random_plies {
+            let moves = generate_legal_moves(&position);
+            if moves.is_empty() {
+                break;
+            }
+            position.make_move(&moves[random.below(&moves.len())]);
+        }
+        if generate_legal_moves(&position).is_empty() {
+            continue;
+        }
+        let limits = SearchLimits {
+            nodes: Some(settings.nodes),
+            ..SearchLimits::default()
+        };
+        if search(&position, &[], &limits, search_options).score.abs() <= MAX_OPENING_SCORE {
+            return position;
+        }
+    }
+}
+
+// plays one game of the engine against itself and returns the quiet
+// positions from it, labelled with the final result
+pub fn play_game(
+    random: &mut Random,
+    settings: &DatagenSettings,
+    search_options: &SearchOptions,
+) -> Vec<TrainingRecord> {
+    let limits = SearchLimits {
+        nodes: Some(settings.nodes),
+        ..SearchLimits::default()
+    };
+    let mut position = play_random_opening(random, settings, search_options);
+    // hashes of the positions played so far, for spotting repetitions
+    let mut history = Vec::new();
+    let mut records = Vec::new();
+    let mut winning_plies: i32 = 0;
+
+    let result = loop {
+        let moves = generate_legal_moves(&position);
+        let in_check = position.is_in_check(&position.turn);
+        if moves.is_empty() {
+            break match (in_check, position.turn) {
+                (true, Colour::White) => 0.0,
+                (true, Colour::Black) => 1.0,
+                (false, _) => 0.5,
+            };
+        }
+        let hash = get_hash(&position);
+        let is_threefold_repetition =
+            count_repetitions(&history, &hash, &(position.halfmove_clock as usize)) >= 2;
+        if position.halfmove_clock >= 100
+            || history.len() >= MAX_GAME_PLIES
+            || is_insufficient_material(&position)
+            || is_threefold_repetition
+        {
+            break 0.5;
+        }
+
+        let search_result = search(&position, &history, &limits, search_options);
+        let Some(best_move) = search_result.best_move else {
+            break 0.5;
+        };
+        let white_score = match position.turn {
+            Colour::White => search_result.score,
+            Colour::Black => -search_result.score,
+        };
+
+        // a position only teaches the evaluation something if the score
+        // does not hinge on a capture, check or mate about to happen
+        let is_quiet = !in_check
+            && !is_capture(&position, &best_move)
+            && best_move.promotion.is_none()
+            && search_result.score.abs() < MATE_THRESHOLD;
+        if is_quiet {
+            records.push(TrainingRecord {
+                position,
+                score: white_score,
+                result: 0.0,
+            });
+        }
+
+        // counted up while white stays well ahead and down for black
+        winning_plies = match white_score {
+            score if score >= WIN_ADJUDICATION_SCORE => winning_plies.max(0) + 1,
+            score if score <= -WIN_ADJUDICATION_SCORE => winning_plies.min(0) - 1,
+            _ => 0,
+        };
+        if winning_plies.unsigned_abs() >= WIN_ADJUDICATION_PLIES {
+            break match winning_plies > 0 {
+                true => 1.0,
+                false => 0.0,
+            };
+        }
+
+        history.push(hash);
+        position.make_move(&best_move);
+    };
+
+    for record in &mut records {
+        record.result = result;
+    }
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::fen_to_position;
+
+    fn assert_round_trip(fen: &str, score: i32, decoded_score: i32) {
+        let position = fen_to_position(fen).unwrap();
+        for result in [0.0, 0.5, 1.0] {
+            let record = TrainingRecord {
+                position,
+                score,
+                result,
+            };
+            let decoded = decode_record(&encode_record(&record).unwrap()).unwrap();
+            assert_eq!(
+                position_to_fen(&decoded.position),
+                position_to_fen(&position)
+            );
+            assert_eq!(decoded.position.castling_rights, position.castling_rights);
+            assert_eq!(
+                decoded.position.en_passant_square,
+                position.en_passant_square
+            );
+            assert_eq!(decoded.position.turn, position.turn);
+            assert_eq!(decoded.score, decoded_score);
+            assert_eq!(decoded.result, result);
+        }
+    }
+
+    #[test]
+    fn records_round_trip() {
+        assert_round_trip(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            25,
+            25,
+        );
+        assert_round_trip("r3k2r/8/8/8/8/8/8/R3K2R w Kq - 7 30", -130, -130);
+        // chess960 rooks away from the corners, with one of them spent
+        assert_round_trip("1r2k1r1/8/8/8/8/8/8/1R2K1R1 w GBgb - 0 1", 0, 0);
+        assert_round_trip("1r2k1r1/8/8/8/8/8/8/1R2K1R1 b Bg - 3 12", 0, 0);
+        assert_round_trip(
+            "rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 3",
+            -40,
+            -40,
+        );
+        assert_round_trip(
+            "rnbqkbnr/ppp2ppp/4p3/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3",
+            60,
+            60,
+        );
+        assert_round_trip("6k1/8/8/8/8/8/8/QQQ3K1 b - - 0 70", 100000, i16::MAX as i32);
+        assert_round_trip(
+            "6k1/8/8/8/8/8/8/qqq3K1 w - - 0 70",
+            -100000,
+            i16::MIN as i32,
+        );
+    }
+
+    #[test]
+    fn bad_bytes_are_rejected() {
+        let record = TrainingRecord {
+            position: get_starting_position(),
+            score: 0,
+            result: 0.5,
+        };
+        let bytes = encode_record(&record).unwrap();
+        assert!(decode_record(&bytes).is_some());
+
+        let corrupt = |index: usize, byte: u8| {
+            let mut bad_bytes = bytes;
+            bad_bytes[index] = byte;
+            assert!(
+                decode_record(&bad_bytes).is_none(),
+                "byte {} as {}",
+                index,
+                byte
+            );
+        };
+        // more than 32 occupied squares
+        corrupt(2, 0xff);
+        // piece codes past the castling rooks
+        corrupt(8, 0x0e);
+        corrupt(8, 0xf0);
+        // white pawns on e1 and f1 leave white without its king
+        corrupt(10, 0x00);
+        corrupt(24, 2);
+        corrupt(25, 65);
+        // an en passant square on e3 with white to move
+        corrupt(25, 20);
+        corrupt(31, 3);
+    }
+}
@@ -0,0 +1,140 @@
+use chess_engine::notation::{castling_rights_from_fen, position_to_fen, square_from_algebraic};
+use chess_engine::position::{get_empty_position, get_starting_position, Position, RANK_1, RANK_8};
+use chess_engine::{get_input, print_board, Class, Colour, Piece, RenderOptions};
+
+const HELP: &str = "Board editor commands:
+  add PIECE SQUARE   put a piece on a square, replacing what was there,
+                     e.g. 'add N f3' for a white knight or 'add q d8' for a
+                     black queen
+  remove SQUARE      take the piece off a square
+  clear              empty the board
+  start              set up the starting position
+  turn COLOUR        set the side to move, 'white' or 'black'
+  castling RIGHTS    set the castling rights as in a FEN, e.g. 'KQkq' or '-'
+  enpassant SQUARE   set the en passant square, or '-' for none
+  fen                show the FEN of the position
+  done               start playing from the position
+  cancel             leave the editor without changing the game
+  help               show this message";
+
+enum EditorOutcome {
+    Continue,
+    Done,
+    Cancel,
+}
+
+pub struct Editor {
+    position: Position,
+    render_options: RenderOptions,
+    messages: Vec<String>,
+}
+
+fn parse_square(algebraic: &str) -> Result<u64, String> {
+    square_from_algebraic(algebraic).map_err(|e| e.to_string())
+}
+
+impl Editor {
+    pub fn new(position: &Position, render_options: &RenderOptions) -> Editor {
+        let mut position = *position;
+        position.clear_last_moved_squares();
+        Editor {
+            position,
+            render_options: *render_options,
+            messages: Vec::new(),
+        }
+    }
+
+    // returns the edited position, or None if editing was cancelled
+    pub fn run(&mut self) -> Option<Position> {
+        self.show();
+        while let Some(input) = get_input("Edit the board ('help' for a list, 'done' to play)") {
+            match self.execute(&input) {
+                Ok(EditorOutcome::Continue) => (),
+                Ok(EditorOutcome::Done) => return Some(self.position),
+                Ok(EditorOutcome::Cancel) => return None,
+                Err(e) => self.messages.push(e),
+            }
+            self.show();
+        }
+        None
+    }
+
+    fn show(&mut self) {
+        print_board(&self.position, &0, &0, &[], &self.render_options);
+        println!("EDITING BOARD");
+        if let Err(problems) = self.position.validate() {
+            println!("Not yet playable:");
+            for problem in problems {
+                println!("  {problem}");
+            }
+        }
+        for message in self.messages.drain(..) {
+            println!("{message}");
+        }
+    }
+
+    fn execute(&mut self, input: &str) -> Result<EditorOutcome, String> {
+        let words: Vec<&str> = input.split_whitespace().collect();
+        match words.as_slice() {
+            [] => (),
+            ["help" | "h" | "?"] => self.messages.push(HELP.to_string()),
+            ["add" | "a", piece, square] => {
+                let piece = Piece::iter()
+                    .iter()
+                    .find(|other| other.str() == *piece)
+                    .ok_or(format!(
+                        "Unknown piece '{piece}', use 'PNBRQK' for white or 'pnbrqk' for black."
+                    ))?;
+                let square = parse_square(square)?;
+                self.clear_square(&square);
+                self.position.insert_piece_at_square(piece, &square);
+            }
+            ["remove" | "r", square] => {
+                let square = parse_square(square)?;
+                self.clear_square(&square);
+            }
+            ["clear"] => self.position = get_empty_position(),
+            ["start"] => self.position = get_starting_position(),
+            ["turn", colour] => {
+                let turn = match *colour {
+                    "white" | "w" => Colour::White,
+                    "black" | "b" => Colour::Black,
+                    _ => return Err("Usage: turn white|black".to_string()),
+                };
+                self.position.set_turn(&turn);
+            }
+            ["castling", rights] => {
+                let castling_rights =
+                    castling_rights_from_fen(&self.position, rights).map_err(|e| e.to_string())?;
+                self.position.set_castling_rights(&castling_rights);
+            }
+            ["enpassant", "-"] => self.position.set_en_passant_square(&0b0),
+            ["enpassant", square] => self.position.set_en_passant_square(&parse_square(square)?),
+            ["fen"] => self.messages.push(position_to_fen(&self.position)),
+            ["done"] => {
+                if self.position.validate().is_err() {
+                    return Err("Fix the problems listed above before playing.".to_string());
+                }
+                self.position.set_halfmove_clock(&0);
+                return Ok(EditorOutcome::Done);
+            }
+            ["cancel"] => return Ok(EditorOutcome::Cancel),
+            [command, ..] => return Err(format!("Unknown command '{command}', try 'help'.")),
+        }
+        Ok(EditorOutcome::Continue)
+    }
+
+    // a rook or king that is taken off the board loses its castling rights
+    fn clear_square(&mut self, square: &u64) {
+        let back_rank = match self.position.get_piece_at(square) {
+            Some(piece) if piece.class() == Class::King => match piece.colour() {
+                Colour::White => RANK_1,
+                Colour::Black => RANK_8,
+            },
+            _ => 0b0,
+        };
+        self.position.remove_piece_at_square(square);
+        let castling_rights = self.position.get_castling_rights() & !(square | back_rank);
+        self.position.set_castling_rights(&castling_rights);
+    }
+}
@@ -0,0 +1,980 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::mem;
+use std::path::Path;
+use std::thread;
+
+use crate::errors::EndgameTableError;
+use crate::move_generation::{generate_attacks, generate_legal_moves};
+use crate::pieces::{Class, Colour, Piece};
+use crate::position::{get_empty_position, Position, RANK_1, RANK_2, RANK_7, RANK_8};
+use crate::search::{is_capture, MATE_SCORE};
+use crate::utils::{bitboard_to_index, pop_lsb};
+
+// distance to mate tables for endings of up to four pieces, worked out here by
+// retrograde analysis: starting from the mates, each pass finds the positions
+// one ply further from them, until all that is left is drawn. a table is
+// named after its material, strongest side first, like KBNvK, and its file
+// holds:
+//   4 bytes   magic
+//   u8        number of pieces
+//   u8        each piece, by its place in Piece::iter(), white's king and
+//             pieces strongest first, then black's
+//   then a byte for each position, either side to move, with the white king
+//   mirrored onto the a1-d1-d4 triangle, or the a to d files with pawns, and
+//   0 for a draw, or otherwise one more than the plies to mate. an odd number
+//   of plies is a win for the side to move and an even number a loss
+//
+// the fifty move rule is left out, as is castling. en passant is too, which
+// only leaves out tables with pawns for both sides
+const MAGIC: [u8; 4] = *b"DTM1";
+
+pub const MAX_PIECES: usize = 4;
+// mates are stored as a byte, one more than their plies
+const MAX_VALUE: u32 = u8::MAX as u32;
+
+// while generating, a position that cannot be lost however many of its moves
+// turn out to lose, for being stalemate, illegal or able to reach a draw
+const NEVER_LOST: u8 = u8::MAX;
+// a pair of king squares the table does not store, being mirrored or illegal
+const NO_KING_PAIR: u16 = u16::MAX;
+
+const PROMOTIONS: [Class; 4] = [Class::Queen, Class::Rook, Class::Bishop, Class::Knight];
+
+// the result for the side to move, with the plies it takes to mate
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dtm {
+    Win(u32),
+    Draw,
+    Loss(u32),
+}
+
+impl Dtm {
+    fn from_value(value: &u8) -> Dtm {
+        match *value as u32 {
+            0 => Dtm::Draw,
+            value if value % 2 == 0 => Dtm::Win(value - 1),
+            value => Dtm::Loss(value - 1),
+        }
+    }
+
+    // as the search scores it, ply moves from its root
+    pub fn get_score(&self, ply: &u32) -> i32 {
+        match self {
+            Dtm::Win(plies) => MATE_SCORE - (ply + plies) as i32,
+            Dtm::Draw => 0,
+            Dtm::Loss(plies) => -MATE_SCORE + (ply + plies) as i32,
+        }
+    }
+}
+
+fn get_colour_index(colour: &Colour) -> usize {
+    match colour {
+        Colour::White => 0,
+        Colour::Black => 1,
+    }
+}
+
+// the pieces of a table, strongest side first and as white, each side
+// strongest piece first, and whether the colours were swapped to get there
+fn normalise_material(white: &[Class], black: &[Class]) -> (Vec<Piece>, bool) {
+    let sort = |classes: &[Class]| {
+        let mut classes = classes.to_vec();
+        classes.sort_by(|a, b| b.cmp(a));
+        classes
+    };
+    let (white, black) = (sort(white), sort(black));
+    let is_flipped = (black.len(), &black).cmp(&(white.len(), &white)) == Ordering::Greater;
+    let (strong, weak) = match is_flipped {
+        true => (black, white),
+        false => (white, black),
+    };
+    let pieces = strong
+        .iter()
+        .map(|class| Piece::new(class, &Colour::White))
+        .chain(weak.iter().map(|class| Piece::new(class, &Colour::Black)))
+        .collect();
+    (pieces, is_flipped)
+}
+
+fn get_side_classes(pieces: &[Piece], colour: &Colour) -> Vec<Class> {
+    pieces
+        .iter()
+        .filter(|piece| piece.colour() == *colour)
+        .map(|piece| piece.class())
+        .collect()
+}
+
+fn normalise_pieces(pieces: &[Piece]) -> Vec<Piece> {
+    let white = get_side_classes(pieces, &Colour::White);
+    let black = get_side_classes(pieces, &Colour::Black);
+    normalise_material(&white, &black).0
+}
+
+pub fn get_table_name(pieces: &[Piece]) -> String {
+    let get_side_name = |colour: &Colour| {
+        get_side_classes(pieces, colour)
+            .iter()
+            .map(|class| class.str())
+            .collect::<String>()
+    };
+    format!(
+        "{}v{}",
+        get_side_name(&Colour::White),
+        get_side_name(&Colour::Black)
+    )
+}
+
+// e.g. "KBNvK", in either order, or None if the name is not of a table that
+// can be generated
+pub fn parse_table_name(name: &str) -> Option<Vec<Piece>> {
+    let (white, black) = name.split_once('v')?;
+    let mut sides = Vec::new();
+    for side in [white, black] {
+        let classes = side
+            .chars()
+            .map(|c| Some(Class::iter()["PNBRQK".find(c)?]))
+            .collect::<Option<Vec<Class>>>()?;
+        let king_count = classes
+            .iter()
+            .filter(|class| **class == Class::King)
+            .count();
+        if king_count != 1 {
+            return None;
+        }
+        sides.push(classes);
+    }
+    let piece_count = sides[0].len() + sides[1].len();
+    let has_pawns = |classes: &Vec<Class>| classes.contains(&Class::Pawn);
+    if !(3..=MAX_PIECES).contains(&piece_count) || sides.iter().all(has_pawns) {
+        return None;
+    }
+    Some(normalise_material(&sides[0], &sides[1]).0)
+}
+
+// the tables a capture or promotion from this one leads into, leaving out
+// the bare kings
+pub fn get_subtable_names(pieces: &[Piece]) -> Vec<String> {
+    let mut materials = Vec::new();
+    let captures = (0..pieces.len()).filter(|slot| pieces[*slot].class() != Class::King);
+    for captured in captures.map(Some).chain([None]) {
+        let mut remaining = pieces.to_vec();
+        if let Some(slot) = captured {
+            remaining.remove(slot);
+            materials.push(remaining.clone());
+        }
+        for (slot, piece) in remaining.iter().enumerate() {
+            let can_promote = piece.class() == Class::Pawn
+                && captured.is_none_or(|captured| pieces[captured].colour() != piece.colour());
+            if !can_promote {
+                continue;
+            }
+            for promotion in PROMOTIONS {
+                let mut promoted = remaining.clone();
+                promoted[slot] = Piece::new(&promotion, &piece.colour());
+                materials.push(promoted);
+            }
+        }
+    }
+    let mut names: Vec<String> = materials
+        .iter()
+        .filter(|pieces| pieces.len() > 2)
+        .map(|pieces| get_table_name(&normalise_pieces(pieces)))
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+// every table that can be generated, smallest first
+pub fn get_all_table_names() -> Vec<String> {
+    let classes = [
+        Class::Queen,
+        Class::Rook,
+        Class::Bishop,
+        Class::Knight,
+        Class::Pawn,
+    ];
+    let mut names = Vec::new();
+    for first in classes {
+        names.push(format!("K{}vK", first.str()));
+    }
+    for (index, first) in classes.iter().enumerate() {
+        for second in &classes[index..] {
+            names.push(format!("K{}{}vK", first.str(), second.str()));
+            names.push(format!("K{}vK{}", first.str(), second.str()));
+        }
+    }
+    names.retain(|name| parse_table_name(name).is_some());
+    names
+}
+
+// the material of a position as a table's pieces, and whether its colours
+// have to be swapped to match the table
+fn get_position_material(position: &Position) -> Option<(Vec<Piece>, bool)> {
+    if position.get_occupancy().count_ones() as usize > MAX_PIECES {
+        return None;
+    }
+    let get_classes = |colour: &Colour| {
+        let mut classes = Vec::new();
+        for class in Class::iter() {
+            let count = position
+                .get_bitboard(&Piece::new(class, colour))
+                .count_ones();
+            classes.extend((0..count).map(|_| *class));
+        }
+        classes
+    };
+    Some(normalise_material(
+        &get_classes(&Colour::White),
+        &get_classes(&Colour::Black),
+    ))
+}
+
+// the same position with the board turned around and the colours swapped
+fn get_flipped_position(position: &Position) -> Position {
+    let mut flipped = get_empty_position();
+    for piece in Piece::iter() {
+        let flipped_piece = Piece::new(&piece.class(), &!piece.colour());
+        flipped.insert_piece_at_square(&flipped_piece, &position.get_bitboard(piece).swap_bytes());
+    }
+    flipped.turn = !position.turn;
+    flipped
+}
+
+// bit 0 mirrors the files, bit 1 the ranks, and bit 2 swaps files for ranks
+fn transform_square(square: &usize, transform: &usize) -> usize {
+    let (mut file, mut rank) = (square % 8, square / 8);
+    if transform & 1 != 0 {
+        file = 7 - file;
+    }
+    if transform & 2 != 0 {
+        rank = 7 - rank;
+    }
+    if transform & 4 != 0 {
+        mem::swap(&mut file, &mut rank);
+    }
+    rank * 8 + file
+}
+
+fn has_pawns(pieces: &[Piece]) -> bool {
+    pieces.iter().any(|piece| piece.class() == Class::Pawn)
+}
+
+// the board can be mirrored every way without pawns, or only left to right
+fn get_transform_count(pieces: &[Piece]) -> usize {
+    match has_pawns(pieces) {
+        true => 2,
+        false => 8,
+    }
+}
+
+// every position has a mirror image with white's king on one of these
+// squares: the a1-d1-d4 triangle, or with pawns the a to d files
+fn is_in_king_region(square: &usize, has_pawns: &bool) -> bool {
+    let (file, rank) = (square % 8, square / 8);
+    file < 4 && (*has_pawns || rank <= file)
+}
+
+// the squares a piece other than a king is stored on, pawns never being on
+// the first or last rank
+fn get_slot_size(piece: &Piece) -> usize {
+    match piece.class() {
+        Class::Pawn => 48,
+        _ => 64,
+    }
+}
+
+fn get_slot_offset(piece: &Piece) -> usize {
+    match piece.class() {
+        Class::Pawn => 8,
+        _ => 0,
+    }
+}
+
+// the pairs of king squares a table stores, white's king in the a1-d1-d4
+// triangle and black's on or below the long diagonal if white's is on it, or
+// with pawns, white's king on the a to d files
+fn get_king_pairs(has_pawns: &bool) -> Vec<(usize, usize)> {
+    let mut pairs = Vec::new();
+    for white_king in 0..64 {
+        for black_king in 0..64 {
+            let (white_file, white_rank): (usize, usize) = (white_king % 8, white_king / 8);
+            let (black_file, black_rank) = (black_king % 8, black_king / 8);
+            let is_stored = is_in_king_region(&white_king, has_pawns)
+                && (*has_pawns || white_rank != white_file || black_rank <= black_file);
+            let are_touching =
+                white_file.abs_diff(black_file) <= 1 && white_rank.abs_diff(black_rank) <= 1;
+            if is_stored && !are_touching {
+                pairs.push((white_king, black_king));
+            }
+        }
+    }
+    pairs
+}
+
+fn get_black_king_slot(pieces: &[Piece]) -> usize {
+    pieces
+        .iter()
+        .position(|piece| *piece == Piece::BlackKing)
+        .unwrap_or(0)
+}
+
+// the generator's own index of a position, every piece on any square and
+// pieces of the same kind in ascending order. white's king changes fastest,
+// which spreads the positions worked out directly evenly over the index
+fn get_full_index(pieces: &[Piece], squares: &[usize], turn: &Colour) -> usize {
+    let mut sorted = [0; MAX_PIECES];
+    sorted[..squares.len()].copy_from_slice(squares);
+    let squares = &mut sorted[..squares.len()];
+    for slot in 1..squares.len() {
+        let mut other = slot;
+        while other > 0 && pieces[other - 1] == pieces[other] && squares[other - 1] > squares[other]
+        {
+            squares.swap(other - 1, other);
+            other -= 1;
+        }
+    }
+    let index = squares
+        .iter()
+        .rev()
+        .fold(0, |index, square| index * 64 + square);
+    index * 2 + get_colour_index(turn)
+}
+
+fn get_full_squares(pieces: &[Piece], index: &usize) -> ([usize; MAX_PIECES], Colour) {
+    let turn = match index % 2 {
+        0 => Colour::White,
+        _ => Colour::Black,
+    };
+    let mut squares = [0; MAX_PIECES];
+    let mut rest = index / 2;
+    for square in squares.iter_mut().take(pieces.len()) {
+        *square = rest % 64;
+        rest /= 64;
+    }
+    (squares, turn)
+}
+
+// the full index of each mirror image of a position, by transform
+fn get_symmetric_indices(pieces: &[Piece], squares: &[usize], turn: &Colour) -> Vec<usize> {
+    (0..get_transform_count(pieces))
+        .map(|transform| {
+            let mut transformed = [0; MAX_PIECES];
+            for (slot, square) in squares.iter().enumerate() {
+                transformed[slot] = transform_square(square, &transform);
+            }
+            get_full_index(pieces, &transformed[..squares.len()], turn)
+        })
+        .collect()
+}
+
+fn is_in_order(pieces: &[Piece], squares: &[usize]) -> bool {
+    (1..pieces.len())
+        .all(|slot| pieces[slot - 1] != pieces[slot] || squares[slot - 1] < squares[slot])
+}
+
+// the position at a full index, or None if the index does not stand for a
+// legal one
+fn get_position(pieces: &[Piece], index: &usize) -> Option<Position> {
+    let (squares, turn) = get_full_squares(pieces, index);
+    if !is_in_order(pieces, &squares) {
+        return None;
+    }
+    let mut position = get_empty_position();
+    position.turn = turn;
+    for (slot, piece) in pieces.iter().enumerate() {
+        let square = 1 << squares[slot];
+        let is_pawn_on_back_rank = piece.class() == Class::Pawn && square & (RANK_1 | RANK_8) != 0;
+        if position.get_occupancy() & square != 0 || is_pawn_on_back_rank {
+            return None;
+        }
+        position.insert_piece_at_square(piece, &square);
+    }
+    match position.is_in_check(&!turn) {
+        true => None,
+        false => Some(position),
+    }
+}
+
+// the squares a pawn can have been pushed from, which are never on its own
+// back rank
+fn get_pawn_origins(square: &u64, colour: &Colour, occupancy: &u64) -> u64 {
+    match colour {
+        Colour::White => {
+            let single = (square >> 8) & !occupancy & !RANK_1;
+            single | ((single >> 8) & RANK_2 & !occupancy)
+        }
+        Colour::Black => {
+            let single = (square << 8) & !occupancy & !RANK_8;
+            single | ((single << 8) & RANK_7 & !occupancy)
+        }
+    }
+}
+
+// the squares of the pieces in each position one quiet move before this
+// one, which stay in the same table, with the other side to move
+fn get_predecessors(pieces: &[Piece], index: &usize) -> Vec<[usize; MAX_PIECES]> {
+    let Some(position) = get_position(pieces, index) else {
+        return Vec::new();
+    };
+    let (squares, turn) = get_full_squares(pieces, index);
+    let occupancy = position.get_occupancy();
+    let mover = !turn;
+    let mut predecessors = Vec::new();
+    for (slot, piece) in pieces.iter().enumerate() {
+        if piece.colour() != mover {
+            continue;
+        }
+        let square = 1 << squares[slot];
+        let mut origins = match piece.class() {
+            Class::Pawn => get_pawn_origins(&square, &mover, &occupancy),
+            _ => generate_attacks(piece, &square, &occupancy) & !occupancy,
+        };
+        while origins != 0 {
+            let origin = pop_lsb(&mut origins);
+            let mut previous = position;
+            *previous.get_bitboard_mut(piece) ^= square | origin;
+            previous.turn = mover;
+            if previous.is_in_check(&turn) {
+                continue;
+            }
+            let mut previous_squares = squares;
+            previous_squares[slot] = bitboard_to_index(&origin);
+            predecessors.push(previous_squares);
+        }
+    }
+    predecessors
+}
+
+// a legal position looked at on its own, before any other in the table is
+// known: its value if it is mated or has a capture or promotion that wins,
+// its quiet moves still to be found losing, or NEVER_LOST, and the longest
+// of its captures and promotions that lose
+fn classify_position(
+    position: &Position,
+    tables: &EndgameTables,
+    name: &str,
+) -> Result<(u8, u8, u8), EndgameTableError> {
+    let moves = generate_legal_moves(position);
+    if moves.is_empty() {
+        return Ok(match position.is_in_check(&position.turn) {
+            true => (1, 0, 0),
+            false => (0, NEVER_LOST, 0),
+        });
+    }
+
+    let mut shortest_win: Option<u32> = None;
+    let mut longest_loss = 0;
+    let mut quiet_moves = 0;
+    let mut can_draw = false;
+    for mv in &moves {
+        if !is_capture(position, mv) && mv.promotion.is_none() {
+            quiet_moves += 1;
+            continue;
+        }
+        let mut next_position = *position;
+        next_position.make_move(mv);
+        // every table a capture or promotion leads into is loaded beforehand
+        match tables.probe(&next_position).unwrap_or(Dtm::Draw) {
+            Dtm::Loss(plies) => {
+                shortest_win = Some(shortest_win.map_or(plies + 1, |win| win.min(plies + 1)))
+            }
+            Dtm::Win(plies) => longest_loss = longest_loss.max(plies + 1),
+            Dtm::Draw => can_draw = true,
+        }
+    }
+    let too_long = || EndgameTableError::MateTooLong(name.to_string());
+    let value = match shortest_win {
+        Some(plies) if plies + 1 > MAX_VALUE => return Err(too_long()),
+        Some(plies) => plies + 1,
+        None => 0,
+    };
+    if longest_loss >= MAX_VALUE {
+        return Err(too_long());
+    }
+    let remaining = match can_draw {
+        true => NEVER_LOST,
+        false => quiet_moves,
+    };
+    Ok((value as u8, remaining, longest_loss as u8))
+}
+
+// works out a table from the mates up, with every table its captures and
+// promotions lead into already among the given ones
+pub fn generate_table(
+    pieces: &[Piece],
+    tables: &EndgameTables,
+) -> Result<EndgameTable, EndgameTableError> {
+    let pieces = normalise_pieces(pieces);
+    let name = get_table_name(&pieces);
+    if parse_table_name(&name).is_none() {
+        return Err(EndgameTableError::InvalidMaterial(name));
+    }
+    if let Some(missing) = get_subtable_names(&pieces)
+        .into_iter()
+        .find(|subtable| !tables.contains(subtable))
+    {
+        return Err(EndgameTableError::MissingTable(missing));
+    }
+
+    let piece_count = pieces.len();
+    let has_pawns = has_pawns(&pieces);
+    let size = 2 << (6 * piece_count);
+    let mut values = vec![0; size];
+    let mut remaining = vec![NEVER_LOST; size];
+    let mut longest_losses = vec![0; size];
+
+    // each position is first looked at on its own, working out only the ones
+    // with white's king in the region the table stores and copying the rest
+    // from their mirror images
+    let threads = thread::available_parallelism().map_or(1, |count| count.get());
+    let chunk_size = size.div_ceil(threads);
+    thread::scope(|scope| {
+        let handles: Vec<_> = values
+            .chunks_mut(chunk_size)
+            .zip(remaining.chunks_mut(chunk_size))
+            .zip(longest_losses.chunks_mut(chunk_size))
+            .enumerate()
+            .map(|(chunk, ((values, remaining), longest_losses))| {
+                let (pieces, name) = (&pieces, &name);
+                scope.spawn(move || -> Result<(), EndgameTableError> {
+                    for offset in 0..values.len() {
+                        let index = chunk * chunk_size + offset;
+                        let (squares, _) = get_full_squares(pieces, &index);
+                        if !is_in_king_region(&squares[0], &has_pawns) {
+                            continue;
+                        }
+                        if let Some(position) = get_position(pieces, &index) {
+                            (values[offset], remaining[offset], longest_losses[offset]) =
+                                classify_position(&position, tables, name)?;
+                        }
+                    }
+                    Ok(())
+                })
+            })
+            .collect();
+        // every thread is joined before any error is returned, since the
+        // scope panics over any that aren't
+        let results: Vec<_> = handles.into_iter().map(|handle| handle.join()).collect();
+        results.into_iter().try_for_each(|result| {
+            result.unwrap_or_else(|_| Err(EndgameTableError::GenerationFailed(name.clone())))
+        })
+    })?;
+    for index in 0..size {
+        let (squares, turn) = get_full_squares(&pieces, &index);
+        if is_in_king_region(&squares[0], &has_pawns) || !is_in_order(&pieces, &squares) {
+            continue;
+        }
+        let transform = (0..get_transform_count(&pieces))
+            .find(|transform| {
+                is_in_king_region(&transform_square(&squares[0], transform), &has_pawns)
+            })
+            .unwrap_or(0);
+        let image = get_symmetric_indices(&pieces, &squares[..piece_count], &turn)[transform];
+        values[index] = values[image];
+        remaining[index] = remaining[image];
+        longest_losses[index] = longest_losses[image];
+    }
+
+    // positions are settled in order of their plies to mate, so that a loss
+    // is only settled once all its moves are known to lose, and a win by the
+    // shortest of the ones found for it. only the image of each position with
+    // the lowest index is queued, and settling it settles the others too
+    let too_long = || EndgameTableError::MateTooLong(name.clone());
+    let is_lowest_image = |index: &usize| {
+        let (squares, turn) = get_full_squares(&pieces, index);
+        let images = get_symmetric_indices(&pieces, &squares[..piece_count], &turn);
+        images.iter().min() == Some(index)
+    };
+    let mut buckets: Vec<Vec<usize>> = vec![Vec::new(); MAX_VALUE as usize];
+    for index in 0..size {
+        if values[index] == 0 && remaining[index] == 0 {
+            if longest_losses[index] as u32 + 1 > MAX_VALUE {
+                return Err(too_long());
+            }
+            values[index] = longest_losses[index] + 1;
+        }
+        if values[index] != 0 && is_lowest_image(&index) {
+            buckets[values[index] as usize - 1].push(index);
+        }
+    }
+    for plies in 0..buckets.len() {
+        for index in mem::take(&mut buckets[plies]) {
+            // a win since found to be shorter
+            if values[index] as usize != plies + 1 {
+                continue;
+            }
+            let (squares, turn) = get_full_squares(&pieces, &index);
+            let mut images = Vec::new();
+            let mut transforms = Vec::new();
+            let symmetric = get_symmetric_indices(&pieces, &squares[..piece_count], &turn);
+            for (transform, image) in symmetric.into_iter().enumerate() {
+                if !images.contains(&image) {
+                    images.push(image);
+                    transforms.push(transform);
+                }
+            }
+            for previous_squares in get_predecessors(&pieces, &index) {
+                let previous_images =
+                    get_symmetric_indices(&pieces, &previous_squares[..piece_count], &!turn);
+                let lowest_image = previous_images.iter().min().copied();
+                for transform in &transforms {
+                    let previous = previous_images[*transform];
+                    let is_queued = Some(previous) == lowest_image;
+                    if plies % 2 == 0 {
+                        if plies + 2 > MAX_VALUE as usize {
+                            return Err(too_long());
+                        }
+                        if values[previous] == 0 || values[previous] as usize > plies + 2 {
+                            values[previous] = plies as u8 + 2;
+                            if is_queued {
+                                buckets[plies + 1].push(previous);
+                            }
+                        }
+                    } else if remaining[previous] != NEVER_LOST {
+                        remaining[previous] -= 1;
+                        longest_losses[previous] = longest_losses[previous].max(plies as u8 + 1);
+                        if remaining[previous] == 0 && values[previous] == 0 {
+                            let loss = longest_losses[previous];
+                            values[previous] = loss + 1;
+                            if is_queued {
+                                buckets[loss as usize].push(previous);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let mut table = EndgameTable::new(pieces.clone(), Vec::new());
+    table.values = (0..table.get_size())
+        .map(|index| {
+            let (squares, turn) = table.get_squares(&index);
+            values[get_full_index(&pieces, &squares[..pieces.len()], &turn)]
+        })
+        .collect();
+    Ok(table)
+}
+
+pub struct EndgameTable {
+    pieces: Vec<Piece>,
+    king_pairs: Vec<(usize, usize)>,
+    // each pair of king squares' place in king_pairs, white's king first, or
+    // NO_KING_PAIR
+    king_indices: Vec<u16>,
+    values: Vec<u8>,
+}
+
+impl EndgameTable {
+    fn new(pieces: Vec<Piece>, values: Vec<u8>) -> EndgameTable {
+        let king_pairs = get_king_pairs(&has_pawns(&pieces));
+        let mut king_indices = vec![NO_KING_PAIR; 64 * 64];
+        for (index, (white_king, black_king)) in king_pairs.iter().enumerate() {
+            king_indices[white_king * 64 + black_king] = index as u16;
+        }
+        EndgameTable {
+            pieces,
+            king_pairs,
+            king_indices,
+            values,
+        }
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<EndgameTable, EndgameTableError> {
+        let header_size = MAGIC.len() + 1;
+        if bytes.len() < header_size || bytes[..MAGIC.len()] != MAGIC {
+            return Err(EndgameTableError::NotATable);
+        }
+        let piece_count = bytes[MAGIC.len()] as usize;
+        let pieces = bytes
+            .get(header_size..header_size + piece_count)
+            .ok_or(EndgameTableError::NotATable)?
+            .iter()
+            .map(|code| Piece::iter().get(*code as usize).copied())
+            .collect::<Option<Vec<Piece>>>()
+            .ok_or(EndgameTableError::NotATable)?;
+        let name = get_table_name(&pieces);
+        if parse_table_name(&name).as_ref() != Some(&pieces) {
+            return Err(EndgameTableError::InvalidMaterial(name));
+        }
+
+        let values = bytes[header_size + piece_count..].to_vec();
+        let table = EndgameTable::new(pieces, values);
+        if table.values.len() != table.get_size() {
+            let header_size = header_size + piece_count;
+            return Err(EndgameTableError::WrongSize(
+                header_size + table.get_size(),
+                bytes.len(),
+            ));
+        }
+        Ok(table)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(self.pieces.len() as u8);
+        for piece in &self.pieces {
+            let code = Piece::iter().iter().position(|other| other == piece);
+            bytes.push(code.unwrap_or(0) as u8);
+        }
+        bytes.extend_from_slice(&self.values);
+        bytes
+    }
+
+    pub fn get_name(&self) -> String {
+        get_table_name(&self.pieces)
+    }
+
+    // in plies, or None if no position in the table is won
+    pub fn get_longest_mate(&self) -> Option<u32> {
+        self.values
+            .iter()
+            .filter_map(|value| match Dtm::from_value(value) {
+                Dtm::Win(plies) => Some(plies),
+                _ => None,
+            })
+            .max()
+    }
+
+    fn get_size(&self) -> usize {
+        let piece_positions: usize = self
+            .pieces
+            .iter()
+            .filter(|piece| piece.class() != Class::King)
+            .map(get_slot_size)
+            .product();
+        self.king_pairs.len() * piece_positions * 2
+    }
+
+    fn get_index(&self, squares: &[usize], turn: &Colour) -> Option<usize> {
+        let black_king_slot = get_black_king_slot(&self.pieces);
+        let king_index = self.king_indices[squares[0] * 64 + squares[black_king_slot]];
+        if king_index == NO_KING_PAIR {
+            return None;
+        }
+        let mut index = king_index as usize;
+        for (piece, square) in self.pieces.iter().zip(squares) {
+            if piece.class() != Class::King {
+                index = index * get_slot_size(piece) + square - get_slot_offset(piece);
+            }
+        }
+        Some(index * 2 + get_colour_index(turn))
+    }
+
+    fn get_squares(&self, index: &usize) -> ([usize; MAX_PIECES], Colour) {
+        let turn = match index % 2 {
+            0 => Colour::White,
+            _ => Colour::Black,
+        };
+        let mut squares = [0; MAX_PIECES];
+        let mut rest = index / 2;
+        for (slot, piece) in self.pieces.iter().enumerate().rev() {
+            if piece.class() != Class::King {
+                squares[slot] = rest % get_slot_size(piece) + get_slot_offset(piece);
+                rest /= get_slot_size(piece);
+            }
+        }
+        let (white_king, black_king) = self.king_pairs[rest];
+        squares[0] = white_king;
+        squares[get_black_king_slot(&self.pieces)] = black_king;
+        (squares, turn)
+    }
+
+    // the position must have the table's material, with the colours as the
+    // table has them
+    fn probe(&self, position: &Position) -> Dtm {
+        let mut remaining = *position;
+        let mut squares = [0; MAX_PIECES];
+        for (slot, piece) in self.pieces.iter().enumerate() {
+            squares[slot] = bitboard_to_index(&pop_lsb(remaining.get_bitboard_mut(piece)));
+        }
+        let transforms = match has_pawns(&self.pieces) {
+            true => 2,
+            false => 8,
+        };
+        for transform in 0..transforms {
+            let squares: Vec<usize> = squares[..self.pieces.len()]
+                .iter()
+                .map(|square| transform_square(square, &transform))
+                .collect();
+            if let Some(index) = self.get_index(&squares, &position.turn) {
+                return Dtm::from_value(&self.values[index]);
+            }
+        }
+        Dtm::Draw
+    }
+}
+
+impl fmt::Debug for EndgameTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "EndgameTable {{ {} }}", self.get_name())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct EndgameTables {
+    tables: HashMap<String, EndgameTable>,
+}
+
+impl EndgameTables {
+    pub fn new() -> EndgameTables {
+        EndgameTables::default()
+    }
+
+    // every .dtm file in the directory
+    pub fn open(directory: &str) -> Result<EndgameTables, EndgameTableError> {
+        let entries = fs::read_dir(directory)
+            .map_err(|_| EndgameTableError::UnreadableDirectory(directory.to_string()))?;
+        let mut tables = EndgameTables::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_none_or(|extension| extension != "dtm") {
+                continue;
+            }
+            let display_path = path.display().to_string();
+            let bytes = fs::read(&path)
+                .map_err(|_| EndgameTableError::UnreadableFile(display_path.clone()))?;
+            let table = EndgameTable::from_bytes(&bytes)
+                .map_err(|e| EndgameTableError::InvalidFile(display_path, Box::new(e)))?;
+            tables.insert(table);
+        }
+        Ok(tables)
+    }
+
+    pub fn insert(&mut self, table: EndgameTable) {
+        self.tables.insert(table.get_name(), table);
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.tables.contains_key(name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.tables.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+    }
+
+    pub fn get_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.tables.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    // None for a position no loaded table covers, or one that can still castle
+    pub fn probe(&self, position: &Position) -> Option<Dtm> {
+        if position.castling_rights != 0 {
+            return None;
+        }
+        let (pieces, is_flipped) = get_position_material(position)?;
+        if pieces.len() == 2 {
+            return Some(Dtm::Draw);
+        }
+        let table = self.tables.get(&get_table_name(&pieces))?;
+        match is_flipped {
+            true => Some(table.probe(&get_flipped_position(position))),
+            false => Some(table.probe(position)),
+        }
+    }
+}
+
+// the file a table is kept in
+pub fn get_table_path(directory: &str, name: &str) -> String {
+    Path::new(directory)
+        .join(format!("{name}.dtm"))
+        .display()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::fen_to_position;
+
+    // the table and every table it leads into
+    fn generate(name: &str, tables: &mut EndgameTables) -> EndgameTable {
+        let pieces = parse_table_name(name).unwrap();
+        for subtable in get_subtable_names(&pieces) {
+            if !tables.contains(&subtable) {
+                let table = generate(&subtable, tables);
+                tables.insert(table);
+            }
+        }
+        generate_table(&pieces, tables).unwrap()
+    }
+
+    fn assert_longest_mate(name: &str, plies: Option<u32>) {
+        let table = generate(name, &mut EndgameTables::new());
+        assert_eq!(table.get_longest_mate(), plies, "{name}");
+    }
+
+    #[test]
+    fn three_piece_longest_mates() {
+        assert_longest_mate("KQvK", Some(19));
+        assert_longest_mate("KRvK", Some(31));
+        assert_longest_mate("KPvK", Some(55));
+        assert_longest_mate("KBvK", None);
+        assert_longest_mate("KNvK", None);
+    }
+
+    #[test]
+    fn bishop_and_knight_longest_mate() {
+        assert_longest_mate("KBNvK", Some(65));
+    }
+
+    #[test]
+    fn queen_against_rook_longest_mate() {
+        assert_longest_mate("KQvKR", Some(69));
+    }
+
+    #[test]
+    fn probe_after_round_trip() {
+        let mut tables = EndgameTables::new();
+        let table = generate("KPvK", &mut tables);
+        let bytes = table.to_bytes();
+        let read_table = EndgameTable::from_bytes(&bytes).unwrap();
+        assert_eq!(read_table.get_name(), "KPvK");
+        assert_eq!(read_table.to_bytes(), bytes);
+
+        let pieces = parse_table_name("KPvK").unwrap();
+        let mut positions = 0;
+        for index in 0..2 << (6 * pieces.len()) {
+            if let Some(position) = get_position(&pieces, &index) {
+                assert_eq!(table.probe(&position), read_table.probe(&position));
+                positions += 1;
+            }
+        }
+        assert!(positions > 0);
+
+        // with the colours swapped, through the set of tables
+        tables.insert(read_table);
+        let position = fen_to_position("8/8/8/8/8/4k3/4P3/4K3 w - - 0 1").unwrap();
+        assert_eq!(tables.probe(&position), Some(Dtm::Draw));
+        let position = fen_to_position("4k3/4p3/4K3/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(tables.probe(&position), Some(Dtm::Draw));
+        let position = fen_to_position("8/8/8/8/4k3/8/4p3/4K3 b - - 0 1").unwrap();
+        assert!(matches!(tables.probe(&position), Some(Dtm::Win(_))));
+    }
+
+    #[test]
+    fn from_bytes_rejects_damaged_tables() {
+        let table = generate("KRvK", &mut EndgameTables::new());
+        let bytes = table.to_bytes();
+        assert!(matches!(
+            EndgameTable::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(EndgameTableError::WrongSize(_, _))
+        ));
+        let mut damaged = bytes.clone();
+        damaged[0] = b'X';
+        assert!(matches!(
+            EndgameTable::from_bytes(&damaged),
+            Err(EndgameTableError::NotATable)
+        ));
+    }
+}
@@ -0,0 +1,527 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::pieces::Colour;
+use crate::utils::{bitboard_to_index, index_to_algebraic, pop_lsb};
+
+fn squares_to_string(squares: &u64) -> String {
+    let mut squares = *squares;
+    let mut names = Vec::new();
+    while squares != 0 {
+        let square = pop_lsb(&mut squares);
+        names.push(index_to_algebraic(&bitboard_to_index(&square)).unwrap_or_default());
+    }
+    names.join(", ")
+}
+
+fn colour_name(colour: &Colour) -> &'static str {
+    match colour {
+        Colour::White => "White",
+        Colour::Black => "Black",
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SquareError {
+    WrongLength,
+    InvalidFile(char),
+    InvalidRank(char),
+    IndexOutOfRange(usize),
+}
+
+impl fmt::Display for SquareError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SquareError::WrongLength => write!(f, "Square must be exactly 2 characters long."),
+            SquareError::InvalidFile(file) => {
+                write!(f, "Invalid file '{file}'. Must be between 'a' and 'h'.")
+            }
+            SquareError::InvalidRank(rank) => {
+                write!(f, "Invalid rank '{rank}'. Must be between '1' and '8'.")
+            }
+            SquareError::IndexOutOfRange(index) => {
+                write!(f, "Invalid index {index}. Must be less than 64.")
+            }
+        }
+    }
+}
+
+impl Error for SquareError {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FenError {
+    WrongFieldCount(usize),
+    WrongRankCount(usize),
+    InvalidPiece(char),
+    // ranks are numbered as on the board, 1 to 8
+    RankTooLong(usize),
+    RankTooShort(usize),
+    InvalidSideToMove(String),
+    CastlingWithoutKing(char),
+    InvalidCastlingRight(char),
+    MissingCastlingRook(char),
+    InvalidEnPassantSquare(SquareError),
+    InvalidHalfmoveClock(String),
+    InvalidFullmoveNumber(String),
+    InvalidPosition(PositionError),
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FenError::WrongFieldCount(count) => {
+                write!(f, "FEN must have between 4 and 6 fields, found {count}.")
+            }
+            FenError::WrongRankCount(count) => {
+                write!(f, "FEN piece placement must have 8 ranks, found {count}.")
+            }
+            FenError::InvalidPiece(c) => write!(f, "Invalid piece '{c}' in FEN piece placement."),
+            FenError::RankTooLong(rank) => write!(f, "FEN rank {rank} has more than 8 squares."),
+            FenError::RankTooShort(rank) => write!(f, "FEN rank {rank} has fewer than 8 squares."),
+            FenError::InvalidSideToMove(turn) => {
+                write!(f, "FEN side to move must be 'w' or 'b', found '{turn}'.")
+            }
+            FenError::CastlingWithoutKing(c) => write!(
+                f,
+                "FEN castling right '{c}' given without a king on its back rank."
+            ),
+            FenError::InvalidCastlingRight(c) => {
+                write!(f, "Invalid character '{c}' in FEN castling rights.")
+            }
+            FenError::MissingCastlingRook(c) => {
+                write!(f, "FEN castling right '{c}' refers to a missing rook.")
+            }
+            FenError::InvalidEnPassantSquare(error) => {
+                write!(f, "Invalid FEN en passant square. {error}")
+            }
+            FenError::InvalidHalfmoveClock(clock) => {
+                write!(f, "FEN halfmove clock must be a number, found '{clock}'.")
+            }
+            FenError::InvalidFullmoveNumber(number) => {
+                write!(f, "FEN fullmove number must be a number, found '{number}'.")
+            }
+            FenError::InvalidPosition(error) => {
+                write!(f, "FEN describes an illegal position. {error}")
+            }
+        }
+    }
+}
+
+impl Error for FenError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FenError::InvalidEnPassantSquare(error) => Some(error),
+            FenError::InvalidPosition(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<PositionError> for FenError {
+    fn from(error: PositionError) -> FenError {
+        FenError::InvalidPosition(error)
+    }
+}
+
+// problems that make a position impossible to reach or to play from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PositionError {
+    OverlappingPieces(u64),
+    MissingKing(Colour),
+    ExtraKings(Colour),
+    PawnsOnBackRank(u64),
+    OpponentInCheck(Colour),
+    ImpossibleEnPassant(u64),
+    InvalidCastlingRights(u64),
+}
+
+impl fmt::Display for PositionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PositionError::OverlappingPieces(squares) => {
+                write!(f, "More than one piece on {}.", squares_to_string(squares))
+            }
+            PositionError::MissingKing(colour) => write!(f, "{} has no king.", colour_name(colour)),
+            PositionError::ExtraKings(colour) => {
+                write!(f, "{} has more than one king.", colour_name(colour))
+            }
+            PositionError::PawnsOnBackRank(squares) => write!(
+                f,
+                "Pawns cannot stand on the first or last rank, found on {}.",
+                squares_to_string(squares)
+            ),
+            PositionError::OpponentInCheck(colour) => write!(
+                f,
+                "{} is in check but it is not their move.",
+                colour_name(colour)
+            ),
+            PositionError::ImpossibleEnPassant(square) => write!(
+                f,
+                "En passant on {} is impossible, no pawn has just passed it.",
+                squares_to_string(square)
+            ),
+            PositionError::InvalidCastlingRights(squares) => write!(
+                f,
+                "Castling rights with {} need a rook there and a king on the same rank.",
+                squares_to_string(squares)
+            ),
+        }
+    }
+}
+
+impl Error for PositionError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IllegalMoveReason {
+    NoPieceOnSquare,
+    NotYourPiece,
+    UnreachableSquare,
+    LeavesKingInCheck,
+    PromotionRequired,
+    InvalidPromotion,
+    CastlingNotAllowed,
+}
+
+impl fmt::Display for IllegalMoveReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let reason = match self {
+            IllegalMoveReason::NoPieceOnSquare => "There is no piece on the origin square.",
+            IllegalMoveReason::NotYourPiece => "The piece on the origin square is not yours.",
+            IllegalMoveReason::UnreachableSquare => "That piece cannot reach the destination.",
+            IllegalMoveReason::LeavesKingInCheck => "That move leaves the king in check.",
+            IllegalMoveReason::PromotionRequired => "Promotion piece required, e.g. 'e7e8q'.",
+            IllegalMoveReason::InvalidPromotion => "That move cannot promote to that piece.",
+            IllegalMoveReason::CastlingNotAllowed => "Castling is not legal in this position.",
+        };
+        write!(f, "{reason}")
+    }
+}
+
+impl Error for IllegalMoveReason {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MoveError {
+    InvalidNotation(String),
+    InvalidSquare(SquareError),
+    InvalidPromotionPiece(char),
+    Ambiguous(String),
+    Illegal(IllegalMoveReason),
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MoveError::InvalidNotation(text) => {
+                write!(
+                    f,
+                    "'{text}' is not a move in UCI or standard algebraic notation."
+                )
+            }
+            MoveError::InvalidSquare(error) => write!(f, "{error}"),
+            MoveError::InvalidPromotionPiece(c) => write!(
+                f,
+                "Invalid promotion piece '{c}'. Must be one of 'n', 'b', 'r' or 'q'."
+            ),
+            MoveError::Ambiguous(text) => write!(f, "Move '{text}' is ambiguous."),
+            MoveError::Illegal(reason) => write!(f, "Illegal move. {reason}"),
+        }
+    }
+}
+
+impl Error for MoveError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MoveError::InvalidSquare(error) => Some(error),
+            MoveError::Illegal(reason) => Some(reason),
+            _ => None,
+        }
+    }
+}
+
+impl From<SquareError> for MoveError {
+    fn from(error: SquareError) -> MoveError {
+        MoveError::InvalidSquare(error)
+    }
+}
+
+impl From<IllegalMoveReason> for MoveError {
+    fn from(reason: IllegalMoveReason) -> MoveError {
+        MoveError::Illegal(reason)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PgnError {
+    InvalidFen(FenError),
+    InvalidMove(String, MoveError),
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PgnError::InvalidFen(error) => write!(f, "Invalid FEN header. {error}"),
+            PgnError::InvalidMove(token, error) => write!(f, "Invalid move '{token}'. {error}"),
+        }
+    }
+}
+
+impl Error for PgnError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PgnError::InvalidFen(error) => Some(error),
+            PgnError::InvalidMove(_, error) => Some(error),
+        }
+    }
+}
+
+impl From<FenError> for PgnError {
+    fn from(error: FenError) -> PgnError {
+        PgnError::InvalidFen(error)
+    }
+}
+
+// errors from reading a file of evaluation parameters
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParameterError {
+    MissingName(String),
+    UnknownName(String),
+    InvalidValue(String),
+    // name, expected count, found count
+    WrongValueCount(String, usize, usize),
+}
+
+impl fmt::Display for ParameterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParameterError::MissingName(value) => {
+                write!(f, "Value '{value}' comes before any parameter name.")
+            }
+            ParameterError::UnknownName(name) => write!(f, "Unknown parameter '{name}'."),
+            ParameterError::InvalidValue(value) => {
+                write!(f, "Expected a whole number, found '{value}'.")
+            }
+            ParameterError::WrongValueCount(name, expected, found) => write!(
+                f,
+                "Parameter '{name}' takes {expected} values, found {found}."
+            ),
+        }
+    }
+}
+
+impl Error for ParameterError {}
+
+// errors from reading a file of positions and game results, with the line
+// number the problem is on
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DatasetError {
+    MissingResult(usize),
+    // a binary record that does not hold a valid position, by its number
+    InvalidRecord(usize),
+    InvalidResult(usize, String),
+    InvalidFen(usize, FenError),
+}
+
+impl fmt::Display for DatasetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DatasetError::MissingResult(line) => write!(f, "Line {line}: missing game result."),
+            DatasetError::InvalidRecord(record) => {
+                write!(f, "Record {record}: not a valid position.")
+            }
+            DatasetError::InvalidResult(line, result) => write!(
+                f,
+                "Line {line}: '{result}' is not a result, use 1-0, 0-1, 1/2-1/2 or 1, 0, 0.5."
+            ),
+            DatasetError::InvalidFen(line, error) => write!(f, "Line {line}: {error}"),
+        }
+    }
+}
+
+impl Error for DatasetError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DatasetError::InvalidFen(_, error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+// errors from reading a network weight file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NetworkError {
+    NotANetwork,
+    UnsupportedVersion(u32),
+    EmptyHiddenLayer,
+    // expected size, found size in bytes
+    WrongSize(usize, usize),
+}
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NetworkError::NotANetwork => write!(f, "Not a network weight file."),
+            NetworkError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported network format version {version}.")
+            }
+            NetworkError::EmptyHiddenLayer => write!(f, "The network has no hidden layer."),
+            NetworkError::WrongSize(expected, found) => write!(
+                f,
+                "The network should be {expected} bytes for its hidden layer size, found {found}."
+            ),
+        }
+    }
+}
+
+impl Error for NetworkError {}
+
+// errors from reading a polyglot opening book
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BookError {
+    // found size in bytes
+    WrongSize(usize),
+}
+
+impl fmt::Display for BookError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BookError::WrongSize(found) => write!(
+                f,
+                "A book should be a whole number of 16 byte entries, found {found} bytes."
+            ),
+        }
+    }
+}
+
+impl Error for BookError {}
+
+// errors from generating or reading distance to mate endgame tables
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EndgameTableError {
+    InvalidMaterial(String),
+    // a table that must be generated first, for the captures and promotions
+    // that lead into it
+    MissingTable(String),
+    MateTooLong(String),
+    NotATable,
+    // expected size, found size in bytes
+    WrongSize(usize, usize),
+    UnreadableDirectory(String),
+    UnreadableFile(String),
+    InvalidFile(String, Box<EndgameTableError>),
+    // a thread working out the table panicked
+    GenerationFailed(String),
+}
+
+impl fmt::Display for EndgameTableError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EndgameTableError::InvalidMaterial(name) => write!(
+                f,
+                "'{name}' is not a table of three or four pieces with pawns for one side at most, like KRvK or KBNvK."
+            ),
+            EndgameTableError::MissingTable(name) => {
+                write!(f, "The {name} table has to be generated first.")
+            }
+            EndgameTableError::MateTooLong(name) => {
+                write!(f, "The {name} table has mates too long to store.")
+            }
+            EndgameTableError::NotATable => write!(f, "Not an endgame table file."),
+            EndgameTableError::WrongSize(expected, found) => write!(
+                f,
+                "The table should be {expected} bytes for its pieces, found {found}."
+            ),
+            EndgameTableError::UnreadableDirectory(path) => {
+                write!(f, "Could not read the directory '{path}'.")
+            }
+            EndgameTableError::UnreadableFile(path) => write!(f, "Could not read '{path}'."),
+            EndgameTableError::InvalidFile(path, error) => write!(f, "In '{path}': {error}"),
+            EndgameTableError::GenerationFailed(name) => {
+                write!(f, "Generating the {name} table failed.")
+            }
+        }
+    }
+}
+
+impl Error for EndgameTableError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            EndgameTableError::InvalidFile(_, error) => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+// errors from a front end talking UCI
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProtocolError {
+    UnknownCommand(String),
+    MissingArgument(&'static str),
+    InvalidNumber(String),
+    UnknownParameter(String),
+    UnknownOption(String),
+    InvalidOptionValue(String, String),
+    InvalidFen(FenError),
+    InvalidMove(String, MoveError),
+    InvalidParameters(ParameterError),
+    InvalidNetwork(NetworkError),
+    InvalidBook(BookError),
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProtocolError::UnknownCommand(command) => write!(f, "Unknown command '{command}'."),
+            ProtocolError::MissingArgument(expected) => write!(f, "Expected {expected}."),
+            ProtocolError::InvalidNumber(text) => write!(f, "Expected a number, found '{text}'."),
+            ProtocolError::UnknownParameter(parameter) => {
+                write!(f, "Unknown parameter '{parameter}'.")
+            }
+            ProtocolError::UnknownOption(name) => write!(f, "Unknown option '{name}'."),
+            ProtocolError::InvalidOptionValue(name, value) => {
+                write!(f, "Invalid value '{value}' for option '{name}'.")
+            }
+            ProtocolError::InvalidFen(error) => write!(f, "{error}"),
+            ProtocolError::InvalidMove(text, error) => write!(f, "Invalid move '{text}'. {error}"),
+            ProtocolError::InvalidParameters(error) => write!(f, "{error}"),
+            ProtocolError::InvalidNetwork(error) => write!(f, "{error}"),
+            ProtocolError::InvalidBook(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl Error for ProtocolError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ProtocolError::InvalidFen(error) => Some(error),
+            ProtocolError::InvalidMove(_, error) => Some(error),
+            ProtocolError::InvalidParameters(error) => Some(error),
+            ProtocolError::InvalidNetwork(error) => Some(error),
+            ProtocolError::InvalidBook(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<FenError> for ProtocolError {
+    fn from(error: FenError) -> ProtocolError {
+        ProtocolError::InvalidFen(error)
+    }
+}
+
+impl From<ParameterError> for ProtocolError {
+    fn from(error: ParameterError) -> ProtocolError {
+        ProtocolError::InvalidParameters(error)
+    }
+}
+
+impl From<NetworkError> for ProtocolError {
+    fn from(error: NetworkError) -> ProtocolError {
+        ProtocolError::InvalidNetwork(error)
+    }
+}
+
+impl From<BookError> for ProtocolError {
+    fn from(error: BookError) -> ProtocolError {
+        ProtocolError::InvalidBook(error)
+    }
+}
@@ -0,0 +1,403 @@
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+use crate::king_safety::{evaluate_king_attacks, evaluate_king_files, evaluate_pawn_shield};
+use crate::mobility::{evaluate_mobility, evaluate_rook_files, evaluate_rooks_on_seventh};
+use crate::parameters::Parameters;
+use crate::pawn_structure::{
+    evaluate_pawn_structure, get_front_span, get_relative_rank, PawnEntry, PawnHashTable,
+};
+use crate::pieces::{Class, Colour, Piece};
+use crate::position::Position;
+use crate::utils::{bitboard_to_index, pop_lsb};
+
+pub const PAWN_VALUE: i32 = 100;
+pub const KNIGHT_VALUE: i32 = 320;
+pub const BISHOP_VALUE: i32 = 330;
+pub const ROOK_VALUE: i32 = 500;
+pub const QUEEN_VALUE: i32 = 900;
+
+// how much each piece counts towards the middlegame, with all of them on the
+// board making up the full phase
+const KNIGHT_PHASE: i32 = 1;
+const BISHOP_PHASE: i32 = 1;
+const ROOK_PHASE: i32 = 2;
+const QUEEN_PHASE: i32 = 4;
+pub const MAX_PHASE: i32 = 24;
+
+// a score split into its middlegame and endgame parts, blended by the phase
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Score {
+    pub mg: i32,
+    pub eg: i32,
+}
+
+impl Score {
+    pub const fn new(mg: i32, eg: i32) -> Score {
+        Score { mg, eg }
+    }
+
+    pub fn taper(&self, phase: &i32) -> i32 {
+        (self.mg * phase + self.eg * (MAX_PHASE - phase)) / MAX_PHASE
+    }
+}
+
+impl Add for Score {
+    type Output = Score;
+    fn add(self, other: Score) -> Score {
+        Score::new(self.mg + other.mg, self.eg + other.eg)
+    }
+}
+
+impl AddAssign for Score {
+    fn add_assign(&mut self, other: Score) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Score {
+    type Output = Score;
+    fn sub(self, other: Score) -> Score {
+        Score::new(self.mg - other.mg, self.eg - other.eg)
+    }
+}
+
+impl SubAssign for Score {
+    fn sub_assign(&mut self, other: Score) {
+        *self = *self - other;
+    }
+}
+
+impl Neg for Score {
+    type Output = Score;
+    fn neg(self) -> Score {
+        Score::new(-self.mg, -self.eg)
+    }
+}
+
+impl Mul<i32> for Score {
+    type Output = Score;
+    fn mul(self, factor: i32) -> Score {
+        Score::new(self.mg * factor, self.eg * factor)
+    }
+}
+
+// piece-square tables are written as the board is printed, from white's side
+// with a8 in the top left
+// https://www.chessprogramming.org/Simplified_Evaluation_Function
+#[rustfmt::skip]
+pub const PAWN_TABLE: [i32; 64] = [
+     0,   0,   0,   0,   0,   0,   0,   0,
+    50,  50,  50,  50,  50,  50,  50,  50,
+    10,  10,  20,  30,  30,  20,  10,  10,
+     5,   5,  10,  25,  25,  10,   5,   5,
+     0,   0,   0,  20,  20,   0,   0,   0,
+     5,  -5, -10,   0,   0, -10,  -5,   5,
+     5,  10,  10, -20, -20,  10,  10,   5,
+     0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+pub const KNIGHT_TABLE: [i32; 64] = [
+    -50, -40, -30, -30, -30, -30, -40, -50,
+    -40, -20,   0,   0,   0,   0, -20, -40,
+    -30,   0,  10,  15,  15,  10,   0, -30,
+    -30,   5,  15,  20,  20,  15,   5, -30,
+    -30,   0,  15,  20,  20,  15,   0, -30,
+    -30,   5,  10,  15,  15,  10,   5, -30,
+    -40, -20,   0,   5,   5,   0, -20, -40,
+    -50, -40, -30, -30, -30, -30, -40, -50,
+];
+
+#[rustfmt::skip]
+pub const BISHOP_TABLE: [i32; 64] = [
+    -20, -10, -10, -10, -10, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,  10,  10,   5,   0, -10,
+    -10,   5,   5,  10,  10,   5,   5, -10,
+    -10,   0,  10,  10,  10,  10,   0, -10,
+    -10,  10,  10,  10,  10,  10,  10, -10,
+    -10,   5,   0,   0,   0,   0,   5, -10,
+    -20, -10, -10, -10, -10, -10, -10, -20,
+];
+
+#[rustfmt::skip]
+pub const ROOK_TABLE: [i32; 64] = [
+     0,   0,   0,   0,   0,   0,   0,   0,
+     5,  10,  10,  10,  10,  10,  10,   5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+     0,   0,   0,   5,   5,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+pub const QUEEN_TABLE: [i32; 64] = [
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+     -5,   0,   5,   5,   5,   5,   0,  -5,
+      0,   0,   5,   5,   5,   5,   0,  -5,
+    -10,   5,   5,   5,   5,   5,   0, -10,
+    -10,   0,   5,   0,   0,   0,   0, -10,
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+];
+
+#[rustfmt::skip]
+pub const KING_TABLE: [i32; 64] = [
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -20, -30, -30, -40, -40, -30, -30, -20,
+    -10, -20, -20, -20, -20, -20, -20, -10,
+     20,  20,   0,   0,   0,   0,  20,  20,
+     20,  30,  10,   0,   0,  10,  30,  20,
+];
+
+// in the endgame the king should come out and head for the centre
+#[rustfmt::skip]
+pub const KING_ENDGAME_TABLE: [i32; 64] = [
+    -50, -40, -30, -20, -20, -30, -40, -50,
+    -30, -20, -10,   0,   0, -10, -20, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -30,   0,   0,   0,   0, -30, -30,
+    -50, -30, -30, -30, -30, -30, -30, -50,
+];
+
+pub fn get_piece_value(class: &Class) -> i32 {
+    match class {
+        Class::Pawn => PAWN_VALUE,
+        Class::Knight => KNIGHT_VALUE,
+        Class::Bishop => BISHOP_VALUE,
+        Class::Rook => ROOK_VALUE,
+        Class::Queen => QUEEN_VALUE,
+        Class::King => 0,
+    }
+}
+
+fn get_piece_phase(class: &Class) -> i32 {
+    match class {
+        Class::Knight => KNIGHT_PHASE,
+        Class::Bishop => BISHOP_PHASE,
+        Class::Rook => ROOK_PHASE,
+        Class::Queen => QUEEN_PHASE,
+        Class::Pawn | Class::King => 0,
+    }
+}
+
+// MAX_PHASE with every piece on the board, down to 0 with only kings and pawns
+pub fn get_phase(position: &Position) -> i32 {
+    let phase: i32 = Piece::iter()
+        .iter()
+        .map(|piece| {
+            position.get_bitboard(piece).count_ones() as i32 * get_piece_phase(&piece.class())
+        })
+        .sum();
+    phase.min(MAX_PHASE)
+}
+
+// tables are laid out rank 8 first, so white squares are flipped vertically
+// to look them up while black squares already line up
+pub fn get_table_index(square: &u64, colour: &Colour) -> usize {
+    let index = bitboard_to_index(square);
+    match colour {
+        Colour::White => index ^ 56,
+        Colour::Black => index,
+    }
+}
+
+pub fn evaluate_material(position: &Position, colour: &Colour, parameters: &Parameters) -> Score {
+    let mut score = Score::default();
+    // every class but the king, which has no value
+    for class in &Class::iter()[..5] {
+        let count = position
+            .get_bitboard(&Piece::new(class, colour))
+            .count_ones() as i32;
+        score += parameters.piece_values[*class as usize] * count;
+    }
+    score
+}
+
+pub fn evaluate_piece_squares(
+    position: &Position,
+    colour: &Colour,
+    parameters: &Parameters,
+) -> Score {
+    let mut score = Score::default();
+    for class in Class::iter() {
+        let table = &parameters.piece_square_tables[*class as usize];
+        let mut pieces = position.get_bitboard(&Piece::new(class, colour));
+        while pieces != 0 {
+            let square = pop_lsb(&mut pieces);
+            score += table[get_table_index(&square, colour)];
+        }
+    }
+    score
+}
+
+// a passed pawn is worth more when nothing stands between it and promotion,
+// which depends on every piece and so is not kept with the pawn structure
+pub fn evaluate_passed_pawn_paths(
+    position: &Position,
+    passed_pawns: &u64,
+    colour: &Colour,
+    parameters: &Parameters,
+) -> Score {
+    let mut score = Score::default();
+    let occupancy = position.get_occupancy();
+    let mut pawns = passed_pawns & position.get_bitboard(&Piece::new(&Class::Pawn, colour));
+    while pawns != 0 {
+        let square = pop_lsb(&mut pawns);
+        if get_front_span(&square, colour) & occupancy == 0 {
+            score += parameters.passed_pawn_free_path[get_relative_rank(&square, colour)];
+        }
+    }
+    score
+}
+
+// how freely the pieces move and how well the king is sheltered
+fn evaluate_activity_and_king_safety(
+    position: &Position,
+    colour: &Colour,
+    parameters: &Parameters,
+) -> Score {
+    evaluate_mobility(position, colour, parameters)
+        + evaluate_rook_files(position, colour, parameters)
+        + evaluate_rooks_on_seventh(position, colour, parameters)
+        + evaluate_king_attacks(position, colour, parameters)
+        + evaluate_pawn_shield(position, colour, parameters)
+        + evaluate_king_files(position, colour, parameters)
+}
+
+// every term that is not kept in the pawn hash table, for one side
+fn evaluate_pieces_of_colour(
+    position: &Position,
+    passed_pawns: &u64,
+    colour: &Colour,
+    parameters: &Parameters,
+) -> Score {
+    evaluate_material(position, colour, parameters)
+        + evaluate_piece_squares(position, colour, parameters)
+        + evaluate_passed_pawn_paths(position, passed_pawns, colour, parameters)
+        + evaluate_activity_and_king_safety(position, colour, parameters)
+}
+
+fn evaluate_with_pawn_structure(
+    position: &Position,
+    pawn_entry: &PawnEntry,
+    parameters: &Parameters,
+) -> i32 {
+    let passed_pawns = pawn_entry.passed_pawns;
+    let score = pawn_entry.score
+        + evaluate_pieces_of_colour(position, &passed_pawns, &Colour::White, parameters)
+        - evaluate_pieces_of_colour(position, &passed_pawns, &Colour::Black, parameters);
+    score.taper(&get_phase(position))
+}
+
+// tapered score from white's point of view
+pub fn evaluate_white_relative(position: &Position, parameters: &Parameters) -> i32 {
+    let pawn_entry =
+        evaluate_pawn_structure(&position.white_pawn, &position.black_pawn, parameters);
+    evaluate_with_pawn_structure(position, &pawn_entry, parameters)
+}
+
+// score from the point of view of the side to move, as negamax expects
+pub fn evaluate(position: &Position, parameters: &Parameters) -> i32 {
+    match position.turn {
+        Colour::White => evaluate_white_relative(position, parameters),
+        Colour::Black => -evaluate_white_relative(position, parameters),
+    }
+}
+
+// as evaluate, but reusing pawn structure already worked out for these pawns
+pub fn evaluate_with_pawn_table(
+    position: &Position,
+    parameters: &Parameters,
+    pawn_table: &mut PawnHashTable,
+) -> i32 {
+    let pawn_entry = pawn_table.probe(&position.white_pawn, &position.black_pawn, parameters);
+    let score = evaluate_with_pawn_structure(position, &pawn_entry, parameters);
+    match position.turn {
+        Colour::White => score,
+        Colour::Black => -score,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::fen_to_position;
+
+    const FENS: [&str; 8] = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 3",
+        "2r3k1/1R3ppp/8/3P4/8/8/5PPP/6K1 b - - 0 1",
+        "6k1/5pp1/7p/8/1P6/P7/6PP/6K1 w - - 0 1",
+        "5rk1/8/8/8/8/6q1/5PPP/6K1 w - - 0 1",
+        "8/5k2/8/2p5/2P5/8/5K2/8 w - - 0 1",
+    ];
+
+    // the same position with the board flipped top to bottom and the colours swapped
+    fn mirror(position: &Position) -> Position {
+        Position {
+            white_pawn: position.black_pawn.swap_bytes(),
+            white_knight: position.black_knight.swap_bytes(),
+            white_bishop: position.black_bishop.swap_bytes(),
+            white_rook: position.black_rook.swap_bytes(),
+            white_queen: position.black_queen.swap_bytes(),
+            white_king: position.black_king.swap_bytes(),
+            black_pawn: position.white_pawn.swap_bytes(),
+            black_knight: position.white_knight.swap_bytes(),
+            black_bishop: position.white_bishop.swap_bytes(),
+            black_rook: position.white_rook.swap_bytes(),
+            black_queen: position.white_queen.swap_bytes(),
+            black_king: position.white_king.swap_bytes(),
+            turn: !position.turn,
+            last_moved_squares: position.last_moved_squares.swap_bytes(),
+            en_passant_square: position.en_passant_square.swap_bytes(),
+            castling_rights: position.castling_rights.swap_bytes(),
+            halfmove_clock: position.halfmove_clock,
+            fullmove_number: position.fullmove_number,
+        }
+    }
+
+    #[test]
+    fn pawn_table_matches_a_fresh_evaluation() {
+        let parameters = Parameters::default();
+        let mut pawn_table = PawnHashTable::new();
+        // the second pass reads every pawn structure back out of the table
+        for _ in 0..2 {
+            for fen in FENS {
+                let position = fen_to_position(fen).unwrap();
+                assert_eq!(
+                    evaluate_with_pawn_table(&position, &parameters, &mut pawn_table),
+                    evaluate(&position, &parameters),
+                    "{fen}"
+                );
+            }
+        }
+        assert!(pawn_table.hits > 0);
+    }
+
+    #[test]
+    fn mirrored_positions_evaluate_the_same() {
+        let parameters = Parameters::default();
+        for fen in FENS {
+            let position = fen_to_position(fen).unwrap();
+            let mirrored = mirror(&position);
+            assert!(mirrored.validate().is_ok(), "{fen}");
+            assert_eq!(
+                evaluate(&mirrored, &parameters),
+                evaluate(&position, &parameters),
+                "{fen}"
+            );
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use std::fmt;
+
+use crate::evaluation::{
+    evaluate_material, evaluate_passed_pawn_paths, evaluate_piece_squares, get_phase, Score,
+    MAX_PHASE,
+};
+use crate::king_safety::{evaluate_king_attacks, evaluate_king_files, evaluate_pawn_shield};
+use crate::mobility::{evaluate_mobility, evaluate_rook_files, evaluate_rooks_on_seventh};
+use crate::parameters::Parameters;
+use crate::pawn_structure::{evaluate_pawns_of_colour, get_passed_pawns};
+use crate::pieces::Colour;
+use crate::position::Position;
+use crate::search::format_score;
+
+// one line of the breakdown, with each side's part counted from its own point
+// of view so that the total is white's minus black's
+pub struct EvaluationTerm {
+    pub name: &'static str,
+    pub white: Score,
+    pub black: Score,
+}
+
+impl EvaluationTerm {
+    pub fn total(&self) -> Score {
+        self.white - self.black
+    }
+}
+
+pub struct EvaluationTrace {
+    pub terms: Vec<EvaluationTerm>,
+    pub phase: i32,
+}
+
+impl EvaluationTrace {
+    pub fn total(&self) -> Score {
+        self.terms
+            .iter()
+            .fold(Score::default(), |total, term| total + term.total())
+    }
+
+    // the same number evaluate_white_relative gives
+    pub fn score(&self) -> i32 {
+        self.total().taper(&self.phase)
+    }
+}
+
+fn trace_term(
+    name: &'static str,
+    position: &Position,
+    parameters: &Parameters,
+    evaluate_term: fn(&Position, &Colour, &Parameters) -> Score,
+) -> EvaluationTerm {
+    EvaluationTerm {
+        name,
+        white: evaluate_term(position, &Colour::White, parameters),
+        black: evaluate_term(position, &Colour::Black, parameters),
+    }
+}
+
+// every term of the evaluation worked out separately, so a misjudged position
+// can be pulled apart
+pub fn trace_evaluation(position: &Position, parameters: &Parameters) -> EvaluationTrace {
+    let white_pawns = position.white_pawn;
+    let black_pawns = position.black_pawn;
+    let passed_pawns = get_passed_pawns(&white_pawns, &black_pawns, &Colour::White)
+        | get_passed_pawns(&black_pawns, &white_pawns, &Colour::Black);
+
+    let terms = vec![
+        trace_term("Material", position, parameters, evaluate_material),
+        trace_term(
+            "Piece squares",
+            position,
+            parameters,
+            evaluate_piece_squares,
+        ),
+        EvaluationTerm {
+            name: "Pawn structure",
+            white: evaluate_pawns_of_colour(&white_pawns, &black_pawns, &Colour::White, parameters),
+            black: evaluate_pawns_of_colour(&black_pawns, &white_pawns, &Colour::Black, parameters),
+        },
+        EvaluationTerm {
+            name: "Passed pawn paths",
+            white: evaluate_passed_pawn_paths(position, &passed_pawns, &Colour::White, parameters),
+            black: evaluate_passed_pawn_paths(position, &passed_pawns, &Colour::Black, parameters),
+        },
+        trace_term("Mobility", position, parameters, evaluate_mobility),
+        trace_term("Rook files", position, parameters, evaluate_rook_files),
+        trace_term(
+            "Rooks on seventh",
+            position,
+            parameters,
+            evaluate_rooks_on_seventh,
+        ),
+        trace_term("King attacks", position, parameters, evaluate_king_attacks),
+        trace_term("Pawn shield", position, parameters, evaluate_pawn_shield),
+        trace_term("King files", position, parameters, evaluate_king_files),
+    ];
+    EvaluationTrace {
+        terms,
+        phase: get_phase(position),
+    }
+}
+
+fn write_row(f: &mut fmt::Formatter, name: &str, scores: &[Score; 3]) -> fmt::Result {
+    write!(f, "{name:<17}")?;
+    for score in scores {
+        write!(f, " | {:>6} {:>6}", score.mg, score.eg)?;
+    }
+    writeln!(f)
+}
+
+impl fmt::Display for EvaluationTrace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<17} | {:>13} | {:>13} | {:>13}",
+            "Term", "White", "Black", "Total"
+        )?;
+        writeln!(
+            f,
+            "{:<17} | {:>6} {:>6} | {:>6} {:>6} | {:>6} {:>6}",
+            "", "MG", "EG", "MG", "EG", "MG", "EG"
+        )?;
+        writeln!(f, "{}", "-".repeat(17 + 3 * 16))?;
+        for term in &self.terms {
+            write_row(f, term.name, &[term.white, term.black, term.total()])?;
+        }
+        writeln!(f, "{}", "-".repeat(17 + 3 * 16))?;
+        let white = self
+            .terms
+            .iter()
+            .fold(Score::default(), |total, term| total + term.white);
+        let black = self
+            .terms
+            .iter()
+            .fold(Score::default(), |total, term| total + term.black);
+        write_row(f, "Total", &[white, black, self.total()])?;
+        write!(
+            f,
+            "Phase {} of {MAX_PHASE}, tapered score {} (from white's side)",
+            self.phase,
+            format_score(&self.score())
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluation::evaluate_white_relative;
+    use crate::notation::fen_to_position;
+
+    #[test]
+    fn trace_adds_up_to_the_evaluation() {
+        let parameters = Parameters::default();
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "2r3k1/1R3ppp/8/3P4/8/8/5PPP/6K1 b - - 0 1",
+            "6k1/5pp1/7p/8/1P6/P7/6PP/6K1 w - - 0 1",
+            "4k3/8/8/8/8/8/4P3/4K3 b - - 0 1",
+            "5rk1/8/8/8/8/6q1/5PPP/6K1 w - - 0 1",
+            "8/5k2/8/2p5/2P5/8/5K2/8 w - - 0 1",
+        ] {
+            let position = fen_to_position(fen).unwrap();
+            assert_eq!(
+                trace_evaluation(&position, &parameters).score(),
+                evaluate_white_relative(&position, &parameters),
+                "{fen}"
+            );
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use crate::moves::Move;
+use crate::notation::move_to_san;
+use crate::pieces::Colour;
+use crate::position::Position;
+use crate::zobrist::get_hash;
+
+// how often a position with this hash appears among the last `plies_back`
+// earlier hashes, which are kept oldest first
+pub(crate) fn count_repetitions(earlier_hashes: &[u64], hash: &u64, plies_back: &usize) -> usize {
+    let start = earlier_hashes.len().saturating_sub(*plies_back);
+    earlier_hashes[start..]
+        .iter()
+        .filter(|earlier_hash| *earlier_hash == hash)
+        .count()
+}
+
+// a game keeps every position it has passed through, so undoing a move is
+// just stepping back to an earlier copy and redoing is stepping forward again
+#[derive(Clone)]
+pub struct Game {
+    positions: Vec<Position>,
+    moves: Vec<Move>,
+    san_moves: Vec<String>,
+    ply: usize,
+}
+
+impl Game {
+    pub fn new(position: &Position) -> Game {
+        Game {
+            positions: vec![*position],
+            moves: Vec::new(),
+            san_moves: Vec::new(),
+            ply: 0,
+        }
+    }
+
+    pub fn get_position(&self) -> &Position {
+        &self.positions[self.ply]
+    }
+
+    pub fn get_start_position(&self) -> &Position {
+        &self.positions[0]
+    }
+
+    pub fn get_ply(&self) -> usize {
+        self.ply
+    }
+
+    pub fn get_length(&self) -> usize {
+        self.moves.len()
+    }
+
+    pub fn is_at_latest_ply(&self) -> bool {
+        self.ply == self.moves.len()
+    }
+
+    pub fn get_moves(&self) -> &[Move] {
+        &self.moves[..self.ply]
+    }
+
+    pub fn get_all_moves(&self) -> &[Move] {
+        &self.moves
+    }
+
+    pub fn get_san_moves(&self) -> &[String] {
+        &self.san_moves
+    }
+
+    // hashes of the positions before the current one, oldest first, which
+    // is the history a search needs to spot repetitions
+    pub fn get_history(&self) -> Vec<u64> {
+        self.positions[..self.ply].iter().map(get_hash).collect()
+    }
+
+    pub fn is_threefold_repetition(&self) -> bool {
+        let position = self.get_position();
+        count_repetitions(
+            &self.get_history(),
+            &get_hash(position),
+            &(position.halfmove_clock as usize),
+        ) >= 2
+    }
+
+    // playing a move from an earlier ply replaces everything that came after it
+    pub fn make_move(&mut self, mv: &Move) {
+        let mut next_position = *self.get_position();
+        let san = move_to_san(&next_position, mv);
+        next_position.make_move(mv);
+
+        self.positions.truncate(self.ply + 1);
+        self.moves.truncate(self.ply);
+        self.san_moves.truncate(self.ply);
+
+        self.positions.push(next_position);
+        self.moves.push(*mv);
+        self.san_moves.push(san);
+        self.ply += 1;
+    }
+
+    pub fn undo(&mut self) -> bool {
+        if self.ply == 0 {
+            return false;
+        }
+        self.ply -= 1;
+        true
+    }
+
+    pub fn redo(&mut self) -> bool {
+        if self.is_at_latest_ply() {
+            return false;
+        }
+        self.ply += 1;
+        true
+    }
+
+    pub fn go_to_ply(&mut self, ply: &usize) -> Result<(), &'static str> {
+        if *ply > self.moves.len() {
+            return Err("There is no such ply in this game.");
+        }
+        self.ply = *ply;
+        Ok(())
+    }
+
+    // numbered move list, e.g. "1. e4 e5", with the move leading to the
+    // current ply bracketed
+    pub fn get_move_list_lines(&self) -> Vec<String> {
+        let start_position = self.get_start_position();
+        let mut lines: Vec<String> = Vec::new();
+        let mut move_number = start_position.fullmove_number;
+        let mut colour = start_position.turn;
+
+        for (index, san) in self.san_moves.iter().enumerate() {
+            let text = match index + 1 == self.ply {
+                true => format!("[{san}]"),
+                false => san.to_string(),
+            };
+            match colour {
+                Colour::White => lines.push(format!("{move_number}. {text}")),
+                Colour::Black => match (index, lines.last_mut()) {
+                    (0, _) | (_, None) => lines.push(format!("{move_number}... {text}")),
+                    (_, Some(line)) => line.push_str(&format!(" {text}")),
+                },
+            }
+            if colour == Colour::Black {
+                move_number += 1;
+            }
+            colour = !colour;
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::uci_to_move;
+    use crate::position::get_starting_position;
+
+    fn play(game: &mut Game, moves: &[&str]) {
+        for text in moves {
+            let mv = uci_to_move(game.get_position(), text).unwrap();
+            game.make_move(&mv);
+        }
+    }
+
+    #[test]
+    fn shuffling_knights_repeats_threefold() {
+        let mut game = Game::new(&get_starting_position());
+        play(&mut game, &["g1f3", "g8f6", "f3g1", "f6g8"]);
+        assert!(!game.is_threefold_repetition());
+        play(&mut game, &["g1f3", "g8f6", "f3g1"]);
+        assert!(!game.is_threefold_repetition());
+        play(&mut game, &["f6g8"]);
+        assert!(game.is_threefold_repetition());
+
+        // stepping back leaves only two occurrences
+        game.undo();
+        game.undo();
+        assert!(!game.is_threefold_repetition());
+        assert_eq!(game.get_history().len(), 6);
+    }
+
+    #[test]
+    fn repetitions_stop_at_the_last_pawn_move() {
+        let history = [1, 2, 1, 2];
+        assert_eq!(count_repetitions(&history, &1, &4), 2);
+        assert_eq!(count_repetitions(&history, &1, &2), 1);
+        assert_eq!(count_repetitions(&history, &1, &0), 0);
+        assert_eq!(count_repetitions(&history, &3, &10), 0);
+    }
+}
@@ -0,0 +1,146 @@
+use crate::evaluation::Score;
+use crate::move_generation::{generate_attacks, generate_king_attacks};
+use crate::parameters::{get_piece_index, Parameters};
+use crate::pawn_structure::get_relative_rank;
+use crate::pieces::{Class, Colour, Piece};
+use crate::position::{Position, FILE_A, FILE_H, FILE_MASKS};
+use crate::utils::{bitboard_to_index, pop_lsb};
+
+// attack units each piece brings when it hits the king zone
+pub const KNIGHT_ATTACK_UNITS: i32 = 2;
+pub const BISHOP_ATTACK_UNITS: i32 = 2;
+pub const ROOK_ATTACK_UNITS: i32 = 3;
+pub const QUEEN_ATTACK_UNITS: i32 = 5;
+
+// the danger grows much faster than the number of attack units, since each
+// extra attacker makes the others harder to parry
+#[rustfmt::skip]
+pub const KING_DANGER: [i32; 32] = [
+      0,   0,   1,   2,   4,   6,   9,  12,
+     16,  20,  25,  30,  36,  42,  49,  56,
+     64,  72,  81,  90, 100, 110, 121, 132,
+    144, 156, 169, 182, 196, 210, 225, 240,
+];
+
+pub const PAWN_SHIELD_CLOSE: Score = Score::new(12, 0);
+pub const PAWN_SHIELD_FAR: Score = Score::new(6, 0);
+pub const OPEN_FILE_NEAR_KING: Score = Score::new(-25, 0);
+pub const SEMI_OPEN_FILE_NEAR_KING: Score = Score::new(-12, 0);
+
+fn get_forward(bitboard: &u64, colour: &Colour) -> u64 {
+    match colour {
+        Colour::White => bitboard << 8,
+        Colour::Black => bitboard >> 8,
+    }
+}
+
+// the squares around the king plus the row in front of them
+pub fn get_king_zone(king_square: &u64, colour: &Colour) -> u64 {
+    let around_king = generate_king_attacks(king_square) | king_square;
+    around_king | get_forward(&around_king, colour)
+}
+
+// units from every enemy piece hitting the zone around the king, turned into
+// a penalty once more than one piece joins in
+pub fn evaluate_king_attacks(
+    position: &Position,
+    colour: &Colour,
+    parameters: &Parameters,
+) -> Score {
+    let king_square = position.get_king_square(colour);
+    if king_square == 0 {
+        return Score::default();
+    }
+    let zone = get_king_zone(&king_square, colour);
+    let occupancy = position.get_occupancy();
+
+    let mut attackers = 0;
+    let mut units = 0;
+    for class in [Class::Knight, Class::Bishop, Class::Rook, Class::Queen] {
+        let piece = Piece::new(&class, &!colour);
+        let mut pieces = position.get_bitboard(&piece);
+        while pieces != 0 {
+            let square = pop_lsb(&mut pieces);
+            let zone_attacks = generate_attacks(&piece, &square, &occupancy) & zone;
+            if zone_attacks != 0 {
+                attackers += 1;
+                units += parameters.attack_units[get_piece_index(&class)]
+                    + zone_attacks.count_ones() as i32;
+            }
+        }
+    }
+    if attackers < 2 {
+        return Score::default();
+    }
+
+    // every zone square the enemy covers at all, pawns and king included
+    units += (zone & position.get_attacks_of_colour(&!colour)).count_ones() as i32;
+    // tuned attack units may be negative, which must not wrap to the top
+    let danger = parameters.king_danger[units.clamp(0, KING_DANGER.len() as i32 - 1) as usize];
+    Score::new(-danger, -danger / 4)
+}
+
+// own pawns one or two steps in front of the king on its file and the files
+// beside it, while the king is still at home
+pub fn evaluate_pawn_shield(
+    position: &Position,
+    colour: &Colour,
+    parameters: &Parameters,
+) -> Score {
+    let king_square = position.get_king_square(colour);
+    if king_square == 0 || get_relative_rank(&king_square, colour) > 1 {
+        return Score::default();
+    }
+    let pawns = position.get_bitboard(&Piece::new(&Class::Pawn, colour));
+
+    let in_front = get_forward(&king_square, colour);
+    let close_row = in_front | ((in_front & !FILE_H) << 1) | ((in_front & !FILE_A) >> 1);
+    let far_row = get_forward(&close_row, colour);
+    let close_shield = pawns & close_row;
+    // a pawn two steps ahead only counts on a file without a closer one
+    let far_shield = pawns & far_row & !get_forward(&close_shield, colour);
+
+    parameters.pawn_shield_close * close_shield.count_ones() as i32
+        + parameters.pawn_shield_far * far_shield.count_ones() as i32
+}
+
+// files around the king without pawns give the enemy rooks a way in
+pub fn evaluate_king_files(position: &Position, colour: &Colour, parameters: &Parameters) -> Score {
+    let king_square = position.get_king_square(colour);
+    if king_square == 0 {
+        return Score::default();
+    }
+    let own_pawns = position.get_bitboard(&Piece::new(&Class::Pawn, colour));
+    let enemy_pawns = position.get_bitboard(&Piece::new(&Class::Pawn, &!colour));
+    let king_file = bitboard_to_index(&king_square) % 8;
+
+    let mut score = Score::default();
+    for file_mask in &FILE_MASKS[king_file.saturating_sub(1)..=(king_file + 1).min(7)] {
+        if own_pawns & file_mask == 0 {
+            score += match enemy_pawns & file_mask {
+                0 => parameters.open_file_near_king,
+                _ => parameters.semi_open_file_near_king,
+            };
+        }
+    }
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::fen_to_position;
+
+    #[test]
+    fn negative_attack_units_mean_no_danger() {
+        // a queen and rook bearing down on the castled king
+        let position = fen_to_position("5rk1/8/8/8/8/6q1/5PPP/6K1 w - - 0 1").unwrap();
+        let mut parameters = Parameters::default();
+        let attacked = evaluate_king_attacks(&position, &Colour::White, &parameters);
+        assert!(attacked.mg < 0);
+
+        parameters.attack_units = [-100; 4];
+        let attacked = evaluate_king_attacks(&position, &Colour::White, &parameters);
+        assert_eq!(attacked, Score::new(-KING_DANGER[0], -KING_DANGER[0] / 4));
+    }
+}
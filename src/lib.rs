@@ -0,0 +1,61 @@
+mod board;
+mod datagen;
+mod endgame_tables;
+mod errors;
+mod evaluation;
+mod evaluation_trace;
+mod game;
+mod king_safety;
+mod mate_search;
+mod mobility;
+pub mod move_generation;
+pub mod moves;
+mod nnue;
+pub mod notation;
+mod parameters;
+mod pawn_structure;
+mod pgn;
+mod pieces;
+mod polyglot;
+pub mod position;
+mod random;
+pub mod search;
+mod see;
+mod syzygy;
+mod time_management;
+mod transposition;
+mod tuning;
+mod utils;
+mod zobrist;
+
+pub use board::{get_input, print_board, RenderOptions, Theme};
+pub use datagen::{encode_record, format_record, play_game, DatagenSettings};
+pub use endgame_tables::{
+    generate_table, get_all_table_names, get_subtable_names, get_table_name, get_table_path,
+    parse_table_name, EndgameTables,
+};
+pub use errors::{
+    BookError, DatasetError, EndgameTableError, FenError, IllegalMoveReason, MoveError,
+    NetworkError, ParameterError, PgnError, PositionError, ProtocolError, SquareError,
+};
+pub use evaluation::evaluate;
+pub use evaluation_trace::trace_evaluation;
+pub use game::Game;
+pub use mate_search::find_mate;
+pub use moves::{Move, MoveKind};
+pub use nnue::Network;
+pub use parameters::{parse_parameters, Parameters};
+pub use pgn::{game_to_pgn, parse_pgn, parse_pgn_games};
+pub use pieces::{Class, Colour, Piece};
+pub use polyglot::{
+    encode_book, encode_book_move, get_polyglot_key, Book, BookEntry, BookSelection,
+};
+pub use position::Position;
+pub use random::Random;
+pub use see::see;
+pub use syzygy::Tablebases;
+pub use time_management::Clock;
+pub use transposition::TranspositionTable;
+pub use tuning::{
+    compute_error, find_scaling, parse_binary_dataset, parse_dataset, tune, TuningPosition,
+};
@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+
+use crate::move_generation::generate_legal_moves;
+use crate::moves::Move;
+use crate::position::Position;
+use crate::search::{is_capture, SearchSignals};
+use crate::zobrist::get_hash;
+
+// how many nodes to search between checks of the stop signal
+const STOP_CHECK_INTERVAL: u64 = 1024;
+// the most positions remembered as failing, which at around 16 bytes each
+// keeps a long search from using up the memory
+const MAX_FAILURES: usize = 1 << 20;
+
+#[derive(Clone, Debug)]
+pub struct MateResult {
+    // both sides' moves, the defence holding out as long as it can, or None
+    // if there is no forced mate within the moves asked for
+    pub line: Option<Vec<Move>>,
+    pub nodes: u64,
+    pub elapsed: Duration,
+    // stopped before it could finish, so a missing mate proves nothing
+    pub stopped: bool,
+}
+
+impl MateResult {
+    // the number of moves the attacking side needs to mate in
+    pub fn get_mate_length(&self) -> Option<usize> {
+        self.line.as_ref().map(|line| line.len().div_ceil(2))
+    }
+}
+
+struct MateSearcher<'a> {
+    signals: &'a SearchSignals,
+    deadline: Option<Instant>,
+    nodes: u64,
+    stopped: bool,
+    // for each position with the attacker to move, the most moves it has
+    // been shown to have no mate within
+    failures: HashMap<u64, u32>,
+}
+
+// checks first since they are what mates, then captures, which take away
+// defenders
+fn order_attacking_moves(position: &Position, moves: &mut [Move]) {
+    moves.sort_by_cached_key(|mv| {
+        let mut next_position = *position;
+        next_position.make_move(mv);
+        let gives_check = next_position.is_in_check(&next_position.turn);
+        (!gives_check, !is_capture(position, mv))
+    });
+}
+
+impl MateSearcher<'_> {
+    fn should_stop(&mut self) -> bool {
+        self.nodes += 1;
+        if self.nodes.is_multiple_of(STOP_CHECK_INTERVAL)
+            && (self.signals.stop.load(Ordering::Relaxed)
+                || self
+                    .deadline
+                    .is_some_and(|deadline| Instant::now() >= deadline))
+        {
+            self.stopped = true;
+        }
+        self.stopped
+    }
+
+    // the quickest mate for the side to move in at most the given number of
+    // moves. each length is tried in turn, which costs little since every
+    // length that failed before is remembered
+    fn find_attack(&mut self, position: &Position, moves: u32) -> Option<Vec<Move>> {
+        if self.should_stop() {
+            return None;
+        }
+        let hash = get_hash(position);
+        let failed_within = self.failures.get(&hash).copied().unwrap_or(0);
+        if failed_within >= moves {
+            return None;
+        }
+
+        let mut candidates = generate_legal_moves(position);
+        order_attacking_moves(position, &mut candidates);
+        for length in failed_within + 1..=moves {
+            for mv in &candidates {
+                let mut next_position = *position;
+                next_position.make_move(mv);
+                // the last move has to give check to mate
+                if length == 1 && !next_position.is_in_check(&next_position.turn) {
+                    continue;
+                }
+                if let Some(defence) = self.find_defence(&next_position, length - 1) {
+                    let mut line = vec![*mv];
+                    line.extend(defence);
+                    return Some(line);
+                }
+                if self.stopped {
+                    return None;
+                }
+            }
+            if self.failures.len() < MAX_FAILURES || self.failures.contains_key(&hash) {
+                self.failures.insert(hash, length);
+            }
+        }
+        None
+    }
+
+    // the longest the side to move can hold out when the attacker has the
+    // given number of moves left, or None if some move escapes mate
+    fn find_defence(&mut self, position: &Position, moves: u32) -> Option<Vec<Move>> {
+        if self.should_stop() {
+            return None;
+        }
+        let defences = generate_legal_moves(position);
+        if defences.is_empty() {
+            return match position.is_in_check(&position.turn) {
+                true => Some(Vec::new()),
+                false => None,
+            };
+        }
+        if moves == 0 {
+            return None;
+        }
+
+        let mut longest_line: Vec<Move> = Vec::new();
+        for mv in &defences {
+            let mut next_position = *position;
+            next_position.make_move(mv);
+            let line = self.find_attack(&next_position, moves)?;
+            if longest_line.is_empty() || line.len() + 1 > longest_line.len() {
+                longest_line = vec![*mv];
+                longest_line.extend(line);
+            }
+        }
+        Some(longest_line)
+    }
+}
+
+// proves a forced mate in at most the given number of moves by trying every
+// line of that length, which unlike the main search cannot be misled by
+// pruning or reductions. gives up once the time limit, if any, has passed
+pub fn find_mate(
+    position: &Position,
+    moves: &u32,
+    time_limit: &Option<Duration>,
+    signals: &SearchSignals,
+) -> MateResult {
+    let start_time = Instant::now();
+    let mut searcher = MateSearcher {
+        signals,
+        deadline: time_limit.map(|time_limit| start_time + time_limit),
+        nodes: 0,
+        stopped: false,
+        failures: HashMap::new(),
+    };
+    let line = searcher.find_attack(position, *moves);
+    MateResult {
+        line: line.filter(|_| !searcher.stopped),
+        nodes: searcher.nodes,
+        elapsed: start_time.elapsed(),
+        stopped: searcher.stopped,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::{fen_to_position, STARTING_FEN};
+
+    #[test]
+    fn finds_the_quickest_mate() {
+        // a back rank mate in one, found before any longer mate
+        let position = fen_to_position("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let result = find_mate(&position, &3, &None, &SearchSignals::default());
+        assert_eq!(result.get_mate_length(), Some(1));
+        assert!(!result.stopped);
+    }
+
+    #[test]
+    fn gives_up_after_the_time_limit() {
+        let position = fen_to_position(STARTING_FEN).unwrap();
+        let result = find_mate(
+            &position,
+            &4,
+            &Some(Duration::ZERO),
+            &SearchSignals::default(),
+        );
+        assert!(result.stopped);
+        assert!(result.line.is_none());
+        assert!(result.nodes <= STOP_CHECK_INTERVAL);
+    }
+}
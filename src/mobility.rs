@@ -0,0 +1,113 @@
+use crate::evaluation::Score;
+use crate::move_generation::{generate_attacks, generate_pawn_attacks_of_colour};
+use crate::parameters::{get_piece_index, Parameters};
+use crate::pawn_structure::get_relative_rank;
+use crate::pieces::{Class, Colour, Piece};
+use crate::position::{Position, FILE_MASKS, RANK_2, RANK_7};
+use crate::utils::{bitboard_to_index, pop_lsb};
+
+// per square a piece can move to, counted from the typical number so that an
+// average piece scores nothing
+pub const KNIGHT_MOBILITY: Score = Score::new(4, 4);
+pub const BISHOP_MOBILITY: Score = Score::new(5, 5);
+pub const ROOK_MOBILITY: Score = Score::new(2, 4);
+pub const QUEEN_MOBILITY: Score = Score::new(1, 2);
+const KNIGHT_TYPICAL_MOBILITY: i32 = 4;
+const BISHOP_TYPICAL_MOBILITY: i32 = 6;
+const ROOK_TYPICAL_MOBILITY: i32 = 7;
+const QUEEN_TYPICAL_MOBILITY: i32 = 13;
+
+pub const ROOK_ON_OPEN_FILE: Score = Score::new(25, 10);
+pub const ROOK_ON_SEMI_OPEN_FILE: Score = Score::new(12, 6);
+pub const ROOK_ON_SEVENTH_RANK: Score = Score::new(20, 30);
+
+fn get_typical_mobility(class: &Class) -> i32 {
+    match class {
+        Class::Knight => KNIGHT_TYPICAL_MOBILITY,
+        Class::Bishop => BISHOP_TYPICAL_MOBILITY,
+        Class::Rook => ROOK_TYPICAL_MOBILITY,
+        Class::Queen => QUEEN_TYPICAL_MOBILITY,
+        Class::Pawn | Class::King => 0,
+    }
+}
+
+// squares a piece can safely use: not blocked by its own side and not covered
+// by an enemy pawn
+pub fn get_mobility_area(position: &Position, colour: &Colour) -> u64 {
+    let enemy_pawns = position.get_bitboard(&Piece::new(&Class::Pawn, &!colour));
+    !position.get_colour_occupancy(colour)
+        & !generate_pawn_attacks_of_colour(&enemy_pawns, &!colour)
+}
+
+pub fn evaluate_mobility_of_class(
+    position: &Position,
+    class: &Class,
+    colour: &Colour,
+    parameters: &Parameters,
+) -> Score {
+    let weight = parameters.mobility[get_piece_index(class)];
+    let typical_mobility = get_typical_mobility(class);
+    let piece = Piece::new(class, colour);
+    let occupancy = position.get_occupancy();
+    let mobility_area = get_mobility_area(position, colour);
+
+    let mut score = Score::default();
+    let mut pieces = position.get_bitboard(&piece);
+    while pieces != 0 {
+        let square = pop_lsb(&mut pieces);
+        let mobility = (generate_attacks(&piece, &square, &occupancy) & mobility_area).count_ones();
+        score += weight * (mobility as i32 - typical_mobility);
+    }
+    score
+}
+
+pub fn evaluate_mobility(position: &Position, colour: &Colour, parameters: &Parameters) -> Score {
+    [Class::Knight, Class::Bishop, Class::Rook, Class::Queen]
+        .iter()
+        .fold(Score::default(), |score, class| {
+            score + evaluate_mobility_of_class(position, class, colour, parameters)
+        })
+}
+
+// open files have no pawns at all, semi-open ones only the enemy's
+pub fn evaluate_rook_files(position: &Position, colour: &Colour, parameters: &Parameters) -> Score {
+    let own_pawns = position.get_bitboard(&Piece::new(&Class::Pawn, colour));
+    let enemy_pawns = position.get_bitboard(&Piece::new(&Class::Pawn, &!colour));
+
+    let mut score = Score::default();
+    let mut rooks = position.get_bitboard(&Piece::new(&Class::Rook, colour));
+    while rooks != 0 {
+        let square = pop_lsb(&mut rooks);
+        let file_mask = FILE_MASKS[bitboard_to_index(&square) % 8];
+        if own_pawns & file_mask == 0 {
+            score += match enemy_pawns & file_mask {
+                0 => parameters.rook_on_open_file,
+                _ => parameters.rook_on_semi_open_file,
+            };
+        }
+    }
+    score
+}
+
+// a rook on the seventh only matters while it has pawns to eat there or keeps
+// the enemy king shut in on the back rank
+pub fn evaluate_rooks_on_seventh(
+    position: &Position,
+    colour: &Colour,
+    parameters: &Parameters,
+) -> Score {
+    let enemy_pawns = position.get_bitboard(&Piece::new(&Class::Pawn, &!colour));
+    let enemy_king = position.get_king_square(&!colour);
+    let seventh_rank = match colour {
+        Colour::White => RANK_7,
+        Colour::Black => RANK_2,
+    };
+    let has_targets = enemy_pawns & seventh_rank != 0
+        || (enemy_king != 0 && get_relative_rank(&enemy_king, &!colour) == 0);
+    if !has_targets {
+        return Score::default();
+    }
+
+    let rooks = position.get_bitboard(&Piece::new(&Class::Rook, colour)) & seventh_rank;
+    parameters.rook_on_seventh_rank * rooks.count_ones() as i32
+}
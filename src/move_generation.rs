@@ -1,9 +1,11 @@
-use crate::pieces::{Class, Colour};
-use crate::position::{self, Position};
+use crate::errors::IllegalMoveReason;
+use crate::moves::{Move, MoveKind};
+use crate::pieces::{Class, Colour, Piece};
+use crate::position::Position;
 use crate::position::{FILES_AB, FILES_GH, FILE_A, FILE_H, RANK_1, RANK_2, RANK_7, RANK_8};
-use crate::utils::{bit_scan, index_to_algebraic};
+use crate::utils::{bitboard_to_index, pop_lsb};
 
-pub enum Direction {
+pub(crate) enum Direction {
     North,
     East,
     South,
@@ -14,33 +16,29 @@ pub enum Direction {
     NorthWest,
 }
 
-pub enum KnightDirection {
-    NorthNorthEast,
-    EastNorthEast,
-    EastSouthEast,
-    SouthSouthEast,
-    SouthSouthWest,
-    WestSouthWest,
-    WestNorthWest,
-    NorthNorthWest,
-}
+pub(crate) const ROOK_DIRECTIONS: [Direction; 4] = [
+    Direction::North,
+    Direction::East,
+    Direction::South,
+    Direction::West,
+];
 
-pub fn check_if_square_obstructed(
-    position: &Position,
-    square: &u64,
-    friendly_colour: &Colour,
-) -> bool {
-    let occupancy = position.get_colour_occupancy(friendly_colour);
-    square & occupancy != 0
-}
+pub(crate) const BISHOP_DIRECTIONS: [Direction; 4] = [
+    Direction::NorthEast,
+    Direction::SouthEast,
+    Direction::SouthWest,
+    Direction::NorthWest,
+];
+
+const PROMOTION_CLASSES: [Class; 4] = [Class::Queen, Class::Rook, Class::Bishop, Class::Knight];
 
-pub fn is_at_edge_in_direction(direction: &Direction, square: &u64) -> bool {
+pub(crate) fn is_at_edge_in_direction(direction: &Direction, square: &u64) -> bool {
     let is_on_file_a = square & FILE_A != 0;
     let is_on_file_h = square & FILE_H != 0;
     let is_on_rank_1 = square & RANK_1 != 0;
     let is_on_rank_8 = square & RANK_8 != 0;
 
-    let is_at_edge = match direction {
+    match direction {
         Direction::North => is_on_rank_8,
         Direction::East => is_on_file_h,
         Direction::South => is_on_rank_1,
@@ -49,119 +47,175 @@ pub fn is_at_edge_in_direction(direction: &Direction, square: &u64) -> bool {
         Direction::SouthEast => is_on_rank_1 || is_on_file_h,
         Direction::SouthWest => is_on_rank_1 || is_on_file_a,
         Direction::NorthWest => is_on_rank_8 || is_on_file_a,
-    };
-
-    return is_at_edge;
+    }
 }
 
-pub fn step_in_direction(direction: &Direction, square: &u64) -> u64 {
-    let mask = square.clone();
+pub(crate) fn step_in_direction(direction: &Direction, square: &u64) -> u64 {
+    let mask = *square;
     match direction {
-        Direction::North => return mask << 8,
-        Direction::East => return mask << 1,
-        Direction::South => return mask >> 8,
-        Direction::West => return mask >> 1,
-        Direction::NorthEast => return mask << 9,
-        Direction::SouthEast => return mask >> 7,
-        Direction::SouthWest => return mask >> 9,
-        Direction::NorthWest => return mask << 7,
-    };
+        Direction::North => mask << 8,
+        Direction::East => mask << 1,
+        Direction::South => mask >> 8,
+        Direction::West => mask >> 1,
+        Direction::NorthEast => mask << 9,
+        Direction::SouthEast => mask >> 7,
+        Direction::SouthWest => mask >> 9,
+        Direction::NorthWest => mask << 7,
+    }
 }
 
-pub fn generate_sliding_moves(
-    moves: &mut u64,
-    directions: Vec<Direction>,
-    travel_limit: u8,
-    position: &Position,
-    root_square: &u64,
-    friendly_colour: &Colour,
-) {
-    for direction in directions {
-        let mut current_square = root_square.clone();
-        let mut travel_distance: u8 = 0;
-        let mut was_previous_capture = false;
-        let mut was_previous_edge = false;
-        while {
-            // don't check for obstructed square on own square
-            let mut is_square_obstructed = false;
-            if travel_distance != 0 {
-                is_square_obstructed =
-                    check_if_square_obstructed(&position, &current_square, &friendly_colour);
-            }
+// the direction of a straight line from one square to the other, if they
+// share a rank, file or diagonal
+pub(crate) fn get_direction_between(from: &u64, to: &u64) -> Option<Direction> {
+    let from_index = bitboard_to_index(from) as i32;
+    let to_index = bitboard_to_index(to) as i32;
+    let file_difference = to_index % 8 - from_index % 8;
+    let rank_difference = to_index / 8 - from_index / 8;
+    let is_diagonal = file_difference.abs() == rank_difference.abs();
+    if from == to || (file_difference != 0 && rank_difference != 0 && !is_diagonal) {
+        return None;
+    }
+    match (file_difference.signum(), rank_difference.signum()) {
+        (0, 1) => Some(Direction::North),
+        (1, 0) => Some(Direction::East),
+        (0, -1) => Some(Direction::South),
+        (-1, 0) => Some(Direction::West),
+        (1, 1) => Some(Direction::NorthEast),
+        (1, -1) => Some(Direction::SouthEast),
+        (-1, -1) => Some(Direction::SouthWest),
+        (-1, 1) => Some(Direction::NorthWest),
+        _ => None,
+    }
+}
 
-            let is_over_travel_limit = travel_distance > travel_limit;
-
-            !is_square_obstructed
-                && !is_over_travel_limit
-                && !was_previous_capture
-                && !was_previous_edge
-        } {
-            // don't want to allow moving to the same square,
-            // but also need to start algorithm here in case the
-            // root square is on an edge
-            if current_square != *root_square {
-                *moves |= current_square;
-            }
-            if let Some(target_piece) = position.get_piece_at(&current_square) {
-                was_previous_capture = friendly_colour != &target_piece.colour()
-            }
-            was_previous_edge = is_at_edge_in_direction(&direction, &current_square);
-            current_square = step_in_direction(&direction, &current_square);
-            travel_distance += 1;
+pub(crate) fn is_diagonal_direction(direction: &Direction) -> bool {
+    matches!(
+        direction,
+        Direction::NorthEast | Direction::SouthEast | Direction::SouthWest | Direction::NorthWest
+    )
+}
+
+// walks from the root square until the edge of the board or the first
+// occupied square, which is included so captures can be masked in later
+pub(crate) fn generate_ray_attacks(direction: &Direction, square: &u64, occupancy: &u64) -> u64 {
+    let mut attacks: u64 = 0b0;
+    let mut current_square = *square;
+    while !is_at_edge_in_direction(direction, &current_square) {
+        current_square = step_in_direction(direction, &current_square);
+        attacks |= current_square;
+        if current_square & occupancy != 0 {
+            break;
         }
     }
+    attacks
+}
+
+pub(crate) fn generate_sliding_attacks(
+    directions: &[Direction],
+    square: &u64,
+    occupancy: &u64,
+) -> u64 {
+    let mut attacks: u64 = 0b0;
+    for direction in directions {
+        attacks |= generate_ray_attacks(direction, square, occupancy);
+    }
+    attacks
+}
+
+pub fn generate_bishop_attacks(square: &u64, occupancy: &u64) -> u64 {
+    generate_sliding_attacks(&BISHOP_DIRECTIONS, square, occupancy)
 }
 
+pub fn generate_rook_attacks(square: &u64, occupancy: &u64) -> u64 {
+    generate_sliding_attacks(&ROOK_DIRECTIONS, square, occupancy)
+}
+
+pub fn generate_queen_attacks(square: &u64, occupancy: &u64) -> u64 {
+    generate_bishop_attacks(square, occupancy) | generate_rook_attacks(square, occupancy)
+}
+
+pub fn generate_knight_attacks(knights: &u64) -> u64 {
+    let mut attacks: u64 = 0b0;
+    // NorthNorthEast --> NorthNorthWest
+    attacks |= (knights & !FILE_H) << 17;
+    attacks |= (knights & !FILES_GH) << 10;
+    attacks |= (knights & !FILES_GH) >> 6;
+    attacks |= (knights & !FILE_H) >> 15;
+    attacks |= (knights & !FILE_A) << 15;
+    attacks |= (knights & !FILES_AB) << 6;
+    attacks |= (knights & !FILES_AB) >> 10;
+    attacks |= (knights & !FILE_A) >> 17;
+    attacks
+}
+
+pub fn generate_king_attacks(kings: &u64) -> u64 {
+    // include root square so we can calc directly north and south squares
+    let mut attacks = *kings;
+    let east: u64 = (kings & !FILE_H) << 1;
+    let west: u64 = (kings & !FILE_A) >> 1;
+    attacks |= east | west;
+    let north_bloc = attacks << 8;
+    let south_bloc = attacks >> 8;
+    attacks |= north_bloc | south_bloc;
+    attacks & !kings
+}
+
+pub fn generate_pawn_attacks_of_colour(pawns: &u64, colour: &Colour) -> u64 {
+    let east_attacks = match colour {
+        Colour::White => (pawns & !FILE_H) << 9,
+        Colour::Black => (pawns & !FILE_H) >> 7,
+    };
+    let west_attacks = match colour {
+        Colour::White => (pawns & !FILE_A) << 7,
+        Colour::Black => (pawns & !FILE_A) >> 9,
+    };
+    east_attacks | west_attacks
+}
 
 // TODO: D.R.Y. in the code for calculating pawn pushes
 // fn generate_pawn_push()
 
-pub fn generate_pawn_attacks(
-    position: &Position,
-    pawns: &u64,
-) -> u64 {
+pub fn generate_pawn_attacks(position: &Position, pawns: &u64) -> u64 {
     let pawn_is_white = pawns & position.get_colour_occupancy(&Colour::White) != 0;
     let friendly_colour = match pawn_is_white {
         true => Colour::White,
-        false => Colour::Black
+        false => Colour::Black,
     };
+    generate_pawn_attacks_of_colour(pawns, &friendly_colour)
+}
 
-    let east_attacks = match friendly_colour {
-        Colour::White => (pawns & !FILE_H) << 9,
-        Colour::Black => (pawns & !FILE_H) >> 7,
+pub fn generate_pawn_pushes(pawns: &u64, colour: &Colour, occupancy: &u64) -> (u64, u64) {
+    let first_push: u64 = match colour {
+        Colour::White => (pawns << 8) & !occupancy,
+        Colour::Black => (pawns >> 8) & !occupancy,
     };
-    let west_attacks = match friendly_colour {
-        Colour::White => (pawns & !FILE_A) << 7,
-        Colour::Black => (pawns & !FILE_A) >> 9,
+    let second_push: u64 = match colour {
+        Colour::White => ((first_push & (RANK_2 << 8)) << 8) & !occupancy,
+        Colour::Black => ((first_push & (RANK_7 >> 8)) >> 8) & !occupancy,
     };
-    let attacks = east_attacks | west_attacks;
-    attacks
+    (first_push, second_push)
+}
+
+pub fn generate_attacks(piece: &Piece, square: &u64, occupancy: &u64) -> u64 {
+    match piece.class() {
+        Class::Pawn => generate_pawn_attacks_of_colour(square, &piece.colour()),
+        Class::Knight => generate_knight_attacks(square),
+        Class::Bishop => generate_bishop_attacks(square, occupancy),
+        Class::Rook => generate_rook_attacks(square, occupancy),
+        Class::Queen => generate_queen_attacks(square, occupancy),
+        Class::King => generate_king_attacks(square),
+    }
 }
 
 pub fn generate_moves(position: &Position, square: &u64) -> u64 {
     let mut moves: u64 = 0b0;
 
     if let Some(piece) = position.get_piece_at(square) {
+        let occupancy = position.get_occupancy();
         match piece.class() {
             Class::Pawn => {
-                let mut first_push: u64 = match piece.colour() {
-                    Colour::White => square << 8,
-                    Colour::Black => square >> 8,
-                };
-                first_push &= !position.get_occupancy();
-
-                let pawn_on_home_row = match piece.colour() {
-                    Colour::White => square & RANK_2 != 0,
-                    Colour::Black => square & RANK_7 != 0,
-                };
-                let mut second_push: u64 = 0b0;
-                if pawn_on_home_row {
-                    second_push = match piece.colour() {
-                        Colour::White => first_push << 8,
-                        Colour::Black => first_push >> 8,
-                    };
-                    second_push &= !position.get_occupancy();
-                }
+                let (first_push, second_push) =
+                    generate_pawn_pushes(square, &piece.colour(), &occupancy);
                 moves |= first_push | second_push;
 
                 let mut attacks = generate_pawn_attacks(position, square);
@@ -170,84 +224,293 @@ pub fn generate_moves(position: &Position, square: &u64) -> u64 {
 
                 moves |= attacks
             }
-            Class::Knight => {
-                // NorthNorthEast --> NorthNorthWest
-                moves |= (square & !FILE_H) << 17;
-                moves |= (square & !FILES_GH) << 10;
-                moves |= (square & !FILES_GH) >> 6;
-                moves |= (square & !FILE_H) >> 15;
-                moves |= (square & !FILE_A) << 15;
-                moves |= (square & !FILES_AB) << 6;
-                moves |= (square & !FILES_AB) >> 10;
-                moves |= (square & !FILE_A) >> 17;
+            _ => {
+                moves = generate_attacks(piece, square, &occupancy);
 
+                // removing occupied squares will remove root_square
                 let occupied_squares = position.get_colour_occupancy(&piece.colour());
                 moves &= !occupied_squares;
             }
-            Class::Bishop => {
-                generate_sliding_moves(
-                    &mut moves,
-                    vec![
-                        Direction::NorthEast,
-                        Direction::SouthEast,
-                        Direction::SouthWest,
-                        Direction::NorthWest,
-                    ],
-                    7,
-                    position,
-                    square,
-                    &piece.colour(),
-                );
-            }
-            Class::Rook => {
-                generate_sliding_moves(
-                    &mut moves,
-                    vec![
-                        Direction::North,
-                        Direction::East,
-                        Direction::South,
-                        Direction::West,
-                    ],
-                    7,
-                    position,
-                    square,
-                    &piece.colour(),
-                );
+        }
+    }
+    moves
+}
+
+fn push_pawn_moves(moves: &mut Vec<Move>, origin_square: &u64, destinations: &u64) {
+    let mut destinations = *destinations;
+    while destinations != 0 {
+        let destination_square = pop_lsb(&mut destinations);
+        if destination_square & (RANK_1 | RANK_8) != 0 {
+            for class in PROMOTION_CLASSES {
+                let mut promotion = Move::new(origin_square, &destination_square);
+                promotion.promotion = Some(class);
+                moves.push(promotion);
             }
-            Class::Queen => {
-                generate_sliding_moves(
-                    &mut moves,
-                    vec![
-                        Direction::North,
-                        Direction::East,
-                        Direction::South,
-                        Direction::West,
-                        Direction::NorthEast,
-                        Direction::SouthEast,
-                        Direction::SouthWest,
-                        Direction::NorthWest,
-                    ],
-                    7,
-                    position,
-                    square,
-                    &piece.colour(),
-                );
+        } else {
+            moves.push(Move::new(origin_square, &destination_square));
+        }
+    }
+}
+
+fn generate_castling_moves(moves: &mut Vec<Move>, position: &Position) {
+    let colour = position.turn;
+    let back_rank = match colour {
+        Colour::White => RANK_1,
+        Colour::Black => RANK_8,
+    };
+    let king_square = position.get_king_square(&colour);
+    if king_square & back_rank == 0 || position.is_in_check(&colour) {
+        return;
+    }
+
+    let mut rooks = position.castling_rights
+        & back_rank
+        & position.get_bitboard(&Piece::new(&Class::Rook, &colour));
+    while rooks != 0 {
+        let rook_square = pop_lsb(&mut rooks);
+        let (king_destination, rook_destination) =
+            position.get_castling_destinations(&king_square, &rook_square);
+
+        // every square either piece passes over must be empty, apart from the
+        // castling king and rook themselves
+        let occupancy = position.get_occupancy() & !king_square & !rook_square;
+        let king_path = squares_between_inclusive(&king_square, &king_destination);
+        let rook_path = squares_between_inclusive(&rook_square, &rook_destination);
+        if (king_path | rook_path) & occupancy != 0 {
+            continue;
+        }
+
+        let mut is_path_attacked = false;
+        let mut path = king_path;
+        while path != 0 {
+            let square = pop_lsb(&mut path);
+            if position.is_square_attacked(&square, &!colour, &occupancy) {
+                is_path_attacked = true;
+                break;
             }
-            Class::King => {
-                // include root_square so we can calc directly north and south squares
-                moves = *square;
-                let east: u64 = (square & !FILE_H) << 1;
-                let west: u64 = (square & !FILE_A) >> 1;
-                moves |= east | west;
-                let north_bloc = moves << 8;
-                let south_bloc = moves >> 8;
-                moves |= north_bloc | south_bloc;
+        }
+        if is_path_attacked {
+            continue;
+        }
 
-                // removing occupied squares will remove root_square
-                let occupied_squares = position.get_colour_occupancy(&piece.colour());
-                moves &= !occupied_squares;
+        moves.push(Move {
+            origin_square: king_square,
+            destination_square: rook_square,
+            promotion: None,
+            kind: MoveKind::Castle,
+        });
+    }
+}
+
+// squares on the same rank from one square to another, both ends included
+pub(crate) fn squares_between_inclusive(from: &u64, to: &u64) -> u64 {
+    let (low, high) = if from < to {
+        (*from, *to)
+    } else {
+        (*to, *from)
+    };
+    (high | (high - 1)) & !(low - 1)
+}
+
+pub fn generate_pseudo_legal_moves(position: &Position) -> Vec<Move> {
+    let mut moves: Vec<Move> = Vec::with_capacity(64);
+    let colour = position.turn;
+    let occupancy = position.get_occupancy();
+    let friendly_occupancy = position.get_colour_occupancy(&colour);
+    let enemy_occupancy = position.get_colour_occupancy(&!colour);
+
+    for class in Class::iter() {
+        let piece = Piece::new(class, &colour);
+        let mut pieces = position.get_bitboard(&piece);
+        while pieces != 0 {
+            let origin_square = pop_lsb(&mut pieces);
+            match class {
+                Class::Pawn => {
+                    let (first_push, second_push) =
+                        generate_pawn_pushes(&origin_square, &colour, &occupancy);
+                    let captures =
+                        generate_pawn_attacks_of_colour(&origin_square, &colour) & enemy_occupancy;
+                    push_pawn_moves(&mut moves, &origin_square, &(first_push | captures));
+                    if second_push != 0 {
+                        moves.push(Move {
+                            origin_square,
+                            destination_square: second_push,
+                            promotion: None,
+                            kind: MoveKind::DoublePawnPush,
+                        });
+                    }
+                    let en_passant = generate_pawn_attacks_of_colour(&origin_square, &colour)
+                        & position.en_passant_square;
+                    if en_passant != 0 {
+                        moves.push(Move {
+                            origin_square,
+                            destination_square: en_passant,
+                            promotion: None,
+                            kind: MoveKind::EnPassant,
+                        });
+                    }
+                }
+                _ => {
+                    let mut destinations =
+                        generate_attacks(&piece, &origin_square, &occupancy) & !friendly_occupancy;
+                    while destinations != 0 {
+                        let destination_square = pop_lsb(&mut destinations);
+                        moves.push(Move::new(&origin_square, &destination_square));
+                    }
+                }
             }
         }
     }
+    generate_castling_moves(&mut moves, position);
     moves
 }
+
+pub(crate) fn is_legal_pseudo_legal_move(position: &Position, mv: &Move) -> bool {
+    let mut next_position = *position;
+    next_position.make_move(mv);
+    !next_position.is_in_check(&position.turn)
+}
+
+pub fn generate_legal_moves(position: &Position) -> Vec<Move> {
+    generate_pseudo_legal_moves(position)
+        .into_iter()
+        .filter(|mv| is_legal_pseudo_legal_move(position, mv))
+        .collect()
+}
+
+// finds the legal move matching the given squares and promotion, or explains
+// why there is none
+pub fn find_legal_move(position: &Position, mv: &Move) -> Result<Move, IllegalMoveReason> {
+    let piece = match position.get_piece_at(&mv.origin_square) {
+        Some(piece) => *piece,
+        None => return Err(IllegalMoveReason::NoPieceOnSquare),
+    };
+    if piece.colour() != position.turn {
+        return Err(IllegalMoveReason::NotYourPiece);
+    }
+
+    let pseudo_legal_moves = generate_pseudo_legal_moves(position);
+    let mut candidates: Vec<Move> = pseudo_legal_moves
+        .iter()
+        .filter(|other| {
+            other.origin_square == mv.origin_square
+                && other.destination_square == mv.destination_square
+        })
+        .copied()
+        .collect();
+    // castling may also be given as the king's destination, as long as that is
+    // not an ordinary king move
+    if candidates.is_empty() && mv.promotion.is_none() {
+        candidates = pseudo_legal_moves
+            .iter()
+            .filter(|other| {
+                other.is_castle()
+                    && other.origin_square == mv.origin_square
+                    && position
+                        .get_castling_destinations(&other.origin_square, &other.destination_square)
+                        .0
+                        == mv.destination_square
+            })
+            .copied()
+            .collect();
+    }
+    if candidates.is_empty() {
+        let origin_index = mv.origin_square.trailing_zeros();
+        let destination_index = mv.destination_square.trailing_zeros();
+        let is_castle_attempt = piece.class() == Class::King
+            && (position.get_piece_with_colour_at(&mv.destination_square, &position.turn)
+                == Some(&Piece::new(&Class::Rook, &position.turn))
+                || (origin_index / 8 == destination_index / 8
+                    && origin_index.abs_diff(destination_index) == 2));
+        return match is_castle_attempt {
+            true => Err(IllegalMoveReason::CastlingNotAllowed),
+            false => Err(IllegalMoveReason::UnreachableSquare),
+        };
+    }
+
+    let candidate = match candidates
+        .iter()
+        .find(|other| other.promotion == mv.promotion)
+    {
+        Some(candidate) => *candidate,
+        None if mv.promotion.is_none() => return Err(IllegalMoveReason::PromotionRequired),
+        None => return Err(IllegalMoveReason::InvalidPromotion),
+    };
+    if !is_legal_pseudo_legal_move(position, &candidate) {
+        return Err(IllegalMoveReason::LeavesKingInCheck);
+    }
+    Ok(candidate)
+}
+
+pub fn perft(position: &Position, depth: &u8) -> u64 {
+    if *depth == 0 {
+        return 1;
+    }
+    let moves = generate_legal_moves(position);
+    if *depth == 1 {
+        return moves.len() as u64;
+    }
+    let mut nodes = 0;
+    for mv in moves {
+        let mut next_position = *position;
+        next_position.make_move(&mv);
+        nodes += perft(&next_position, &(depth - 1));
+    }
+    nodes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::{fen_to_position, STARTING_FEN};
+
+    fn assert_perft(fen: &str, expected_nodes: &[u64]) {
+        let position = fen_to_position(fen).unwrap();
+        for (depth, expected) in expected_nodes.iter().enumerate() {
+            let depth = depth as u8 + 1;
+            assert_eq!(
+                perft(&position, &depth),
+                *expected,
+                "{fen} at depth {depth}"
+            );
+        }
+    }
+
+    #[test]
+    fn perft_standard_positions() {
+        assert_perft(STARTING_FEN, &[20, 400, 8902, 197281, 4865609]);
+        // castling, en passant and promotion all through the middlegame
+        assert_perft(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            &[48, 2039, 97862, 4085603],
+        );
+        assert_perft(
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            &[14, 191, 2812, 43238, 674624],
+        );
+        assert_perft(
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            &[6, 264, 9467, 422333],
+        );
+        assert_perft(
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            &[44, 1486, 62379],
+        );
+        assert_perft(
+            "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+            &[46, 2079, 89890],
+        );
+    }
+
+    #[test]
+    fn perft_chess960_positions() {
+        assert_perft(
+            "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+            &[21, 528, 12189],
+        );
+        assert_perft(
+            "2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9",
+            &[21, 807, 18002],
+        );
+    }
+}
@@ -0,0 +1,34 @@
+use crate::pieces::Class;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MoveKind {
+    Normal,
+    DoublePawnPush,
+    EnPassant,
+    // castling is stored as the king capturing its own rook, so the same
+    // representation works for both standard chess and Chess960
+    Castle,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Move {
+    pub origin_square: u64,
+    pub destination_square: u64,
+    pub promotion: Option<Class>,
+    pub kind: MoveKind,
+}
+
+impl Move {
+    pub fn new(origin_square: &u64, destination_square: &u64) -> Move {
+        Move {
+            origin_square: *origin_square,
+            destination_square: *destination_square,
+            promotion: None,
+            kind: MoveKind::Normal,
+        }
+    }
+
+    pub fn is_castle(&self) -> bool {
+        self.kind == MoveKind::Castle
+    }
+}
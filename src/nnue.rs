@@ -0,0 +1,341 @@
+use std::fmt;
+
+use crate::errors::NetworkError;
+use crate::pieces::{Colour, Piece};
+use crate::position::Position;
+use crate::search::MAX_EVALUATION;
+use crate::utils::{bitboard_to_index, pop_lsb};
+
+// a HalfKA network: every piece on every square, seen from each side with
+// its own king's square, feeds one hidden layer per side. the two layers are
+// clipped, joined with the side to move first and summed into the score
+//
+// weight files are little endian:
+//   4 bytes   "NNUE"
+//   u32       format version, 1
+//   u32       hidden layer size H
+//   i16 x INPUT_SIZE * H   feature weights, H for each feature in turn
+//   i16 x H               hidden biases
+//   i16 x 2 * H           output weights, side to move then the other side
+//   i32                   output bias
+const MAGIC: &[u8; 4] = b"NNUE";
+const VERSION: u32 = 1;
+const HEADER_SIZE: usize = 12;
+
+// king square, piece from the side's point of view and the piece's square
+pub const INPUT_SIZE: usize = 64 * 12 * 64;
+// hidden values are clipped to 0..=ACTIVATION_MAX, which stands for 1.0
+const ACTIVATION_MAX: i32 = 255;
+// an output weight of OUTPUT_QUANTISATION stands for 1.0
+const OUTPUT_QUANTISATION: i64 = 64;
+// turns the network's output into centipawns
+const OUTPUT_SCALE: i64 = 400;
+
+pub struct Network {
+    hidden_size: usize,
+    feature_weights: Vec<i16>,
+    hidden_biases: Vec<i16>,
+    output_weights: Vec<i16>,
+    output_bias: i32,
+}
+
+impl fmt::Debug for Network {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Network {{ hidden_size: {} }}", self.hidden_size)
+    }
+}
+
+// the hidden layer of each side before clipping, kept up to date move by move
+// instead of being added up from every piece at each evaluation
+#[derive(Clone, Debug)]
+pub struct Accumulator {
+    white: Vec<i16>,
+    black: Vec<i16>,
+}
+
+impl Accumulator {
+    fn get_side_mut(&mut self, colour: &Colour) -> &mut Vec<i16> {
+        match colour {
+            Colour::White => &mut self.white,
+            Colour::Black => &mut self.black,
+        }
+    }
+
+    fn get_side(&self, colour: &Colour) -> &[i16] {
+        match colour {
+            Colour::White => &self.white,
+            Colour::Black => &self.black,
+        }
+    }
+}
+
+// squares are flipped for black so that both sides see their own pieces
+// coming up the board from rank 1
+fn get_relative_square(square: &u64, perspective: &Colour) -> usize {
+    let index = bitboard_to_index(square);
+    match perspective {
+        Colour::White => index,
+        Colour::Black => index ^ 56,
+    }
+}
+
+// own pieces pawn to king come first, then the other side's
+fn get_relative_piece_index(piece: &Piece, perspective: &Colour) -> usize {
+    let class_index = piece.class() as usize;
+    match piece.colour() == *perspective {
+        true => class_index,
+        false => 6 + class_index,
+    }
+}
+
+fn get_feature_index(
+    king_square: &u64,
+    piece: &Piece,
+    square: &u64,
+    perspective: &Colour,
+) -> usize {
+    let king_index = get_relative_square(king_square, perspective);
+    let piece_index = get_relative_piece_index(piece, perspective);
+    (king_index * 12 + piece_index) * 64 + get_relative_square(square, perspective)
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        bytes[offset],
+        bytes[offset + 1],
+        bytes[offset + 2],
+        bytes[offset + 3],
+    ])
+}
+
+fn read_i16s(bytes: &[u8], offset: usize, count: usize) -> Vec<i16> {
+    bytes[offset..offset + count * 2]
+        .chunks_exact(2)
+        .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+        .collect()
+}
+
+impl Network {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Network, NetworkError> {
+        if bytes.len() < HEADER_SIZE || &bytes[..4] != MAGIC {
+            return Err(NetworkError::NotANetwork);
+        }
+        let version = read_u32(bytes, 4);
+        if version != VERSION {
+            return Err(NetworkError::UnsupportedVersion(version));
+        }
+        let hidden_size = read_u32(bytes, 8) as usize;
+        if hidden_size == 0 {
+            return Err(NetworkError::EmptyHiddenLayer);
+        }
+
+        let expected_size = HEADER_SIZE + (INPUT_SIZE * hidden_size + 3 * hidden_size) * 2 + 4;
+        if bytes.len() != expected_size {
+            return Err(NetworkError::WrongSize(expected_size, bytes.len()));
+        }
+        let mut offset = HEADER_SIZE;
+        let feature_weights = read_i16s(bytes, offset, INPUT_SIZE * hidden_size);
+        offset += INPUT_SIZE * hidden_size * 2;
+        let hidden_biases = read_i16s(bytes, offset, hidden_size);
+        offset += hidden_size * 2;
+        let output_weights = read_i16s(bytes, offset, 2 * hidden_size);
+        offset += 2 * hidden_size * 2;
+        let output_bias = read_u32(bytes, offset) as i32;
+
+        Ok(Network {
+            hidden_size,
+            feature_weights,
+            hidden_biases,
+            output_weights,
+            output_bias,
+        })
+    }
+
+    pub fn hidden_size(&self) -> usize {
+        self.hidden_size
+    }
+
+    fn get_feature_weights(&self, feature_index: &usize) -> &[i16] {
+        let start = feature_index * self.hidden_size;
+        &self.feature_weights[start..start + self.hidden_size]
+    }
+
+    fn add_feature(&self, values: &mut [i16], feature_index: &usize) {
+        let weights = self.get_feature_weights(feature_index);
+        for (value, weight) in values.iter_mut().zip(weights) {
+            *value = value.wrapping_add(*weight);
+        }
+    }
+
+    fn remove_feature(&self, values: &mut [i16], feature_index: &usize) {
+        let weights = self.get_feature_weights(feature_index);
+        for (value, weight) in values.iter_mut().zip(weights) {
+            *value = value.wrapping_sub(*weight);
+        }
+    }
+
+    // works one side's hidden layer out again from every piece on the board
+    fn refresh_side(&self, position: &Position, perspective: &Colour, values: &mut Vec<i16>) {
+        values.clear();
+        values.extend_from_slice(&self.hidden_biases);
+        let king_square = position.get_king_square(perspective);
+        for piece in Piece::iter() {
+            let mut pieces = position.get_bitboard(piece);
+            while pieces != 0 {
+                let square = pop_lsb(&mut pieces);
+                let feature_index = get_feature_index(&king_square, piece, &square, perspective);
+                self.add_feature(values, &feature_index);
+            }
+        }
+    }
+
+    pub fn new_accumulator(&self, position: &Position) -> Accumulator {
+        let mut accumulator = Accumulator {
+            white: Vec::with_capacity(self.hidden_size),
+            black: Vec::with_capacity(self.hidden_size),
+        };
+        self.refresh(position, &mut accumulator);
+        accumulator
+    }
+
+    pub fn refresh(&self, position: &Position, accumulator: &mut Accumulator) {
+        for colour in [Colour::White, Colour::Black] {
+            self.refresh_side(position, &colour, accumulator.get_side_mut(&colour));
+        }
+    }
+
+    // brings the accumulator of a position one move on from the parent's by
+    // only adding and removing the pieces that changed. a side whose king
+    // moved sees every piece from a new square, so is worked out afresh
+    pub fn update(
+        &self,
+        parent: &Accumulator,
+        parent_position: &Position,
+        position: &Position,
+        accumulator: &mut Accumulator,
+    ) {
+        for perspective in [Colour::White, Colour::Black] {
+            let values = accumulator.get_side_mut(&perspective);
+            let king_square = position.get_king_square(&perspective);
+            if king_square != parent_position.get_king_square(&perspective) {
+                self.refresh_side(position, &perspective, values);
+                continue;
+            }
+
+            values.clear();
+            values.extend_from_slice(parent.get_side(&perspective));
+            for piece in Piece::iter() {
+                let before = parent_position.get_bitboard(piece);
+                let after = position.get_bitboard(piece);
+                let mut removed = before & !after;
+                while removed != 0 {
+                    let square = pop_lsb(&mut removed);
+                    let feature_index =
+                        get_feature_index(&king_square, piece, &square, &perspective);
+                    self.remove_feature(values, &feature_index);
+                }
+                let mut added = after & !before;
+                while added != 0 {
+                    let square = pop_lsb(&mut added);
+                    let feature_index =
+                        get_feature_index(&king_square, piece, &square, &perspective);
+                    self.add_feature(values, &feature_index);
+                }
+            }
+        }
+    }
+
+    // score in centipawns from the point of view of the side to move, held
+    // below the mate and tablebase scores however large the weights
+    pub fn evaluate(&self, accumulator: &Accumulator, turn: &Colour) -> i32 {
+        let (own_weights, other_weights) = self.output_weights.split_at(self.hidden_size);
+        let mut sum = self.output_bias as i64;
+        for (values, weights) in [
+            (accumulator.get_side(turn), own_weights),
+            (accumulator.get_side(&!turn), other_weights),
+        ] {
+            for (value, weight) in values.iter().zip(weights) {
+                let activation = (*value as i32).clamp(0, ACTIVATION_MAX) as i64;
+                sum += activation * *weight as i64;
+            }
+        }
+        let score = sum * OUTPUT_SCALE / (ACTIVATION_MAX as i64 * OUTPUT_QUANTISATION);
+        score.clamp(-MAX_EVALUATION as i64, MAX_EVALUATION as i64) as i32
+    }
+
+    // for a single position outside a search, where there is no parent to
+    // update from
+    pub fn evaluate_position(&self, position: &Position) -> i32 {
+        let accumulator = self.new_accumulator(position);
+        self.evaluate(&accumulator, &position.turn)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::{fen_to_position, uci_to_move};
+    use crate::position::get_starting_position;
+    use crate::random::Random;
+
+    // a network of one hidden value, which is always 0, so that the score is
+    // only the output bias
+    fn get_bias_network(output_bias: &i32) -> Network {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(VERSION.to_le_bytes());
+        bytes.extend(1u32.to_le_bytes());
+        bytes.extend(vec![0; 2 * (INPUT_SIZE + 3)]);
+        bytes.extend(output_bias.to_le_bytes());
+        Network::from_bytes(&bytes).unwrap()
+    }
+
+    // small weights, so that no sum of them can overflow
+    fn get_random_network(hidden_size: &usize) -> Network {
+        let mut random = Random::new(&0x5eed);
+        let mut get_weights = |count: usize| -> Vec<i16> {
+            (0..count)
+                .map(|_| random.below(&201) as i16 - 100)
+                .collect()
+        };
+        Network {
+            hidden_size: *hidden_size,
+            feature_weights: get_weights(INPUT_SIZE * hidden_size),
+            hidden_biases: get_weights(*hidden_size),
+            output_weights: get_weights(2 * hidden_size),
+            output_bias: 0,
+        }
+    }
+
+    #[test]
+    fn update_matches_a_fresh_accumulator() {
+        let network = get_random_network(&8);
+        let mut position = fen_to_position("r3k2r/6P1/8/8/3p4/8/4P3/R3K2R w KQkq - 0 1").unwrap();
+        let mut accumulator = network.new_accumulator(&position);
+        // en passant, castling on both wings, a capturing promotion, a
+        // capture and king moves
+        for text in [
+            "e2e4", "d4e3", "e1g1", "e8c8", "g7h8q", "d8h8", "g1g2", "c8b7",
+        ] {
+            let mv = uci_to_move(&position, text).unwrap();
+            let parent_position = position;
+            position.make_move(&mv);
+            let parent = accumulator.clone();
+            network.update(&parent, &parent_position, &position, &mut accumulator);
+
+            let expected = network.new_accumulator(&position);
+            assert_eq!(accumulator.white, expected.white, "white after {text}");
+            assert_eq!(accumulator.black, expected.black, "black after {text}");
+        }
+    }
+
+    #[test]
+    fn evaluate_stays_below_mate_scores() {
+        let position = get_starting_position();
+        let network = get_bias_network(&4080);
+        assert_eq!(network.evaluate_position(&position), 100);
+        let network = get_bias_network(&i32::MAX);
+        assert_eq!(network.evaluate_position(&position), MAX_EVALUATION);
+        let network = get_bias_network(&i32::MIN);
+        assert_eq!(network.evaluate_position(&position), -MAX_EVALUATION);
+    }
+}
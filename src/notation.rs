@@ -0,0 +1,680 @@
+use crate::board::FILES;
+use crate::errors::{FenError, IllegalMoveReason, MoveError, SquareError};
+use crate::move_generation::{find_legal_move, generate_legal_moves, generate_pseudo_legal_moves};
+use crate::moves::{Move, MoveKind};
+use crate::pieces::{Class, Colour, Piece};
+use crate::position::{get_empty_position, Position, RANK_1, RANK_8};
+use crate::utils::{algebraic_to_index, bitboard_to_index, index_to_algebraic, index_to_bitboard};
+
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+fn square_to_algebraic(square: &u64) -> String {
+    index_to_algebraic(&bitboard_to_index(square)).unwrap_or_else(|_| "-".to_string())
+}
+
+pub fn square_from_algebraic(algebraic: &str) -> Result<u64, SquareError> {
+    let index = algebraic_to_index(algebraic)?;
+    Ok(index_to_bitboard(&index))
+}
+
+fn class_from_char(c: &char) -> Result<Class, MoveError> {
+    match c.to_ascii_lowercase() {
+        'n' => Ok(Class::Knight),
+        'b' => Ok(Class::Bishop),
+        'r' => Ok(Class::Rook),
+        'q' => Ok(Class::Queen),
+        _ => Err(MoveError::InvalidPromotionPiece(*c)),
+    }
+}
+
+pub fn move_to_uci(position: &Position, mv: &Move, chess960: &bool) -> String {
+    let destination_square = if mv.is_castle() && !chess960 {
+        position
+            .get_castling_destinations(&mv.origin_square, &mv.destination_square)
+            .0
+    } else {
+        mv.destination_square
+    };
+
+    let mut uci = format!(
+        "{}{}",
+        square_to_algebraic(&mv.origin_square),
+        square_to_algebraic(&destination_square)
+    );
+    if let Some(class) = mv.promotion {
+        uci.push_str(&class.str().to_lowercase());
+    }
+    uci
+}
+
+pub fn uci_to_move(position: &Position, uci: &str) -> Result<Move, MoveError> {
+    if !uci.is_ascii() || uci.len() < 4 || uci.len() > 5 {
+        return Err(MoveError::InvalidNotation(uci.to_string()));
+    }
+    let origin_square = square_from_algebraic(&uci[0..2])?;
+    let destination_square = square_from_algebraic(&uci[2..4])?;
+    let promotion = match uci.chars().nth(4) {
+        Some(c) => Some(class_from_char(&c)?),
+        None => None,
+    };
+
+    let legal_moves = generate_legal_moves(position);
+
+    // an exact match wins, so that in Chess960 a one square king move is never
+    // mistaken for castling
+    let exact_match = legal_moves.iter().find(|mv| {
+        !mv.is_castle()
+            && mv.origin_square == origin_square
+            && mv.destination_square == destination_square
+            && mv.promotion == promotion
+    });
+    if let Some(mv) = exact_match {
+        return Ok(*mv);
+    }
+
+    // castling may be given either as the king's destination or as the king
+    // taking its own rook
+    let castle = legal_moves.iter().find(|mv| {
+        mv.is_castle()
+            && promotion.is_none()
+            && mv.origin_square == origin_square
+            && (mv.destination_square == destination_square
+                || position
+                    .get_castling_destinations(&mv.origin_square, &mv.destination_square)
+                    .0
+                    == destination_square)
+    });
+    if let Some(mv) = castle {
+        return Ok(*mv);
+    }
+
+    let requested_move = Move {
+        origin_square,
+        destination_square,
+        promotion,
+        kind: MoveKind::Normal,
+    };
+    Ok(find_legal_move(position, &requested_move)?)
+}
+
+// reads the castling field of a FEN against the pieces already on the board
+pub fn castling_rights_from_fen(position: &Position, castling: &str) -> Result<u64, FenError> {
+    let mut castling_rights: u64 = 0b0;
+    if castling == "-" {
+        return Ok(castling_rights);
+    }
+    for c in castling.chars() {
+        let colour = if c.is_ascii_uppercase() {
+            Colour::White
+        } else {
+            Colour::Black
+        };
+        let back_rank = match colour {
+            Colour::White => RANK_1,
+            Colour::Black => RANK_8,
+        };
+        let rooks = position.get_bitboard(&Piece::new(&Class::Rook, &colour)) & back_rank;
+        let king_square = position.get_king_square(&colour) & back_rank;
+        if king_square == 0 {
+            return Err(FenError::CastlingWithoutKing(c));
+        }
+
+        // KQkq refer to the outermost rook on each side, while Shredder and
+        // X-FEN style file letters name the rook directly
+        let rook_square = match c.to_ascii_lowercase() {
+            'k' => {
+                let kingside_rooks = rooks & !(king_square | (king_square - 1));
+                if kingside_rooks == 0 {
+                    0
+                } else {
+                    1 << (63 - kingside_rooks.leading_zeros())
+                }
+            }
+            'q' => {
+                let queenside_rooks = rooks & (king_square - 1);
+                queenside_rooks & queenside_rooks.wrapping_neg()
+            }
+            file @ 'a'..='h' => {
+                let file_index = file as u8 - b'a';
+                let rank_start = king_square.trailing_zeros() / 8 * 8;
+                rooks & (1 << (rank_start + file_index as u32))
+            }
+            _ => return Err(FenError::InvalidCastlingRight(c)),
+        };
+        if rook_square == 0 {
+            return Err(FenError::MissingCastlingRook(c));
+        }
+        castling_rights |= rook_square;
+    }
+    Ok(castling_rights)
+}
+
+pub fn fen_to_position(fen: &str) -> Result<Position, FenError> {
+    let fields: Vec<&str> = fen.split_whitespace().collect();
+    if fields.len() < 4 || fields.len() > 6 {
+        return Err(FenError::WrongFieldCount(fields.len()));
+    }
+
+    let mut position = get_empty_position();
+
+    let ranks: Vec<&str> = fields[0].split('/').collect();
+    if ranks.len() != 8 {
+        return Err(FenError::WrongRankCount(ranks.len()));
+    }
+    for (rank_offset, rank) in ranks.iter().enumerate() {
+        let rank_index = 7 - rank_offset;
+        let mut file_index: usize = 0;
+        for c in rank.chars() {
+            if let Some(empty_squares) = c.to_digit(10) {
+                file_index += empty_squares as usize;
+                continue;
+            }
+            let piece = match Piece::iter()
+                .iter()
+                .find(|piece| piece.str() == c.to_string())
+            {
+                Some(piece) => piece,
+                None => return Err(FenError::InvalidPiece(c)),
+            };
+            if file_index > 7 {
+                return Err(FenError::RankTooLong(rank_index + 1));
+            }
+            position
+                .insert_piece_at_square(piece, &index_to_bitboard(&(rank_index * 8 + file_index)));
+            file_index += 1;
+        }
+        if file_index > 8 {
+            return Err(FenError::RankTooLong(rank_index + 1));
+        }
+        if file_index < 8 {
+            return Err(FenError::RankTooShort(rank_index + 1));
+        }
+    }
+
+    position.turn = match fields[1] {
+        "w" => Colour::White,
+        "b" => Colour::Black,
+        turn => return Err(FenError::InvalidSideToMove(turn.to_string())),
+    };
+
+    position.castling_rights = castling_rights_from_fen(&position, fields[2])?;
+
+    if fields[3] != "-" {
+        position.en_passant_square =
+            square_from_algebraic(fields[3]).map_err(FenError::InvalidEnPassantSquare)?;
+    }
+
+    if let Some(halfmove_clock) = fields.get(4) {
+        position.halfmove_clock = match halfmove_clock.parse() {
+            Ok(halfmove_clock) => halfmove_clock,
+            Err(_) => return Err(FenError::InvalidHalfmoveClock(halfmove_clock.to_string())),
+        };
+    }
+    if let Some(fullmove_number) = fields.get(5) {
+        position.fullmove_number = match fullmove_number.parse() {
+            Ok(fullmove_number) => fullmove_number,
+            Err(_) => return Err(FenError::InvalidFullmoveNumber(fullmove_number.to_string())),
+        };
+    }
+
+    if let Err(problems) = position.validate() {
+        return Err(FenError::InvalidPosition(problems[0]));
+    }
+
+    Ok(position)
+}
+
+fn castling_rights_to_fen(position: &Position) -> String {
+    let mut castling = String::new();
+    for colour in [Colour::White, Colour::Black] {
+        let back_rank = match colour {
+            Colour::White => RANK_1,
+            Colour::Black => RANK_8,
+        };
+        let rooks = position.get_bitboard(&Piece::new(&Class::Rook, &colour)) & back_rank;
+        let king_square = position.get_king_square(&colour) & back_rank;
+        let rights = position.castling_rights & back_rank;
+        if king_square == 0 || rights == 0 {
+            continue;
+        }
+
+        // kingside before queenside, using KQkq where it is unambiguous
+        let kingside = rights & !(king_square | (king_square - 1));
+        let queenside = rights & (king_square - 1);
+        for (right, outermost, letter) in [
+            (
+                kingside,
+                kingside != 0 && rooks & !(kingside | (kingside - 1)) == 0,
+                'K',
+            ),
+            (
+                queenside,
+                queenside != 0 && rooks & (queenside - 1) == 0,
+                'Q',
+            ),
+        ] {
+            if right == 0 {
+                continue;
+            }
+            let c = if outermost {
+                letter
+            } else {
+                FILES[bitboard_to_index(&right) % 8].to_ascii_uppercase()
+            };
+            castling.push(match colour {
+                Colour::White => c,
+                Colour::Black => c.to_ascii_lowercase(),
+            });
+        }
+    }
+    if castling.is_empty() {
+        castling.push('-');
+    }
+    castling
+}
+
+pub fn position_to_fen(position: &Position) -> String {
+    let mut placement = String::new();
+    for rank_index in (0..8).rev() {
+        let mut empty_squares = 0;
+        for file_index in 0..8 {
+            let square = index_to_bitboard(&(rank_index * 8 + file_index));
+            match position.get_piece_at(&square) {
+                Some(piece) => {
+                    if empty_squares > 0 {
+                        placement.push_str(&empty_squares.to_string());
+                        empty_squares = 0;
+                    }
+                    placement.push_str(piece.str());
+                }
+                None => empty_squares += 1,
+            }
+        }
+        if empty_squares > 0 {
+            placement.push_str(&empty_squares.to_string());
+        }
+        if rank_index > 0 {
+            placement.push('/');
+        }
+    }
+
+    let turn = match position.turn {
+        Colour::White => "w",
+        Colour::Black => "b",
+    };
+    let en_passant = match position.en_passant_square {
+        0 => "-".to_string(),
+        square => square_to_algebraic(&square),
+    };
+
+    format!(
+        "{} {} {} {} {} {}",
+        placement,
+        turn,
+        castling_rights_to_fen(position),
+        en_passant,
+        position.halfmove_clock,
+        position.fullmove_number
+    )
+}
+
+pub fn move_to_san(position: &Position, mv: &Move) -> String {
+    let piece = match position.get_piece_at(&mv.origin_square) {
+        Some(piece) => *piece,
+        None => return move_to_uci(position, mv, &false),
+    };
+
+    let mut san = if mv.is_castle() {
+        match mv.destination_square > mv.origin_square {
+            true => "O-O".to_string(),
+            false => "O-O-O".to_string(),
+        }
+    } else {
+        let is_capture =
+            mv.kind == MoveKind::EnPassant || position.get_occupancy() & mv.destination_square != 0;
+        let origin = square_to_algebraic(&mv.origin_square);
+        let mut san = String::new();
+
+        if piece.class() == Class::Pawn {
+            if is_capture {
+                san.push_str(&origin[0..1]);
+            }
+        } else {
+            san.push_str(piece.class().str());
+
+            // only name as much of the origin square as is needed to tell
+            // apart pieces of the same type that could reach the same square
+            let rivals: Vec<Move> = generate_legal_moves(position)
+                .into_iter()
+                .filter(|other| {
+                    !other.is_castle()
+                        && other.destination_square == mv.destination_square
+                        && other.origin_square != mv.origin_square
+                        && position.get_piece_at(&other.origin_square) == Some(&piece)
+                })
+                .collect();
+            if !rivals.is_empty() {
+                let origin_index = bitboard_to_index(&mv.origin_square);
+                let shares_file = rivals
+                    .iter()
+                    .any(|other| bitboard_to_index(&other.origin_square) % 8 == origin_index % 8);
+                let shares_rank = rivals
+                    .iter()
+                    .any(|other| bitboard_to_index(&other.origin_square) / 8 == origin_index / 8);
+                if !shares_file {
+                    san.push_str(&origin[0..1]);
+                } else if !shares_rank {
+                    san.push_str(&origin[1..2]);
+                } else {
+                    san.push_str(&origin);
+                }
+            }
+        }
+
+        if is_capture {
+            san.push('x');
+        }
+        san.push_str(&square_to_algebraic(&mv.destination_square));
+        if let Some(class) = mv.promotion {
+            san.push('=');
+            san.push_str(class.str());
+        }
+        san
+    };
+
+    let mut next_position = *position;
+    next_position.make_move(mv);
+    if next_position.is_in_check(&next_position.turn) {
+        match generate_legal_moves(&next_position).is_empty() {
+            true => san.push('#'),
+            false => san.push('+'),
+        }
+    }
+    san
+}
+
+// a line of moves from the position in SAN with move numbers, e.g.
+// '12... Nf6 13. e5'
+pub fn line_to_san(position: &Position, moves: &[Move]) -> String {
+    let mut tokens = Vec::new();
+    let mut position = *position;
+    for (index, mv) in moves.iter().enumerate() {
+        match (position.turn, index) {
+            (Colour::White, _) => tokens.push(format!("{}.", position.fullmove_number)),
+            (Colour::Black, 0) => tokens.push(format!("{}...", position.fullmove_number)),
+            (Colour::Black, _) => (),
+        }
+        tokens.push(move_to_san(&position, mv));
+        position.make_move(mv);
+    }
+    tokens.join(" ")
+}
+
+pub fn san_to_move(position: &Position, san: &str) -> Result<Move, MoveError> {
+    let text = san;
+    let san = san.trim_end_matches(['+', '#', '!', '?']);
+    let legal_moves = generate_legal_moves(position);
+
+    if matches!(san, "O-O" | "0-0" | "O-O-O" | "0-0-0") {
+        let kingside = san.len() == 3;
+        return match legal_moves
+            .iter()
+            .find(|mv| mv.is_castle() && (mv.destination_square > mv.origin_square) == kingside)
+        {
+            Some(mv) => Ok(*mv),
+            None => Err(MoveError::Illegal(IllegalMoveReason::CastlingNotAllowed)),
+        };
+    }
+
+    if !san.is_ascii() || san.len() < 2 {
+        return Err(MoveError::InvalidNotation(text.to_string()));
+    }
+    let (san, promotion) = match san.split_once('=') {
+        Some((san, promotion)) => match promotion.chars().next() {
+            Some(c) if promotion.len() == 1 => (san, Some(class_from_char(&c)?)),
+            _ => return Err(MoveError::InvalidNotation(text.to_string())),
+        },
+        // the '=' is often left out, as in 'e8Q', and only pawn moves start
+        // with a lowercase letter
+        None => match san.strip_suffix(['N', 'B', 'R', 'Q']) {
+            Some(pawn_move) if san.starts_with(|c: char| c.is_ascii_lowercase()) => {
+                let c = san.chars().last().unwrap_or_default();
+                (pawn_move, Some(class_from_char(&c)?))
+            }
+            _ => (san, None),
+        },
+    };
+
+    let class = match san.chars().next() {
+        Some('N') => Class::Knight,
+        Some('B') => Class::Bishop,
+        Some('R') => Class::Rook,
+        Some('Q') => Class::Queen,
+        Some('K') => Class::King,
+        _ => Class::Pawn,
+    };
+    let body = match class {
+        Class::Pawn => san,
+        _ => &san[1..],
+    };
+    if body.len() < 2 {
+        return Err(MoveError::InvalidNotation(text.to_string()));
+    }
+    let destination_square = square_from_algebraic(&body[body.len() - 2..])
+        .map_err(|_| MoveError::InvalidNotation(text.to_string()))?;
+
+    // whatever is left before the destination narrows down the origin
+    let disambiguation: Vec<char> = body[..body.len() - 2]
+        .chars()
+        .filter(|c| *c != 'x')
+        .collect();
+    let mut origin_file = None;
+    let mut origin_rank = None;
+    for c in disambiguation {
+        match c {
+            'a'..='h' => origin_file = Some(c as usize - 'a' as usize),
+            '1'..='8' => origin_rank = Some(c as usize - '1' as usize),
+            _ => return Err(MoveError::InvalidNotation(text.to_string())),
+        }
+    }
+
+    let matches_san = |mv: &&Move, promotion: &Option<Class>| {
+        let origin_index = bitboard_to_index(&mv.origin_square);
+        !mv.is_castle()
+            && mv.destination_square == destination_square
+            && mv.promotion == *promotion
+            && position
+                .get_piece_at(&mv.origin_square)
+                .is_some_and(|piece| piece.class() == class)
+            && origin_file.is_none_or(|file| origin_index % 8 == file)
+            && origin_rank.is_none_or(|rank| origin_index / 8 == rank)
+    };
+    let candidates: Vec<&Move> = legal_moves
+        .iter()
+        .filter(|mv| matches_san(mv, &promotion))
+        .collect();
+    match candidates.as_slice() {
+        [mv] => Ok(**mv),
+        [] => {
+            // a piece that could make the move were it not pinned or ignoring
+            // check gets a more helpful reason
+            let is_missing_promotion = promotion.is_none()
+                && legal_moves
+                    .iter()
+                    .any(|mv| matches_san(&mv, &Some(Class::Queen)));
+            let is_pseudo_legal = generate_pseudo_legal_moves(position)
+                .iter()
+                .any(|mv| matches_san(&mv, &promotion));
+            let reason = match (is_missing_promotion, is_pseudo_legal) {
+                (true, _) => IllegalMoveReason::PromotionRequired,
+                (false, true) => IllegalMoveReason::LeavesKingInCheck,
+                (false, false) => IllegalMoveReason::UnreachableSquare,
+            };
+            Err(MoveError::Illegal(reason))
+        }
+        _ => Err(MoveError::Ambiguous(text.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+
+    #[test]
+    fn errors_keep_their_cause() {
+        let error = fen_to_position("4k3/8/8/8/8/8/8/4K3 w - z9 0 1").unwrap_err();
+        assert_eq!(
+            error,
+            FenError::InvalidEnPassantSquare(SquareError::InvalidFile('z'))
+        );
+
+        let position = fen_to_position("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let error = uci_to_move(&position, "e1e3").unwrap_err();
+        assert_eq!(
+            error,
+            MoveError::Illegal(IllegalMoveReason::UnreachableSquare)
+        );
+        assert!(error.source().is_some());
+    }
+
+    // castling both ways, promotions with and without captures, and en passant
+    const ROUND_TRIP_FENS: [&str; 4] = [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+    ];
+    // the second king castles queenside without moving
+    const CHESS960_FENS: [&str; 2] = [
+        "1r2k1r1/8/8/8/8/8/8/1R2K1R1 w GBgb - 0 1",
+        "r1k4r/8/8/8/8/8/8/R1K4R w HAha - 0 1",
+    ];
+
+    #[test]
+    fn uci_round_trip() {
+        for fen in ROUND_TRIP_FENS {
+            let position = fen_to_position(fen).unwrap();
+            for mv in generate_legal_moves(&position) {
+                let uci = move_to_uci(&position, &mv, &false);
+                assert_eq!(uci_to_move(&position, &uci), Ok(mv), "{uci} in {fen}");
+            }
+        }
+        for fen in CHESS960_FENS {
+            let position = fen_to_position(fen).unwrap();
+            let moves = generate_legal_moves(&position);
+            assert!(moves.iter().any(|mv| mv.is_castle()), "{fen}");
+            for mv in moves {
+                let uci = move_to_uci(&position, &mv, &true);
+                assert_eq!(uci_to_move(&position, &uci), Ok(mv), "{uci} in {fen}");
+            }
+        }
+    }
+
+    #[test]
+    fn uci_castling_and_promotion() {
+        let position = fen_to_position(ROUND_TRIP_FENS[0]).unwrap();
+        for (uci, king_side) in [
+            ("e1g1", true),
+            ("e1h1", true),
+            ("e1c1", false),
+            ("e1a1", false),
+        ] {
+            let mv = uci_to_move(&position, uci).unwrap();
+            assert!(mv.is_castle());
+            assert_eq!(
+                move_to_uci(&position, &mv, &false),
+                match king_side {
+                    true => "e1g1",
+                    false => "e1c1",
+                }
+            );
+            assert_eq!(
+                move_to_uci(&position, &mv, &true),
+                match king_side {
+                    true => "e1h1",
+                    false => "e1a1",
+                }
+            );
+        }
+
+        let position = fen_to_position(ROUND_TRIP_FENS[2]).unwrap();
+        let mv = uci_to_move(&position, "d7c8q").unwrap();
+        assert_eq!(mv.promotion, Some(Class::Queen));
+        assert_eq!(
+            uci_to_move(&position, "d7c8N").unwrap().promotion,
+            Some(Class::Knight)
+        );
+        assert_eq!(
+            uci_to_move(&position, "d7c8"),
+            Err(MoveError::Illegal(IllegalMoveReason::PromotionRequired))
+        );
+        assert_eq!(
+            uci_to_move(&position, "d7c8k"),
+            Err(MoveError::InvalidPromotionPiece('k'))
+        );
+    }
+
+    #[test]
+    fn fen_round_trip() {
+        for fen in ROUND_TRIP_FENS.iter().chain([&STARTING_FEN]) {
+            let position = fen_to_position(fen).unwrap();
+            assert_eq!(position_to_fen(&position), *fen);
+        }
+        // chess960 rights are written as X-FEN, naming a file only when the
+        // rook is not the outermost one
+        for fen in CHESS960_FENS {
+            let position = fen_to_position(fen).unwrap();
+            let written = position_to_fen(&position);
+            let read_back = fen_to_position(&written).unwrap();
+            assert_eq!(
+                read_back.castling_rights, position.castling_rights,
+                "{written}"
+            );
+            assert_eq!(position_to_fen(&read_back), written);
+        }
+    }
+
+    #[test]
+    fn san_round_trip() {
+        for fen in ROUND_TRIP_FENS.iter().chain(CHESS960_FENS.iter()) {
+            let position = fen_to_position(fen).unwrap();
+            for mv in generate_legal_moves(&position) {
+                let san = move_to_san(&position, &mv);
+                assert_eq!(san_to_move(&position, &san), Ok(mv), "{san} in {fen}");
+            }
+        }
+    }
+
+    #[test]
+    fn san_castling_and_promotion() {
+        let position = fen_to_position(ROUND_TRIP_FENS[0]).unwrap();
+        let king_side = uci_to_move(&position, "e1g1").unwrap();
+        let queen_side = uci_to_move(&position, "e1c1").unwrap();
+        assert_eq!(move_to_san(&position, &king_side), "O-O");
+        assert_eq!(move_to_san(&position, &queen_side), "O-O-O");
+        assert_eq!(san_to_move(&position, "O-O"), Ok(king_side));
+        assert_eq!(san_to_move(&position, "0-0-0"), Ok(queen_side));
+
+        let position = fen_to_position(ROUND_TRIP_FENS[2]).unwrap();
+        let mv = uci_to_move(&position, "d7c8q").unwrap();
+        assert_eq!(move_to_san(&position, &mv), "dxc8=Q");
+        assert_eq!(san_to_move(&position, "dxc8=Q"), Ok(mv));
+        let mv = uci_to_move(&position, "d7c8n").unwrap();
+        assert_eq!(san_to_move(&position, "dxc8=N"), Ok(mv));
+        assert_eq!(san_to_move(&position, "dxc8N"), Ok(mv));
+        assert_eq!(
+            san_to_move(&position, "Nf3Q"),
+            Err(MoveError::InvalidNotation("Nf3Q".to_string()))
+        );
+
+        let position = fen_to_position("8/4P1k1/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let mv = uci_to_move(&position, "e7e8q").unwrap();
+        assert_eq!(san_to_move(&position, "e8Q"), Ok(mv));
+        assert_eq!(san_to_move(&position, "e8Q+"), Ok(mv));
+        assert_eq!(
+            san_to_move(&position, "e8"),
+            Err(MoveError::Illegal(IllegalMoveReason::PromotionRequired))
+        );
+    }
+}
@@ -0,0 +1,298 @@
+use std::fmt;
+
+use crate::errors::ParameterError;
+use crate::evaluation::{
+    Score, BISHOP_TABLE, BISHOP_VALUE, KING_ENDGAME_TABLE, KING_TABLE, KNIGHT_TABLE, KNIGHT_VALUE,
+    PAWN_TABLE, PAWN_VALUE, QUEEN_TABLE, QUEEN_VALUE, ROOK_TABLE, ROOK_VALUE,
+};
+use crate::king_safety::{
+    BISHOP_ATTACK_UNITS, KING_DANGER, KNIGHT_ATTACK_UNITS, OPEN_FILE_NEAR_KING, PAWN_SHIELD_CLOSE,
+    PAWN_SHIELD_FAR, QUEEN_ATTACK_UNITS, ROOK_ATTACK_UNITS, SEMI_OPEN_FILE_NEAR_KING,
+};
+use crate::mobility::{
+    BISHOP_MOBILITY, KNIGHT_MOBILITY, QUEEN_MOBILITY, ROOK_MOBILITY, ROOK_ON_OPEN_FILE,
+    ROOK_ON_SEMI_OPEN_FILE, ROOK_ON_SEVENTH_RANK,
+};
+use crate::pawn_structure::{
+    BACKWARD_PAWN, DOUBLED_PAWN, ISOLATED_PAWN, PASSED_PAWN, PASSED_PAWN_FREE_PATH, PAWN_ISLAND,
+    SUPPORTED_PAWN,
+};
+use crate::pieces::Class;
+
+// every weight the evaluation uses, so that they can be tuned and loaded from
+// a file instead of only being fixed when the engine is built
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Parameters {
+    // pawn to queen
+    pub piece_values: [Score; 5],
+    // pawn to king, laid out like the tables in the evaluation module
+    pub piece_square_tables: [[Score; 64]; 6],
+    pub doubled_pawn: Score,
+    pub isolated_pawn: Score,
+    pub backward_pawn: Score,
+    pub supported_pawn: Score,
+    pub pawn_island: Score,
+    pub passed_pawn: [Score; 8],
+    pub passed_pawn_free_path: [Score; 8],
+    // knight to queen
+    pub mobility: [Score; 4],
+    pub rook_on_open_file: Score,
+    pub rook_on_semi_open_file: Score,
+    pub rook_on_seventh_rank: Score,
+    // knight to queen
+    pub attack_units: [i32; 4],
+    pub king_danger: [i32; 32],
+    pub pawn_shield_close: Score,
+    pub pawn_shield_far: Score,
+    pub open_file_near_king: Score,
+    pub semi_open_file_near_king: Score,
+}
+
+const fn get_table_pair(middlegame_table: &[i32; 64], endgame_table: &[i32; 64]) -> [Score; 64] {
+    let mut table = [Score::new(0, 0); 64];
+    let mut index = 0;
+    while index < 64 {
+        table[index] = Score::new(middlegame_table[index], endgame_table[index]);
+        index += 1;
+    }
+    table
+}
+
+pub const DEFAULT_PARAMETERS: Parameters = Parameters {
+    piece_values: [
+        Score::new(PAWN_VALUE, PAWN_VALUE),
+        Score::new(KNIGHT_VALUE, KNIGHT_VALUE),
+        Score::new(BISHOP_VALUE, BISHOP_VALUE),
+        Score::new(ROOK_VALUE, ROOK_VALUE),
+        Score::new(QUEEN_VALUE, QUEEN_VALUE),
+    ],
+    piece_square_tables: [
+        get_table_pair(&PAWN_TABLE, &PAWN_TABLE),
+        get_table_pair(&KNIGHT_TABLE, &KNIGHT_TABLE),
+        get_table_pair(&BISHOP_TABLE, &BISHOP_TABLE),
+        get_table_pair(&ROOK_TABLE, &ROOK_TABLE),
+        get_table_pair(&QUEEN_TABLE, &QUEEN_TABLE),
+        get_table_pair(&KING_TABLE, &KING_ENDGAME_TABLE),
+    ],
+    doubled_pawn: DOUBLED_PAWN,
+    isolated_pawn: ISOLATED_PAWN,
+    backward_pawn: BACKWARD_PAWN,
+    supported_pawn: SUPPORTED_PAWN,
+    pawn_island: PAWN_ISLAND,
+    passed_pawn: PASSED_PAWN,
+    passed_pawn_free_path: PASSED_PAWN_FREE_PATH,
+    mobility: [
+        KNIGHT_MOBILITY,
+        BISHOP_MOBILITY,
+        ROOK_MOBILITY,
+        QUEEN_MOBILITY,
+    ],
+    rook_on_open_file: ROOK_ON_OPEN_FILE,
+    rook_on_semi_open_file: ROOK_ON_SEMI_OPEN_FILE,
+    rook_on_seventh_rank: ROOK_ON_SEVENTH_RANK,
+    attack_units: [
+        KNIGHT_ATTACK_UNITS,
+        BISHOP_ATTACK_UNITS,
+        ROOK_ATTACK_UNITS,
+        QUEEN_ATTACK_UNITS,
+    ],
+    king_danger: KING_DANGER,
+    pawn_shield_close: PAWN_SHIELD_CLOSE,
+    pawn_shield_far: PAWN_SHIELD_FAR,
+    open_file_near_king: OPEN_FILE_NEAR_KING,
+    semi_open_file_near_king: SEMI_OPEN_FILE_NEAR_KING,
+};
+
+impl Default for Parameters {
+    fn default() -> Parameters {
+        DEFAULT_PARAMETERS
+    }
+}
+
+// where a knight, bishop, rook or queen sits in the arrays that leave out
+// pawns and kings
+pub fn get_piece_index(class: &Class) -> usize {
+    *class as usize - Class::Knight as usize
+}
+
+trait Values {
+    fn values_mut(&mut self) -> Vec<&mut i32>;
+}
+
+impl Values for i32 {
+    fn values_mut(&mut self) -> Vec<&mut i32> {
+        vec![self]
+    }
+}
+
+impl Values for Score {
+    fn values_mut(&mut self) -> Vec<&mut i32> {
+        vec![&mut self.mg, &mut self.eg]
+    }
+}
+
+impl<T: Values, const N: usize> Values for [T; N] {
+    fn values_mut(&mut self) -> Vec<&mut i32> {
+        self.iter_mut()
+            .flat_map(|value| value.values_mut())
+            .collect()
+    }
+}
+
+impl Parameters {
+    // each named group of numbers in the order they are written to a file,
+    // scores giving their middlegame then endgame part
+    fn groups_mut(&mut self) -> Vec<(&'static str, Vec<&mut i32>)> {
+        let [pawn_table, knight_table, bishop_table, rook_table, queen_table, king_table] =
+            &mut self.piece_square_tables;
+        vec![
+            ("piece_values", self.piece_values.values_mut()),
+            ("pawn_table", pawn_table.values_mut()),
+            ("knight_table", knight_table.values_mut()),
+            ("bishop_table", bishop_table.values_mut()),
+            ("rook_table", rook_table.values_mut()),
+            ("queen_table", queen_table.values_mut()),
+            ("king_table", king_table.values_mut()),
+            ("doubled_pawn", self.doubled_pawn.values_mut()),
+            ("isolated_pawn", self.isolated_pawn.values_mut()),
+            ("backward_pawn", self.backward_pawn.values_mut()),
+            ("supported_pawn", self.supported_pawn.values_mut()),
+            ("pawn_island", self.pawn_island.values_mut()),
+            ("passed_pawn", self.passed_pawn.values_mut()),
+            (
+                "passed_pawn_free_path",
+                self.passed_pawn_free_path.values_mut(),
+            ),
+            ("mobility", self.mobility.values_mut()),
+            ("rook_on_open_file", self.rook_on_open_file.values_mut()),
+            (
+                "rook_on_semi_open_file",
+                self.rook_on_semi_open_file.values_mut(),
+            ),
+            (
+                "rook_on_seventh_rank",
+                self.rook_on_seventh_rank.values_mut(),
+            ),
+            ("attack_units", self.attack_units.values_mut()),
+            ("king_danger", self.king_danger.values_mut()),
+            ("pawn_shield_close", self.pawn_shield_close.values_mut()),
+            ("pawn_shield_far", self.pawn_shield_far.values_mut()),
+            ("open_file_near_king", self.open_file_near_king.values_mut()),
+            (
+                "semi_open_file_near_king",
+                self.semi_open_file_near_king.values_mut(),
+            ),
+        ]
+    }
+
+    // every weight as one flat list, for the tuner to adjust one at a time
+    pub fn get_values(&self) -> Vec<i32> {
+        self.clone()
+            .groups_mut()
+            .into_iter()
+            .flat_map(|(_, values)| values.into_iter().map(|value| *value))
+            .collect()
+    }
+
+    pub fn set_values(&mut self, values: &[i32]) {
+        let targets = self.groups_mut().into_iter().flat_map(|(_, values)| values);
+        for (target, value) in targets.zip(values) {
+            *target = *value;
+        }
+    }
+}
+
+// a group name followed by its numbers, e.g. 'doubled_pawn -10 -20'. numbers
+// may run over several lines, '#' starts a comment and any group left out
+// keeps its default
+pub fn parse_parameters(text: &str) -> Result<Parameters, ParameterError> {
+    let mut groups: Vec<(String, Vec<i32>)> = Vec::new();
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap_or("");
+        for word in line.split_whitespace() {
+            match (word.parse::<i32>(), groups.last_mut()) {
+                (Ok(value), Some((_, values))) => values.push(value),
+                (Ok(_), None) => return Err(ParameterError::MissingName(word.to_string())),
+                (Err(_), _) if word.starts_with(|c: char| c == '-' || c.is_ascii_digit()) => {
+                    return Err(ParameterError::InvalidValue(word.to_string()))
+                }
+                (Err(_), _) => groups.push((word.to_string(), Vec::new())),
+            }
+        }
+    }
+
+    let mut parameters = Parameters::default();
+    let mut targets = parameters.groups_mut();
+    for (name, values) in groups {
+        let (_, target) = targets
+            .iter_mut()
+            .find(|(target_name, _)| *target_name == name)
+            .ok_or(ParameterError::UnknownName(name.clone()))?;
+        if values.len() != target.len() {
+            return Err(ParameterError::WrongValueCount(
+                name,
+                target.len(),
+                values.len(),
+            ));
+        }
+        for (target, value) in target.iter_mut().zip(values) {
+            **target = value;
+        }
+    }
+    drop(targets);
+    Ok(parameters)
+}
+
+impl fmt::Display for Parameters {
+    // the format parse_parameters reads, with long groups split into rows of
+    // eight scores
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "# evaluation parameters, scores given as middlegame then endgame"
+        )?;
+        for (name, values) in self.clone().groups_mut() {
+            write!(f, "{name}")?;
+            if values.len() > 16 {
+                for row in values.chunks(16) {
+                    writeln!(f)?;
+                    let row: Vec<String> = row.iter().map(|value| format!("{value:>4}")).collect();
+                    write!(f, "{}", row.join(" "))?;
+                }
+            } else {
+                for value in values {
+                    write!(f, " {value}")?;
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn written_parameters_read_back() {
+        let mut parameters = Parameters::default();
+        // every value different, and a good share of them negative or wider
+        // than the columns they are written in
+        let values: Vec<i32> = (0..parameters.get_values().len() as i32)
+            .map(|index| index * 37 % 2401 - 1200)
+            .collect();
+        parameters.set_values(&values);
+        assert_eq!(parse_parameters(&parameters.to_string()), Ok(parameters));
+    }
+
+    #[test]
+    fn groups_left_out_keep_their_defaults() {
+        let parameters = parse_parameters("# only one group\ndoubled_pawn -7 -13\n").unwrap();
+        let expected = Parameters {
+            doubled_pawn: Score::new(-7, -13),
+            ..Parameters::default()
+        };
+        assert_eq!(parameters, expected);
+    }
+}
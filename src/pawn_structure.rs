@@ -0,0 +1,256 @@
+use crate::evaluation::Score;
+use crate::move_generation::generate_pawn_attacks_of_colour;
+use crate::parameters::Parameters;
+use crate::pieces::Colour;
+use crate::position::{ADJACENT_FILE_MASKS, FILE_A, FILE_H, FILE_MASKS};
+use crate::utils::{bitboard_to_index, pop_lsb};
+
+pub const DOUBLED_PAWN: Score = Score::new(-10, -20);
+pub const ISOLATED_PAWN: Score = Score::new(-10, -15);
+pub const BACKWARD_PAWN: Score = Score::new(-8, -10);
+pub const SUPPORTED_PAWN: Score = Score::new(8, 6);
+// for every island after the first
+pub const PAWN_ISLAND: Score = Score::new(-5, -10);
+
+// indexed by rank counted from the pawn's own side, so index 6 is one step
+// from promoting
+pub const PASSED_PAWN: [Score; 8] = [
+    Score::new(0, 0),
+    Score::new(5, 10),
+    Score::new(10, 15),
+    Score::new(15, 25),
+    Score::new(25, 45),
+    Score::new(40, 75),
+    Score::new(60, 120),
+    Score::new(0, 0),
+];
+// on top of the passed pawn bonus when nothing stands in front of the pawn
+pub const PASSED_PAWN_FREE_PATH: [Score; 8] = [
+    Score::new(0, 0),
+    Score::new(0, 5),
+    Score::new(2, 8),
+    Score::new(5, 12),
+    Score::new(10, 25),
+    Score::new(15, 40),
+    Score::new(25, 60),
+    Score::new(0, 0),
+];
+
+const PAWN_HASH_TABLE_SIZE: usize = 1 << 14;
+
+// spreads every set bit towards the far side of the board for the colour
+pub fn fill_forward(bitboard: &u64, colour: &Colour) -> u64 {
+    let mut fill = *bitboard;
+    match colour {
+        Colour::White => {
+            fill |= fill << 8;
+            fill |= fill << 16;
+            fill |= fill << 32;
+        }
+        Colour::Black => {
+            fill |= fill >> 8;
+            fill |= fill >> 16;
+            fill |= fill >> 32;
+        }
+    }
+    fill
+}
+
+fn fill_backward(bitboard: &u64, colour: &Colour) -> u64 {
+    fill_forward(bitboard, &!colour)
+}
+
+// the squares in front of each pawn on its own file, not including the pawn
+pub fn get_front_span(pawns: &u64, colour: &Colour) -> u64 {
+    fill_forward(pawns, colour) & !pawns
+}
+
+// the square a pawn would step onto next
+fn get_stop_square(square: &u64, colour: &Colour) -> u64 {
+    match colour {
+        Colour::White => square << 8,
+        Colour::Black => square >> 8,
+    }
+}
+
+fn get_adjacent_files(bitboard: &u64) -> u64 {
+    ((bitboard & !FILE_H) << 1) | ((bitboard & !FILE_A) >> 1)
+}
+
+pub fn get_relative_rank(square: &u64, colour: &Colour) -> usize {
+    let rank_index = bitboard_to_index(square) / 8;
+    match colour {
+        Colour::White => rank_index,
+        Colour::Black => 7 - rank_index,
+    }
+}
+
+pub fn get_passed_pawns(pawns: &u64, enemy_pawns: &u64, colour: &Colour) -> u64 {
+    let mut passed_pawns = 0b0;
+    let mut remaining = *pawns;
+    while remaining != 0 {
+        let square = pop_lsb(&mut remaining);
+        let front_span = get_front_span(&square, colour);
+        // of doubled pawns only the front one counts as passed
+        let is_front_pawn = front_span & pawns == 0;
+        if is_front_pawn && (front_span | get_adjacent_files(&front_span)) & enemy_pawns == 0 {
+            passed_pawns |= square;
+        }
+    }
+    passed_pawns
+}
+
+fn count_islands(pawns: &u64) -> i32 {
+    let mut islands = 0;
+    let mut on_island = false;
+    for file_mask in FILE_MASKS {
+        let has_pawn = pawns & file_mask != 0;
+        if has_pawn && !on_island {
+            islands += 1;
+        }
+        on_island = has_pawn;
+    }
+    islands
+}
+
+// the terms for one side that depend on nothing but where the pawns stand
+pub fn evaluate_pawns_of_colour(
+    pawns: &u64,
+    enemy_pawns: &u64,
+    colour: &Colour,
+    parameters: &Parameters,
+) -> Score {
+    let mut score = Score::default();
+    let enemy_attacks = generate_pawn_attacks_of_colour(enemy_pawns, &!colour);
+    let supported = generate_pawn_attacks_of_colour(pawns, colour) & pawns;
+
+    for file_mask in FILE_MASKS {
+        let count = (pawns & file_mask).count_ones() as i32;
+        if count > 1 {
+            score += parameters.doubled_pawn * (count - 1);
+        }
+    }
+
+    let mut remaining = *pawns;
+    while remaining != 0 {
+        let square = pop_lsb(&mut remaining);
+        let file_index = bitboard_to_index(&square) % 8;
+        let neighbours = pawns & ADJACENT_FILE_MASKS[file_index];
+
+        if neighbours == 0 {
+            score += parameters.isolated_pawn;
+        } else {
+            // no neighbour level with or behind it can ever step up to defend
+            // it, and an enemy pawn stops it advancing to find one
+            let rear_span = fill_backward(&square, colour);
+            let stop_square = get_stop_square(&square, colour);
+            if neighbours & get_adjacent_files(&rear_span) == 0 && stop_square & enemy_attacks != 0
+            {
+                score += parameters.backward_pawn;
+            }
+        }
+
+        if square & supported != 0 {
+            score += parameters.supported_pawn;
+        }
+    }
+
+    let passed_pawns = get_passed_pawns(pawns, enemy_pawns, colour);
+    let mut remaining = passed_pawns;
+    while remaining != 0 {
+        let square = pop_lsb(&mut remaining);
+        score += parameters.passed_pawn[get_relative_rank(&square, colour)];
+    }
+
+    let islands = count_islands(pawns);
+    if islands > 1 {
+        score += parameters.pawn_island * (islands - 1);
+    }
+    score
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PawnEntry {
+    white_pawns: u64,
+    black_pawns: u64,
+    // from white's point of view
+    pub score: Score,
+    pub passed_pawns: u64,
+}
+
+pub fn evaluate_pawn_structure(
+    white_pawns: &u64,
+    black_pawns: &u64,
+    parameters: &Parameters,
+) -> PawnEntry {
+    let score = evaluate_pawns_of_colour(white_pawns, black_pawns, &Colour::White, parameters)
+        - evaluate_pawns_of_colour(black_pawns, white_pawns, &Colour::Black, parameters);
+    let passed_pawns = get_passed_pawns(white_pawns, black_pawns, &Colour::White)
+        | get_passed_pawns(black_pawns, white_pawns, &Colour::Black);
+    PawnEntry {
+        white_pawns: *white_pawns,
+        black_pawns: *black_pawns,
+        score,
+        passed_pawns,
+    }
+}
+
+// pawns move rarely compared with the other pieces, so their structure is
+// worked out once and looked up by the pawn bitboards alone
+pub struct PawnHashTable {
+    entries: Vec<PawnEntry>,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl Default for PawnHashTable {
+    fn default() -> PawnHashTable {
+        PawnHashTable::new()
+    }
+}
+
+impl PawnHashTable {
+    pub fn new() -> PawnHashTable {
+        // both sides having a pawn on every square never happens, so marks an
+        // empty slot
+        let empty_entry = PawnEntry {
+            white_pawns: u64::MAX,
+            black_pawns: u64::MAX,
+            score: Score::default(),
+            passed_pawns: 0b0,
+        };
+        PawnHashTable {
+            entries: vec![empty_entry; PAWN_HASH_TABLE_SIZE],
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    fn get_index(white_pawns: &u64, black_pawns: &u64) -> usize {
+        let key = white_pawns.wrapping_mul(0x9E37_79B9_7F4A_7C15)
+            ^ black_pawns
+                .rotate_left(32)
+                .wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+        (key >> (64 - PAWN_HASH_TABLE_SIZE.trailing_zeros())) as usize
+    }
+
+    // entries are only valid for the parameters they were worked out with, so
+    // a table must not be shared between different parameters
+    pub fn probe(
+        &mut self,
+        white_pawns: &u64,
+        black_pawns: &u64,
+        parameters: &Parameters,
+    ) -> PawnEntry {
+        let index = PawnHashTable::get_index(white_pawns, black_pawns);
+        let entry = self.entries[index];
+        if entry.white_pawns == *white_pawns && entry.black_pawns == *black_pawns {
+            self.hits += 1;
+            return entry;
+        }
+        self.misses += 1;
+        let entry = evaluate_pawn_structure(white_pawns, black_pawns, parameters);
+        self.entries[index] = entry;
+        entry
+    }
+}
@@ -0,0 +1,249 @@
+use crate::errors::PgnError;
+use crate::game::Game;
+use crate::move_generation::generate_legal_moves;
+use crate::moves::Move;
+use crate::notation::{fen_to_position, position_to_fen, san_to_move, STARTING_FEN};
+use crate::pieces::Colour;
+use crate::position::{get_starting_position, Position};
+
+pub struct PgnGame {
+    pub headers: Vec<(String, String)>,
+    pub start_position: Position,
+    pub moves: Vec<Move>,
+    pub result: String,
+}
+
+impl PgnGame {
+    pub fn get_header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+pub fn get_result(position: &Position) -> &'static str {
+    if !generate_legal_moves(position).is_empty() {
+        return "*";
+    }
+    match (position.is_in_check(&position.turn), position.turn) {
+        (true, Colour::White) => "0-1",
+        (true, Colour::Black) => "1-0",
+        (false, _) => "1/2-1/2",
+    }
+}
+
+pub fn game_to_pgn(game: &Game) -> String {
+    let start_position = game.get_start_position();
+    let mut end_position = *start_position;
+    for mv in game.get_all_moves() {
+        end_position.make_move(mv);
+    }
+    let result = get_result(&end_position);
+
+    let mut pgn = String::new();
+    for (key, value) in [
+        ("Event", "?"),
+        ("Site", "?"),
+        ("Date", "????.??.??"),
+        ("Round", "?"),
+        ("White", "?"),
+        ("Black", "?"),
+        ("Result", result),
+    ] {
+        pgn.push_str(&format!("[{key} \"{value}\"]\n"));
+    }
+    let start_fen = position_to_fen(start_position);
+    if start_fen != STARTING_FEN {
+        pgn.push_str("[SetUp \"1\"]\n");
+        pgn.push_str(&format!("[FEN \"{start_fen}\"]\n"));
+    }
+    pgn.push('\n');
+
+    let mut movetext: Vec<String> = Vec::new();
+    let mut move_number = start_position.fullmove_number;
+    let mut colour = start_position.turn;
+    for (index, san) in game.get_san_moves().iter().enumerate() {
+        match (colour, index) {
+            (Colour::White, _) => movetext.push(format!("{move_number}.")),
+            (Colour::Black, 0) => movetext.push(format!("{move_number}...")),
+            (Colour::Black, _) => (),
+        }
+        movetext.push(san.to_string());
+        if colour == Colour::Black {
+            move_number += 1;
+        }
+        colour = !colour;
+    }
+    movetext.push(result.to_string());
+
+    // keep lines under 80 characters as the export format asks
+    let mut line = String::new();
+    for token in movetext {
+        if !line.is_empty() && line.len() + token.len() + 1 > 79 {
+            pgn.push_str(&line);
+            pgn.push('\n');
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(&token);
+    }
+    pgn.push_str(&line);
+    pgn.push('\n');
+    pgn
+}
+
+fn is_result_token(token: &str) -> bool {
+    matches!(token, "1-0" | "0-1" | "1/2-1/2" | "*")
+}
+
+// splits movetext into tokens, dropping comments, variations and annotations
+fn tokenise_movetext(movetext: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut comment_depth = 0;
+    let mut variation_depth = 0;
+    let mut in_line_comment = false;
+
+    for c in movetext.chars() {
+        if in_line_comment {
+            in_line_comment = c != '\n';
+            continue;
+        }
+        if comment_depth > 0 {
+            if c == '}' {
+                comment_depth -= 1;
+            }
+            continue;
+        }
+        match c {
+            '{' => comment_depth += 1,
+            ';' => in_line_comment = true,
+            '(' => variation_depth += 1,
+            ')' => variation_depth -= 1,
+            _ if variation_depth > 0 => (),
+            c if c.is_whitespace() => {
+                if !token.is_empty() {
+                    tokens.push(token.clone());
+                    token.clear();
+                }
+                continue;
+            }
+            _ => {
+                token.push(c);
+                continue;
+            }
+        }
+        if !token.is_empty() {
+            tokens.push(token.clone());
+            token.clear();
+        }
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+
+    tokens
+        .into_iter()
+        .filter(|token| !token.starts_with('$'))
+        .map(|token| {
+            // move numbers may be glued to the move, e.g. "1.e4"
+            match token.rfind('.') {
+                Some(index) => token[index + 1..].to_string(),
+                None => token,
+            }
+        })
+        .filter(|token| !token.is_empty())
+        .collect()
+}
+
+pub fn parse_pgn(text: &str) -> Result<Vec<PgnGame>, PgnError> {
+    parse_pgn_games(text).into_iter().collect()
+}
+
+// every game of a collection parsed on its own, so that one broken game does
+// not lose the rest
+pub fn parse_pgn_games(text: &str) -> Vec<Result<PgnGame, PgnError>> {
+    let mut games = Vec::new();
+    let mut headers: Vec<(String, String)> = Vec::new();
+    let mut movetext = String::new();
+
+    let mut lines = text.lines().peekable();
+    while let Some(line) = lines.next() {
+        let line = line.trim();
+        if line.starts_with('[') && movetext.trim().is_empty() {
+            let inner = line.trim_start_matches('[').trim_end_matches(']');
+            if let Some((key, value)) = inner.split_once(' ') {
+                headers.push((key.to_string(), value.trim().trim_matches('"').to_string()));
+            }
+            continue;
+        }
+        movetext.push_str(line);
+        movetext.push('\n');
+
+        let next_is_header = lines.peek().is_none_or(|next| next.trim().starts_with('['));
+        if next_is_header && !movetext.trim().is_empty() {
+            games.push(parse_pgn_game(&headers, &movetext));
+            headers.clear();
+            movetext.clear();
+        }
+    }
+    if !headers.is_empty() {
+        games.push(parse_pgn_game(&headers, &movetext));
+    }
+    games
+}
+
+fn parse_pgn_game(headers: &[(String, String)], movetext: &str) -> Result<PgnGame, PgnError> {
+    let fen = headers
+        .iter()
+        .find(|(key, _)| key == "FEN")
+        .map(|(_, value)| value.as_str());
+    let start_position = match fen {
+        Some(fen) => fen_to_position(fen)?,
+        None => get_starting_position(),
+    };
+
+    let mut position = start_position;
+    let mut moves = Vec::new();
+    let mut result = "*".to_string();
+    for token in tokenise_movetext(movetext) {
+        if is_result_token(&token) {
+            result = token;
+            break;
+        }
+        let mv = match san_to_move(&position, &token) {
+            Ok(mv) => mv,
+            Err(error) => return Err(PgnError::InvalidMove(token, error)),
+        };
+        position.make_move(&mv);
+        moves.push(mv);
+    }
+
+    Ok(PgnGame {
+        headers: headers.to_vec(),
+        start_position,
+        moves,
+        result,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_broken_game_does_not_lose_the_others() {
+        let text = "[Result \"1-0\"]\n\n1. e4 e5 2. Nf3 1-0\n\n\
+                    [Result \"0-1\"]\n\n1. e4 Ke7 0-1\n\n\
+                    [Result \"1/2-1/2\"]\n\n1. d4 d5 1/2-1/2\n";
+        let games = parse_pgn_games(text);
+        assert_eq!(games.len(), 3);
+        assert_eq!(games[0].as_ref().unwrap().moves.len(), 3);
+        assert!(games[1].is_err());
+        assert_eq!(games[2].as_ref().unwrap().result, "1/2-1/2");
+        assert!(parse_pgn(text).is_err());
+    }
+}
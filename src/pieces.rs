@@ -1,6 +1,6 @@
 use std::ops::Not;
 
-// #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Class {
     Pawn,
     Knight,
@@ -10,7 +10,31 @@ pub enum Class {
     King,
 }
 
-#[derive(Clone, Copy, PartialEq)]
+impl Class {
+    pub fn iter() -> &'static [Class] {
+        &[
+            Class::Pawn,
+            Class::Knight,
+            Class::Bishop,
+            Class::Rook,
+            Class::Queen,
+            Class::King,
+        ]
+    }
+
+    pub fn str(&self) -> &'static str {
+        match self {
+            Class::Pawn => "P",
+            Class::Knight => "N",
+            Class::Bishop => "B",
+            Class::Rook => "R",
+            Class::Queen => "Q",
+            Class::King => "K",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Colour {
     White,
     Black,
@@ -36,6 +60,7 @@ impl Not for &Colour {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Piece {
     WhitePawn,
     WhiteKnight,
@@ -52,6 +77,23 @@ pub enum Piece {
 }
 
 impl Piece {
+    pub fn new(class: &Class, colour: &Colour) -> Piece {
+        match (colour, class) {
+            (Colour::White, Class::Pawn) => Piece::WhitePawn,
+            (Colour::White, Class::Knight) => Piece::WhiteKnight,
+            (Colour::White, Class::Bishop) => Piece::WhiteBishop,
+            (Colour::White, Class::Rook) => Piece::WhiteRook,
+            (Colour::White, Class::Queen) => Piece::WhiteQueen,
+            (Colour::White, Class::King) => Piece::WhiteKing,
+            (Colour::Black, Class::Pawn) => Piece::BlackPawn,
+            (Colour::Black, Class::Knight) => Piece::BlackKnight,
+            (Colour::Black, Class::Bishop) => Piece::BlackBishop,
+            (Colour::Black, Class::Rook) => Piece::BlackRook,
+            (Colour::Black, Class::Queen) => Piece::BlackQueen,
+            (Colour::Black, Class::King) => Piece::BlackKing,
+        }
+    }
+
     pub fn iter() -> &'static [Piece] {
         &[
             Piece::WhitePawn,
@@ -85,6 +127,24 @@ impl Piece {
             Piece::BlackKing => "k",
         }
     }
+
+    pub fn glyph(&self) -> &'static str {
+        match self {
+            Piece::WhitePawn => "♙",
+            Piece::WhiteKnight => "♘",
+            Piece::WhiteBishop => "♗",
+            Piece::WhiteRook => "♖",
+            Piece::WhiteQueen => "♕",
+            Piece::WhiteKing => "♔",
+            Piece::BlackPawn => "♟",
+            Piece::BlackKnight => "♞",
+            Piece::BlackBishop => "♝",
+            Piece::BlackRook => "♜",
+            Piece::BlackQueen => "♛",
+            Piece::BlackKing => "♚",
+        }
+    }
+
     pub fn class(&self) -> Class {
         match self {
             Piece::WhitePawn => Class::Pawn,
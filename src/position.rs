@@ -1,25 +1,35 @@
 use crate::{
-    move_generation::generate_pawn_attacks,
+    errors::{MoveError, PositionError},
+    move_generation::{
+        find_legal_move, generate_attacks, generate_bishop_attacks, generate_king_attacks,
+        generate_knight_attacks, generate_pawn_attacks_of_colour, generate_rook_attacks,
+    },
+    moves::{Move, MoveKind},
     pieces::{Class, Colour, Piece},
-    utils::{bit_scan, index_to_algebraic},
+    utils::{bitboard_to_index, pop_lsb},
 };
 
+#[derive(Clone, Copy, Debug)]
 pub struct Position {
-    pub white_pawn: u64,
-    pub white_knight: u64,
-    pub white_bishop: u64,
-    pub white_rook: u64,
-    pub white_queen: u64,
-    pub white_king: u64,
-    pub black_pawn: u64,
-    pub black_knight: u64,
-    pub black_bishop: u64,
-    pub black_rook: u64,
-    pub black_queen: u64,
-    pub black_king: u64,
-    pub turn: Colour,
-    pub last_moved_squares: u64,
-    pub en_passant_square: u64,
+    pub(crate) white_pawn: u64,
+    pub(crate) white_knight: u64,
+    pub(crate) white_bishop: u64,
+    pub(crate) white_rook: u64,
+    pub(crate) white_queen: u64,
+    pub(crate) white_king: u64,
+    pub(crate) black_pawn: u64,
+    pub(crate) black_knight: u64,
+    pub(crate) black_bishop: u64,
+    pub(crate) black_rook: u64,
+    pub(crate) black_queen: u64,
+    pub(crate) black_king: u64,
+    pub(crate) turn: Colour,
+    pub(crate) last_moved_squares: u64,
+    pub(crate) en_passant_square: u64,
+    // squares of the rooks that may still castle, which also covers Chess960
+    pub(crate) castling_rights: u64,
+    pub(crate) halfmove_clock: u32,
+    pub(crate) fullmove_number: u32,
 }
 
 impl Position {
@@ -39,7 +49,7 @@ impl Position {
             Piece::BlackKing => self.black_king,
         }
     }
-    pub fn get_bitboard_mut(&mut self, piece: &Piece) -> &mut u64 {
+    pub(crate) fn get_bitboard_mut(&mut self, piece: &Piece) -> &mut u64 {
         match piece {
             Piece::WhitePawn => &mut self.white_pawn,
             Piece::WhiteKnight => &mut self.white_knight,
@@ -56,8 +66,57 @@ impl Position {
         }
     }
 
+    pub fn get_turn(&self) -> Colour {
+        self.turn
+    }
+
+    pub fn set_turn(&mut self, turn: &Colour) {
+        self.turn = *turn;
+    }
+
+    // the origin and destination of the move that reached this position
+    pub fn get_last_moved_squares(&self) -> u64 {
+        self.last_moved_squares
+    }
+
+    pub fn clear_last_moved_squares(&mut self) {
+        self.last_moved_squares = 0b0;
+    }
+
+    pub fn get_en_passant_square(&self) -> u64 {
+        self.en_passant_square
+    }
+
+    pub fn set_en_passant_square(&mut self, square: &u64) {
+        self.en_passant_square = *square;
+    }
+
+    pub fn get_castling_rights(&self) -> u64 {
+        self.castling_rights
+    }
+
+    pub fn set_castling_rights(&mut self, castling_rights: &u64) {
+        self.castling_rights = *castling_rights;
+    }
+
+    pub fn get_halfmove_clock(&self) -> u32 {
+        self.halfmove_clock
+    }
+
+    pub fn set_halfmove_clock(&mut self, halfmove_clock: &u32) {
+        self.halfmove_clock = *halfmove_clock;
+    }
+
+    pub fn get_fullmove_number(&self) -> u32 {
+        self.fullmove_number
+    }
+
+    pub fn set_fullmove_number(&mut self, fullmove_number: &u32) {
+        self.fullmove_number = *fullmove_number;
+    }
+
     pub fn get_occupancy(&self) -> u64 {
-        return self.white_pawn
+        self.white_pawn
             | self.white_knight
             | self.white_bishop
             | self.white_rook
@@ -68,26 +127,26 @@ impl Position {
             | self.black_bishop
             | self.black_rook
             | self.black_queen
-            | self.black_king;
+            | self.black_king
     }
 
     pub fn get_colour_occupancy(&self, colour: &Colour) -> u64 {
         match colour {
             Colour::White => {
-                return self.white_pawn
+                self.white_pawn
                     | self.white_knight
                     | self.white_bishop
                     | self.white_rook
                     | self.white_queen
-                    | self.white_king;
+                    | self.white_king
             }
             Colour::Black => {
-                return self.black_pawn
+                self.black_pawn
                     | self.black_knight
                     | self.black_bishop
                     | self.black_rook
                     | self.black_queen
-                    | self.black_king;
+                    | self.black_king
             }
         }
     }
@@ -105,12 +164,9 @@ impl Position {
     }
 
     pub fn get_piece_at(&self, square: &u64) -> Option<&Piece> {
-        for piece in Piece::iter() {
-            if self.get_bitboard(piece) & square != 0 {
-                return Some(piece);
-            }
-        }
-        None
+        Piece::iter()
+            .iter()
+            .find(|piece| self.get_bitboard(piece) & square != 0)
     }
 
     pub fn insert_piece_at_square(&mut self, piece: &Piece, square: &u64) {
@@ -118,27 +174,246 @@ impl Position {
         *bitboard |= square;
     }
 
-    pub fn move_piece(&mut self, origin_square: &u64, destination_square: &u64) {
+    pub fn remove_piece_at_square(&mut self, square: &u64) {
         for piece in Piece::iter() {
-            self.last_moved_squares = *origin_square | *destination_square;
+            *self.get_bitboard_mut(piece) &= !square;
+        }
+    }
 
-            // remove taken piece from board
-            *self.get_bitboard_mut(piece) &= !destination_square;
+    // the move only needs the right squares and promotion, its kind is taken
+    // from the matching legal move
+    pub fn try_make_move(&mut self, mv: &Move) -> Result<(), MoveError> {
+        let legal_move = find_legal_move(self, mv)?;
+        self.make_move(&legal_move);
+        Ok(())
+    }
 
-            if origin_square & self.get_bitboard(piece) != 0 {
-                *self.get_bitboard_mut(piece) ^= origin_square;
-                *self.get_bitboard_mut(piece) |= destination_square;
+    pub fn move_piece(
+        &mut self,
+        origin_square: &u64,
+        destination_square: &u64,
+    ) -> Result<(), MoveError> {
+        self.try_make_move(&Move::new(origin_square, destination_square))
+    }
+
+    pub fn get_attacks_of_colour(&self, colour: &Colour) -> u64 {
+        let occupancy = self.get_occupancy();
+        let mut attacks: u64 = 0b0;
+        for class in Class::iter() {
+            let piece = Piece::new(class, colour);
+            let mut pieces = self.get_bitboard(&piece);
+            if *class == Class::Pawn {
+                attacks |= generate_pawn_attacks_of_colour(&pieces, colour);
+                continue;
+            }
+            while pieces != 0 {
+                let square = pop_lsb(&mut pieces);
+                attacks |= generate_attacks(&piece, &square, &occupancy);
             }
         }
-        self.turn = !self.turn;
+        attacks
     }
 
-    pub fn get_attacks_of_colour(&self, colour: &Colour) -> u64 {
+    pub fn get_king_square(&self, colour: &Colour) -> u64 {
         match colour {
-            Colour::White => generate_pawn_attacks(self, &self.white_pawn),
-            Colour::Black => generate_pawn_attacks(self, &self.black_pawn),
+            Colour::White => self.white_king,
+            Colour::Black => self.black_king,
         }
     }
+
+    pub fn is_square_attacked(&self, square: &u64, by_colour: &Colour, occupancy: &u64) -> bool {
+        let pawns = self.get_bitboard(&Piece::new(&Class::Pawn, by_colour));
+        let knights = self.get_bitboard(&Piece::new(&Class::Knight, by_colour));
+        let bishops = self.get_bitboard(&Piece::new(&Class::Bishop, by_colour));
+        let rooks = self.get_bitboard(&Piece::new(&Class::Rook, by_colour));
+        let queens = self.get_bitboard(&Piece::new(&Class::Queen, by_colour));
+        let king = self.get_bitboard(&Piece::new(&Class::King, by_colour));
+
+        // look outwards from the square as each piece type, since attacks are symmetric
+        generate_pawn_attacks_of_colour(square, &!by_colour) & pawns != 0
+            || generate_knight_attacks(square) & knights != 0
+            || generate_king_attacks(square) & king != 0
+            || generate_bishop_attacks(square, occupancy) & (bishops | queens) != 0
+            || generate_rook_attacks(square, occupancy) & (rooks | queens) != 0
+    }
+
+    pub fn is_in_check(&self, colour: &Colour) -> bool {
+        let king_square = self.get_king_square(colour);
+        king_square != 0 && self.is_square_attacked(&king_square, &!colour, &self.get_occupancy())
+    }
+
+    // with only pawns and a king, passing would often be the best move if it
+    // were allowed, so a search cannot assume that moving beats standing still
+    pub fn has_non_pawn_material(&self, colour: &Colour) -> bool {
+        [Class::Knight, Class::Bishop, Class::Rook, Class::Queen]
+            .iter()
+            .any(|class| self.get_bitboard(&Piece::new(class, colour)) != 0)
+    }
+
+    // every problem is reported rather than just the first, so that a board
+    // editor can show them all at once
+    pub fn validate(&self) -> Result<(), Vec<PositionError>> {
+        let mut problems = Vec::new();
+
+        let mut occupancy: u64 = 0b0;
+        let mut overlapping: u64 = 0b0;
+        for piece in Piece::iter() {
+            let bitboard = self.get_bitboard(piece);
+            overlapping |= occupancy & bitboard;
+            occupancy |= bitboard;
+        }
+        if overlapping != 0 {
+            problems.push(PositionError::OverlappingPieces(overlapping));
+        }
+
+        for colour in [Colour::White, Colour::Black] {
+            match self.get_king_square(&colour).count_ones() {
+                0 => problems.push(PositionError::MissingKing(colour)),
+                1 => (),
+                _ => problems.push(PositionError::ExtraKings(colour)),
+            }
+        }
+
+        let back_rank_pawns = (self.white_pawn | self.black_pawn) & (RANK_1 | RANK_8);
+        if back_rank_pawns != 0 {
+            problems.push(PositionError::PawnsOnBackRank(back_rank_pawns));
+        }
+
+        // the side that has just moved cannot have left its own king in check
+        let opponent = !self.turn;
+        if self.get_king_square(&opponent).count_ones() == 1 && self.is_in_check(&opponent) {
+            problems.push(PositionError::OpponentInCheck(opponent));
+        }
+
+        if self.en_passant_square != 0 && !self.is_en_passant_square_possible() {
+            problems.push(PositionError::ImpossibleEnPassant(self.en_passant_square));
+        }
+
+        let mut invalid_castling_rights = self.castling_rights & !(RANK_1 | RANK_8);
+        for (colour, back_rank) in [(Colour::White, RANK_1), (Colour::Black, RANK_8)] {
+            let rights = self.castling_rights & back_rank;
+            let rooks = self.get_bitboard(&Piece::new(&Class::Rook, &colour));
+            invalid_castling_rights |= match self.get_king_square(&colour) & back_rank {
+                0 => rights,
+                _ => rights & !rooks,
+            };
+        }
+        if invalid_castling_rights != 0 {
+            problems.push(PositionError::InvalidCastlingRights(
+                invalid_castling_rights,
+            ));
+        }
+
+        match problems.is_empty() {
+            true => Ok(()),
+            false => Err(problems),
+        }
+    }
+
+    // the en passant square must be the one just passed over by a double push
+    fn is_en_passant_square_possible(&self) -> bool {
+        let square = self.en_passant_square;
+        let (pushed_pawn_square, origin_square, rank_index) = match self.turn {
+            Colour::White => (square >> 8, square << 8, 5),
+            Colour::Black => (square << 8, square >> 8, 2),
+        };
+        let pawns = self.get_bitboard(&Piece::new(&Class::Pawn, &!self.turn));
+        square.count_ones() == 1
+            && bitboard_to_index(&square) / 8 == rank_index
+            && pawns & pushed_pawn_square != 0
+            && self.get_occupancy() & (square | origin_square) == 0
+    }
+
+    // castling always lands the king on the g or c file and the rook next to
+    // it, wherever they started from
+    pub fn get_castling_destinations(&self, king_square: &u64, rook_square: &u64) -> (u64, u64) {
+        let rank_start = king_square.trailing_zeros() / 8 * 8;
+        if rook_square > king_square {
+            (1 << (rank_start + 6), 1 << (rank_start + 5))
+        } else {
+            (1 << (rank_start + 2), 1 << (rank_start + 3))
+        }
+    }
+
+    pub fn make_move(&mut self, mv: &Move) {
+        let piece = match self.get_piece_at(&mv.origin_square) {
+            Some(piece) => *piece,
+            None => return,
+        };
+        let colour = piece.colour();
+        let mut resets_halfmove_clock = piece.class() == Class::Pawn;
+        self.en_passant_square = 0b0;
+
+        match mv.kind {
+            MoveKind::Castle => {
+                let rook = Piece::new(&Class::Rook, &colour);
+                let (king_destination, rook_destination) =
+                    self.get_castling_destinations(&mv.origin_square, &mv.destination_square);
+                *self.get_bitboard_mut(&piece) &= !mv.origin_square;
+                *self.get_bitboard_mut(&rook) &= !mv.destination_square;
+                *self.get_bitboard_mut(&piece) |= king_destination;
+                *self.get_bitboard_mut(&rook) |= rook_destination;
+                self.last_moved_squares = mv.origin_square | king_destination;
+            }
+            _ => {
+                let captured_square = match (mv.kind, colour) {
+                    (MoveKind::EnPassant, Colour::White) => mv.destination_square >> 8,
+                    (MoveKind::EnPassant, Colour::Black) => mv.destination_square << 8,
+                    _ => mv.destination_square,
+                };
+                if let Some(captured_piece) = self.get_piece_at(&captured_square).copied() {
+                    *self.get_bitboard_mut(&captured_piece) &= !captured_square;
+                    resets_halfmove_clock = true;
+                }
+
+                let placed_piece = match mv.promotion {
+                    Some(class) => Piece::new(&class, &colour),
+                    None => piece,
+                };
+                *self.get_bitboard_mut(&piece) &= !mv.origin_square;
+                *self.get_bitboard_mut(&placed_piece) |= mv.destination_square;
+
+                if mv.kind == MoveKind::DoublePawnPush {
+                    self.en_passant_square = match colour {
+                        Colour::White => mv.origin_square << 8,
+                        Colour::Black => mv.origin_square >> 8,
+                    };
+                }
+                self.last_moved_squares = mv.origin_square | mv.destination_square;
+            }
+        }
+
+        if piece.class() == Class::King {
+            self.castling_rights &= match colour {
+                Colour::White => !RANK_1,
+                Colour::Black => !RANK_8,
+            };
+        }
+        // a rook leaving or being captured on its square loses that right
+        self.castling_rights &= !(mv.origin_square | mv.destination_square);
+
+        if resets_halfmove_clock {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+        if colour == Colour::Black {
+            self.fullmove_number += 1;
+        }
+        self.turn = !colour;
+    }
+
+    // hands the move to the other side without moving anything, which is not
+    // legal chess but lets a search see what the opponent threatens
+    pub fn make_null_move(&mut self) {
+        self.en_passant_square = 0b0;
+        self.last_moved_squares = 0b0;
+        self.halfmove_clock += 1;
+        if self.turn == Colour::Black {
+            self.fullmove_number += 1;
+        }
+        self.turn = !self.turn;
+    }
 }
 
 pub const FILE_A: u64 = 0b0000000100000001000000010000000100000001000000010000000100000001;
@@ -151,11 +426,50 @@ pub const RANK_1: u64 = 0b000000000000000000000000000000000000000000000000000000
 pub const RANK_2: u64 = 0b0000000000000000000000000000000000000000000000001111111100000000;
 pub const RANK_7: u64 = 0b0000000011111111000000000000000000000000000000000000000000000000;
 pub const RANK_8: u64 = 0b1111111100000000000000000000000000000000000000000000000000000000;
+const fn generate_file_masks() -> [u64; 8] {
+    let mut masks = [0b0; 8];
+    let mut file_index = 0;
+    while file_index < 8 {
+        masks[file_index] = FILE_A << file_index;
+        file_index += 1;
+    }
+    masks
+}
+
+const fn generate_rank_masks() -> [u64; 8] {
+    let mut masks = [0b0; 8];
+    let mut rank_index = 0;
+    while rank_index < 8 {
+        masks[rank_index] = RANK_1 << (rank_index * 8);
+        rank_index += 1;
+    }
+    masks
+}
+
+const fn generate_adjacent_file_masks() -> [u64; 8] {
+    let mut masks = [0b0; 8];
+    let mut file_index = 0;
+    while file_index < 8 {
+        if file_index > 0 {
+            masks[file_index] |= FILE_A << (file_index - 1);
+        }
+        if file_index < 7 {
+            masks[file_index] |= FILE_A << (file_index + 1);
+        }
+        file_index += 1;
+    }
+    masks
+}
+
+// indexed by file or rank, a to h and 1 to 8
+pub const FILE_MASKS: [u64; 8] = generate_file_masks();
+pub const RANK_MASKS: [u64; 8] = generate_rank_masks();
+pub const ADJACENT_FILE_MASKS: [u64; 8] = generate_adjacent_file_masks();
 pub const DARK_SQUARES: u64 = 0b0101010110101010010101011010101001010101101010100101010110101010;
 pub const LIGHT_SQUARES: u64 = 0b1010101001010101101010100101010110101010010101011010101001010101;
 
 pub fn get_starting_position() -> Position {
-    let starting_position = Position {
+    Position {
         white_pawn: 0b0000000000000000000000000000000000000000000000001111111100000000,
         white_knight: 0b0000000000000000000000000000000000000000000000000000000001000010,
         white_bishop: 0b0000000000000000000000000000000000000000000000000000000000100100,
@@ -171,6 +485,118 @@ pub fn get_starting_position() -> Position {
         turn: Colour::White,
         last_moved_squares: 0b0,
         en_passant_square: 0b0,
-    };
-    starting_position
+        castling_rights: 0b1000000100000000000000000000000000000000000000000000000010000001,
+        halfmove_clock: 0,
+        fullmove_number: 1,
+    }
+}
+
+pub fn get_empty_position() -> Position {
+    Position {
+        white_pawn: 0b0,
+        white_knight: 0b0,
+        white_bishop: 0b0,
+        white_rook: 0b0,
+        white_queen: 0b0,
+        white_king: 0b0,
+        black_pawn: 0b0,
+        black_knight: 0b0,
+        black_bishop: 0b0,
+        black_rook: 0b0,
+        black_queen: 0b0,
+        black_king: 0b0,
+        turn: Colour::White,
+        last_moved_squares: 0b0,
+        en_passant_square: 0b0,
+        castling_rights: 0b0,
+        halfmove_clock: 0,
+        fullmove_number: 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{notation::fen_to_position, utils::algebraic_to_index};
+
+    fn square(algebraic: &str) -> u64 {
+        1 << algebraic_to_index(algebraic).unwrap()
+    }
+
+    #[test]
+    fn move_piece_castles_by_king_destination() {
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        let king = Piece::new(&Class::King, &Colour::White);
+        let rook = Piece::new(&Class::Rook, &Colour::White);
+
+        let mut position = fen_to_position(fen).unwrap();
+        position.move_piece(&square("e1"), &square("g1")).unwrap();
+        assert_eq!(position.get_piece_at(&square("g1")), Some(&king));
+        assert_eq!(position.get_piece_at(&square("f1")), Some(&rook));
+
+        let mut position = fen_to_position(fen).unwrap();
+        position.move_piece(&square("e1"), &square("c1")).unwrap();
+        assert_eq!(position.get_piece_at(&square("c1")), Some(&king));
+        assert_eq!(position.get_piece_at(&square("d1")), Some(&rook));
+
+        let mut position = fen_to_position("r3k2r/8/8/8/8/8/8/R3K2R w - - 0 1").unwrap();
+        assert!(position.move_piece(&square("e1"), &square("g1")).is_err());
+    }
+
+    fn assert_problems(position: &Position, problems: &[PositionError]) {
+        assert_eq!(position.validate(), Err(problems.to_vec()));
+    }
+
+    #[test]
+    fn validate_reports_each_problem() {
+        let kings = fen_to_position("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let white_pawn = Piece::new(&Class::Pawn, &Colour::White);
+        let black_pawn = Piece::new(&Class::Pawn, &Colour::Black);
+        assert_eq!(get_starting_position().validate(), Ok(()));
+
+        let mut position = kings;
+        position.insert_piece_at_square(&Piece::new(&Class::Knight, &Colour::White), &square("e1"));
+        assert_problems(&position, &[PositionError::OverlappingPieces(square("e1"))]);
+
+        let mut position = kings;
+        position.remove_piece_at_square(&square("e8"));
+        assert_problems(&position, &[PositionError::MissingKing(Colour::Black)]);
+
+        let mut position = kings;
+        position.insert_piece_at_square(&Piece::new(&Class::King, &Colour::White), &square("a1"));
+        assert_problems(&position, &[PositionError::ExtraKings(Colour::White)]);
+
+        let mut position = kings;
+        position.insert_piece_at_square(&white_pawn, &square("a8"));
+        position.insert_piece_at_square(&black_pawn, &square("h1"));
+        assert_problems(
+            &position,
+            &[PositionError::PawnsOnBackRank(square("a8") | square("h1"))],
+        );
+
+        let mut position = kings;
+        position.insert_piece_at_square(&Piece::new(&Class::Rook, &Colour::White), &square("e4"));
+        assert_problems(&position, &[PositionError::OpponentInCheck(Colour::Black)]);
+
+        // no pawn has just passed over e6
+        let mut position = kings;
+        position.set_en_passant_square(&square("e6"));
+        assert_problems(
+            &position,
+            &[PositionError::ImpossibleEnPassant(square("e6"))],
+        );
+        let mut position = kings;
+        position.insert_piece_at_square(&black_pawn, &square("e5"));
+        position.set_en_passant_square(&square("e6"));
+        assert_eq!(position.validate(), Ok(()));
+
+        let mut position = kings;
+        position.set_castling_rights(&square("h1"));
+        assert_problems(
+            &position,
+            &[PositionError::InvalidCastlingRights(square("h1"))],
+        );
+        position.insert_piece_at_square(&Piece::new(&Class::Rook, &Colour::White), &square("h1"));
+        assert_eq!(position.validate(), Ok(()));
+    }
 }
@@ -0,0 +1,41 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// a small xorshift generator, plenty for picking moves and openings where
+// nothing depends on the numbers being unpredictable
+#[derive(Clone, Debug)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: &u64) -> Random {
+        // splitmix the seed so that nearby seeds give unrelated sequences,
+        // and so that the state is never the zero xorshift gets stuck on
+        let mut state = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        state = (state ^ (state >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        state = (state ^ (state >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        state ^= state >> 31;
+        Random {
+            state: state.max(1),
+        }
+    }
+
+    pub fn from_clock() -> Random {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64);
+        Random::new(&nanos)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    // a number from 0 up to but not including bound
+    pub fn below(&mut self, bound: &usize) -> usize {
+        (((self.next_u64() >> 32) * *bound as u64) >> 32) as usize
+    }
+}
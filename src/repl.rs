@@ -0,0 +1,896 @@
+use std::fs;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use chess_engine::move_generation::generate_legal_moves;
+use chess_engine::moves::Move;
+use chess_engine::notation::{
+    fen_to_position, line_to_san, move_to_san, move_to_uci, position_to_fen, san_to_move,
+    square_from_algebraic, uci_to_move,
+};
+use chess_engine::position::Position;
+use chess_engine::search::{
+    format_score, search_until_stopped, Evaluator, SearchLimits, SearchOptions, SearchResult,
+    SearchSignals, MAX_MULTI_PV,
+};
+use chess_engine::{
+    find_mate, game_to_pgn, get_input, parse_parameters, parse_pgn, print_board, trace_evaluation,
+    Clock, Colour, EndgameTables, Game, Network, Parameters, RenderOptions, Theme,
+    TranspositionTable,
+};
+
+use crate::editor::Editor;
+
+const HELP: &str = "Commands:
+  move MOVE          play a move, e.g. 'move e2e4', 'move Nf3' or just 'Nf3'
+  select SQUARE      show the moves of a piece, e.g. 'select e2' or just 'e2',
+                     then enter a highlighted square to move there
+  go [STRENGTH]      let the engine move for the side to move
+  play COLOUR [STRENGTH]
+                     play as 'white' or 'black' against the engine, or 'none',
+                     the engine thinking about its expected reply on your time
+  analyse N [STRENGTH]
+                     show the engine's N best moves with their lines, without
+                     playing any of them
+  mate N             look for a forced mate in N moves and show its line,
+                     giving up after 30 seconds
+  tables DIR         let the engine play by the distance to mate tables in DIR,
+                     made with the endgame tool, or 'tables off'
+  probe              show how far the position and each move are from mate
+                     by the tables
+  eval               break the static evaluation down term by term
+  fen                show the FEN of the position
+  load FEN|FILE      start from a FEN, or a FEN or PGN file
+  edit               set up a position by hand and play from it
+  params FILE        evaluate with weights from a tuner's parameter file, or
+                     'params default' to go back to the built-in ones
+  network FILE       evaluate with a NNUE weight file, or 'network off' and
+                     'network on' to switch between it and the handcrafted one
+  save FILE          save the game as PGN
+  undo, redo         step back or forward through the game
+  goto PLY           jump to a ply of the game, 0 being the start
+  flip               turn the board around
+  theme NAME         colour the board with 'brown', 'blue', '16' or 'plain'
+  pieces STYLE       draw pieces as 'letters' or 'unicode' symbols
+  coords on|off      show or hide the file and rank labels
+  help               show this message
+  quit               leave the game
+STRENGTH is 'depth N', 'time SECONDS', 'nodes N' or 'clock MINUTES [INCREMENT]'
+(default 'depth 4'). On a clock the engine manages its own time, gaining
+INCREMENT seconds after each move.";
+
+// how long 'mate' may look before giving up, since a long mate can take
+// far longer to prove than anyone would wait for
+const MATE_TIME_LIMIT: Duration = Duration::from_secs(30);
+
+const STRENGTH_USAGE: &str =
+    "Strength must be 'depth N', 'time SECONDS', 'nodes N' or 'clock MINUTES [INCREMENT]'.";
+
+pub struct EngineOpponent {
+    pub colour: Colour,
+    pub limits: SearchLimits,
+}
+
+fn get_default_limits() -> SearchLimits {
+    SearchLimits {
+        depth: Some(4),
+        ..SearchLimits::default()
+    }
+}
+
+pub fn parse_search_limits(input: &str) -> Result<SearchLimits, &'static str> {
+    let words: Vec<&str> = input.split_whitespace().collect();
+    if words.is_empty() {
+        return Ok(get_default_limits());
+    }
+    let mut limits = SearchLimits::default();
+    match words.as_slice() {
+        ["depth", depth] => match depth.parse() {
+            Ok(depth) if depth > 0 => limits.depth = Some(depth),
+            _ => return Err("Depth must be a positive whole number."),
+        },
+        ["time", seconds] => match seconds.parse::<f64>() {
+            Ok(seconds) if seconds > 0.0 => {
+                limits.movetime = Some(Duration::from_secs_f64(seconds))
+            }
+            _ => return Err("Time must be a positive number of seconds."),
+        },
+        ["nodes", nodes] => match nodes.parse() {
+            Ok(nodes) if nodes > 0 => limits.nodes = Some(nodes),
+            _ => return Err("Nodes must be a positive whole number."),
+        },
+        ["clock", minutes, increment @ ..] if increment.len() <= 1 => {
+            let minutes = match minutes.parse::<f64>() {
+                Ok(minutes) if minutes > 0.0 => minutes,
+                _ => return Err("Clock minutes must be a positive number."),
+            };
+            let increment = match increment.first().map(|seconds| seconds.parse::<f64>()) {
+                Some(Ok(seconds)) if seconds >= 0.0 => seconds,
+                Some(_) => return Err("Increment must be a number of seconds."),
+                None => 0.0,
+            };
+            limits.clock = Some(Clock {
+                remaining: Duration::from_secs_f64(minutes * 60.0),
+                increment: Duration::from_secs_f64(increment),
+                ..Clock::default()
+            });
+        }
+        _ => return Err(STRENGTH_USAGE),
+    }
+    Ok(limits)
+}
+
+fn parse_opponent(colour: &str, strength: &str) -> Result<Option<EngineOpponent>, &'static str> {
+    // the engine takes the colour the player did not choose
+    let colour = match colour {
+        "white" | "w" => Colour::Black,
+        "black" | "b" => Colour::White,
+        "none" | "n" | "" => return Ok(None),
+        _ => return Err("Colour must be 'white', 'black' or 'none'."),
+    };
+    let limits = parse_search_limits(strength)?;
+    Ok(Some(EngineOpponent { colour, limits }))
+}
+
+pub fn ask_for_engine_opponent() -> Option<EngineOpponent> {
+    loop {
+        let input =
+            get_input("Play as 'white' or 'black' against the engine, or 'none' for two players")?;
+        match parse_opponent(&input, "") {
+            Ok(None) => return None,
+            Ok(Some(_)) => (),
+            Err(e) => {
+                println!("{e}");
+                continue;
+            }
+        }
+
+        loop {
+            let strength = get_input(
+                "Engine strength? 'depth N', 'time SECONDS', 'nodes N' or 'clock MINUTES [INCREMENT]' (default 'depth 4')",
+            )?;
+            match parse_opponent(&input, &strength) {
+                Ok(opponent) => return opponent,
+                Err(e) => println!("{e}"),
+            }
+        }
+    }
+}
+
+// castling is stored as the king taking its rook, but is shown and entered
+// as the square the king lands on
+fn get_display_destination(position: &Position, mv: &Move) -> u64 {
+    match mv.is_castle() {
+        true => {
+            position
+                .get_castling_destinations(&mv.origin_square, &mv.destination_square)
+                .0
+        }
+        false => mv.destination_square,
+    }
+}
+
+fn get_move_squares(position: &Position, moves: &[Move]) -> u64 {
+    moves.iter().fold(0, |squares, mv| {
+        squares | get_display_destination(position, mv)
+    })
+}
+
+fn ask_for_promotion(moves: &[Move]) -> Option<Move> {
+    loop {
+        let input = get_input("Promote to which piece? ('q', 'r', 'b' or 'n')")?;
+        let promotion = moves.iter().find(|mv| match mv.promotion {
+            Some(class) => class.str().to_lowercase() == input.to_lowercase(),
+            None => false,
+        });
+        match promotion {
+            Some(mv) => return Some(*mv),
+            None => println!("'{}' is not a valid promotion.", &input),
+        }
+    }
+}
+
+enum Outcome {
+    Continue,
+    Quit,
+}
+
+// a search of the position after the reply the engine expects, run while
+// the player thinks and kept if the player makes that reply
+struct Ponder {
+    // the length of the game when the reply was expected
+    ply: usize,
+    expected_move: Move,
+    signals: Arc<SearchSignals>,
+    search_thread: JoinHandle<SearchResult>,
+}
+
+pub struct Session {
+    game: Game,
+    opponent: Option<EngineOpponent>,
+    selected_square: u64,
+    render_options: RenderOptions,
+    search_options: SearchOptions,
+    // kept from one search to the next until a new game is loaded or set up
+    table: Arc<TranspositionTable>,
+    network: Option<Arc<Network>>,
+    messages: Vec<String>,
+    ponder: Option<Ponder>,
+}
+
+impl Session {
+    pub fn new(
+        position: &Position,
+        opponent: Option<EngineOpponent>,
+        render_options: &RenderOptions,
+    ) -> Session {
+        let mut render_options = *render_options;
+        // playing black against the engine puts black at the bottom
+        render_options.flipped ^= matches!(
+            opponent,
+            Some(EngineOpponent {
+                colour: Colour::White,
+                ..
+            })
+        );
+        Session {
+            game: Game::new(position),
+            opponent,
+            selected_square: 0b0,
+            render_options,
+            search_options: SearchOptions::default(),
+            table: Arc::new(TranspositionTable::new(&SearchOptions::default().hash_size)),
+            network: None,
+            messages: Vec::new(),
+            ponder: None,
+        }
+    }
+
+    pub fn run(&mut self) {
+        self.play_engine_move();
+        self.show();
+        while let Some(input) = get_input("Enter a move or command ('help' for a list)") {
+            match self.execute(&input) {
+                Ok(Outcome::Quit) => break,
+                Ok(Outcome::Continue) => (),
+                Err(e) => self.messages.push(e),
+            }
+            self.stop_stale_ponder();
+            self.show();
+        }
+        self.stop_ponder();
+    }
+
+    fn show(&mut self) {
+        let position = self.game.get_position();
+        let move_squares = match self.selected_square {
+            0 => 0,
+            square => get_move_squares(position, &self.get_moves_from(&square)),
+        };
+        print_board(
+            position,
+            &self.selected_square,
+            &move_squares,
+            &self.game.get_move_list_lines(),
+            &self.render_options,
+        );
+        if !self.game.is_at_latest_ply() {
+            println!(
+                "Reviewing ply {} of {}, 'redo' or 'goto {}' to return",
+                self.game.get_ply(),
+                self.game.get_length(),
+                self.game.get_length()
+            );
+        }
+        if self.game.is_threefold_repetition() {
+            println!("Draw by threefold repetition");
+        }
+        for message in self.messages.drain(..) {
+            println!("{message}");
+        }
+    }
+
+    fn get_moves_from(&self, square: &u64) -> Vec<Move> {
+        generate_legal_moves(self.game.get_position())
+            .into_iter()
+            .filter(|mv| mv.origin_square == *square)
+            .collect()
+    }
+
+    fn execute(&mut self, input: &str) -> Result<Outcome, String> {
+        let (command, argument) = match input.split_once(char::is_whitespace) {
+            Some((command, argument)) => (command, argument.trim()),
+            None => (input, ""),
+        };
+
+        match command {
+            "" => (),
+            "help" | "h" | "?" => self.messages.push(HELP.to_string()),
+            "quit" | "exit" => return Ok(Outcome::Quit),
+            "move" | "m" => self.play_move_text(argument)?,
+            "select" | "s" => self.select_square(argument)?,
+            "go" => {
+                let limits = parse_search_limits(argument)?;
+                let result = self.play_search(&limits)?;
+                self.play_result(&result);
+            }
+            "play" => {
+                let (colour, strength) = argument.split_once(' ').unwrap_or((argument, ""));
+                self.opponent = parse_opponent(colour, strength)?;
+                self.play_engine_move();
+            }
+            "analyse" | "analyze" => self.analyse(argument)?,
+            "mate" => self.show_mate(argument)?,
+            "tables" => self.set_endgame_tables(argument)?,
+            "probe" => self.show_probe()?,
+            "eval" => {
+                let position = self.game.get_position();
+                let trace = trace_evaluation(position, &self.search_options.parameters);
+                self.messages.push(trace.to_string());
+                if let Evaluator::Network(network) = &self.search_options.evaluator {
+                    let score = match position.get_turn() {
+                        Colour::White => network.evaluate_position(position),
+                        Colour::Black => -network.evaluate_position(position),
+                    };
+                    self.messages.push(format!(
+                        "Network score {} (from white's side)",
+                        format_score(&score)
+                    ));
+                }
+            }
+            "fen" => self
+                .messages
+                .push(position_to_fen(self.game.get_position())),
+            "load" => self.load(argument)?,
+            "edit" => self.edit(),
+            "params" => self.load_parameters(argument)?,
+            "network" => self.set_network(argument)?,
+            "save" => {
+                if argument.is_empty() {
+                    return Err("Usage: save FILE".to_string());
+                }
+                fs::write(argument, game_to_pgn(&self.game)).map_err(|e| e.to_string())?;
+                self.messages.push(format!("Saved game to {argument}"));
+            }
+            "undo" => self.undo()?,
+            "redo" => self.redo()?,
+            "goto" => {
+                let ply = argument
+                    .parse::<usize>()
+                    .map_err(|_| format!("'{argument}' is not a ply number."))?;
+                self.game.go_to_ply(&ply)?;
+                self.selected_square = 0b0;
+            }
+            "flip" => self.render_options.flipped = !self.render_options.flipped,
+            "theme" => {
+                self.render_options.theme = Theme::from_name(argument).ok_or(format!(
+                    "Unknown theme '{argument}', try 'brown', 'blue', '16' or 'plain'."
+                ))?
+            }
+            "pieces" => {
+                self.render_options.unicode_pieces = match argument {
+                    "unicode" => true,
+                    "letters" => false,
+                    _ => return Err("Usage: pieces letters|unicode".to_string()),
+                }
+            }
+            "coords" => {
+                self.render_options.show_coordinates = match argument {
+                    "on" => true,
+                    "off" => false,
+                    _ => return Err("Usage: coords on|off".to_string()),
+                }
+            }
+            // anything else is taken to be a square or a move on its own
+            _ if argument.is_empty() && square_from_algebraic(command).is_ok() => {
+                self.select_square(command)?
+            }
+            _ if argument.is_empty() => self.play_move_text(command)?,
+            _ => return Err(format!("Unknown command '{command}', try 'help'.")),
+        }
+        Ok(Outcome::Continue)
+    }
+
+    fn play_move(&mut self, mv: &Move) {
+        self.game.make_move(mv);
+        self.selected_square = 0b0;
+        self.play_engine_move();
+    }
+
+    fn play_move_text(&mut self, text: &str) -> Result<(), String> {
+        if text.is_empty() {
+            return Err("Usage: move MOVE, e.g. 'move e2e4' or 'move Nf3'".to_string());
+        }
+        let position = self.game.get_position();
+        // long algebraic moves start with a square, anything else is read as SAN
+        let mv = match text.get(0..2).map(square_from_algebraic) {
+            Some(Ok(_)) if text.len() > 3 => {
+                uci_to_move(position, text).map_err(|e| e.to_string())?
+            }
+            _ => san_to_move(position, text).map_err(|e| e.to_string())?,
+        };
+        self.play_move(&mv);
+        Ok(())
+    }
+
+    // picks up a piece, or drops the picked up piece on one of its squares
+    fn select_square(&mut self, algebraic: &str) -> Result<(), String> {
+        let square = square_from_algebraic(algebraic).map_err(|e| e.to_string())?;
+        let position = *self.game.get_position();
+
+        if self.selected_square != 0 {
+            let matching_moves: Vec<Move> = self
+                .get_moves_from(&self.selected_square)
+                .into_iter()
+                .filter(|mv| get_display_destination(&position, mv) == square)
+                .collect();
+            if !matching_moves.is_empty() {
+                let mv = match matching_moves.len() {
+                    1 => Some(matching_moves[0]),
+                    _ => ask_for_promotion(&matching_moves),
+                };
+                if let Some(mv) = mv {
+                    self.play_move(&mv);
+                }
+                return Ok(());
+            }
+        }
+
+        match position.get_piece_with_colour_at(&square, &position.get_turn()) {
+            Some(_) => {
+                self.selected_square = square;
+                Ok(())
+            }
+            // a bare square can also be a pawn move in SAN, e.g. 'e4'
+            None => {
+                self.selected_square = 0b0;
+                match san_to_move(&position, algebraic) {
+                    Ok(mv) => {
+                        self.play_move(&mv);
+                        Ok(())
+                    }
+                    Err(_) => Err(format!("No piece to move on square {algebraic}")),
+                }
+            }
+        }
+    }
+
+    fn play_search(&mut self, limits: &SearchLimits) -> Result<SearchResult, String> {
+        self.stop_ponder();
+        let position = *self.game.get_position();
+        if generate_legal_moves(&position).is_empty() {
+            return Err("There are no legal moves in this position.".to_string());
+        }
+        println!("Engine is thinking...");
+        Ok(self.search(&position, limits, &self.search_options))
+    }
+
+    fn play_result(&mut self, result: &SearchResult) {
+        let position = *self.game.get_position();
+        if let Some(mv) = result.best_move {
+            self.game.make_move(&mv);
+            self.selected_square = 0b0;
+            self.messages.push(format!(
+                "Engine played {} (depth {}, score {}, {} nodes)",
+                move_to_uci(&position, &mv, &false),
+                result.depth,
+                format_score(&result.score),
+                result.nodes
+            ));
+        }
+    }
+
+    fn search(
+        &self,
+        position: &Position,
+        limits: &SearchLimits,
+        options: &SearchOptions,
+    ) -> SearchResult {
+        let signals = SearchSignals::default();
+        search_until_stopped(
+            position,
+            &self.game.get_history(),
+            limits,
+            options,
+            &self.table,
+            &signals,
+            &mut |_| {},
+        )
+    }
+
+    // searches the best few moves in turn, each without the ones before it
+    fn analyse(&mut self, argument: &str) -> Result<(), String> {
+        let (count, strength) = argument.split_once(' ').unwrap_or((argument, ""));
+        let multi_pv = match count.parse() {
+            Ok(count @ 1..=MAX_MULTI_PV) => count,
+            _ => return Err("Usage: analyse N [STRENGTH], e.g. 'analyse 3 depth 6'".to_string()),
+        };
+        let limits = parse_search_limits(strength)?;
+        let position = *self.game.get_position();
+        if generate_legal_moves(&position).is_empty() {
+            return Err("There are no legal moves in this position.".to_string());
+        }
+        println!("Engine is analysing...");
+
+        let options = SearchOptions {
+            multi_pv,
+            ..self.search_options.clone()
+        };
+        let result = self.search(&position, &limits, &options);
+        self.messages.push(format!(
+            "Analysis to depth {} ({} nodes):",
+            result.depth, result.nodes
+        ));
+        for (index, line) in result.lines.iter().enumerate() {
+            self.messages.push(format!(
+                "{:>3}. {:>13}  {}",
+                index + 1,
+                format_score(&line.score),
+                line_to_san(&position, &line.pv)
+            ));
+        }
+        Ok(())
+    }
+
+    fn show_mate(&mut self, argument: &str) -> Result<(), String> {
+        let moves = match argument.parse::<u32>() {
+            Ok(moves) if moves > 0 => moves,
+            _ => return Err("Usage: mate N, e.g. 'mate 3'".to_string()),
+        };
+        self.stop_ponder();
+        let position = *self.game.get_position();
+        if generate_legal_moves(&position).is_empty() {
+            return Err("There are no legal moves in this position.".to_string());
+        }
+        println!("Engine is looking for a mate...");
+
+        let result = find_mate(
+            &position,
+            &moves,
+            &Some(MATE_TIME_LIMIT),
+            &SearchSignals::default(),
+        );
+        let summary = format!(
+            "{} nodes, {:.1}s",
+            result.nodes,
+            result.elapsed.as_secs_f64()
+        );
+        match (&result.line, result.get_mate_length()) {
+            (Some(line), Some(length)) => self.messages.push(format!(
+                "Mate in {length} ({summary}): {}",
+                line_to_san(&position, line)
+            )),
+            _ if result.stopped => self.messages.push(format!(
+                "Gave up looking for a mate in {moves} ({summary})."
+            )),
+            _ => self
+                .messages
+                .push(format!("No forced mate in {moves} ({summary}).")),
+        }
+        Ok(())
+    }
+
+    fn set_endgame_tables(&mut self, argument: &str) -> Result<(), String> {
+        match argument {
+            "" => return Err("Usage: tables DIR|off".to_string()),
+            "off" => {
+                self.search_options.endgame_tables = None;
+                self.messages
+                    .push("Playing without endgame tables".to_string());
+                return Ok(());
+            }
+            _ => (),
+        }
+        let tables = EndgameTables::open(argument).map_err(|e| e.to_string())?;
+        if tables.is_empty() {
+            return Err(format!("No endgame tables in '{argument}'."));
+        }
+        self.messages.push(format!(
+            "Loaded {} endgame tables: {}",
+            tables.len(),
+            tables.get_names().join(", ")
+        ));
+        self.search_options.endgame_tables = Some(Arc::new(tables));
+        Ok(())
+    }
+
+    // the result by the tables, then every move the tables hold the
+    // position after, best first
+    fn show_probe(&mut self) -> Result<(), String> {
+        let tables = self
+            .search_options
+            .endgame_tables
+            .as_ref()
+            .ok_or("No endgame tables loaded yet, use 'tables DIR'.")?;
+        let position = *self.game.get_position();
+        let dtm = tables
+            .probe(&position)
+            .ok_or("The loaded tables do not hold this position.")?;
+        let format_dtm_score = |score: &i32| match score {
+            0 => "draw".to_string(),
+            score => format_score(score),
+        };
+
+        let mut moves: Vec<(i32, String)> = generate_legal_moves(&position)
+            .iter()
+            .filter_map(|mv| {
+                let mut next_position = position;
+                next_position.make_move(mv);
+                let score = -tables.probe(&next_position)?.get_score(&1);
+                Some((score, move_to_san(&position, mv)))
+            })
+            .collect();
+        moves.sort_by_key(|(score, _)| -score);
+        self.messages.push(format!(
+            "Tables give {}",
+            format_dtm_score(&dtm.get_score(&0))
+        ));
+        for (score, san) in moves {
+            self.messages
+                .push(format!("{san:>8}  {}", format_dtm_score(&score)));
+        }
+        Ok(())
+    }
+
+    // lets the engine reply if it is its turn, leaving its move highlighted. the
+    // engine stays quiet while an earlier ply is being reviewed
+    fn play_engine_move(&mut self) {
+        let limits = match &self.opponent {
+            Some(opponent) if opponent.colour == self.game.get_position().get_turn() => {
+                opponent.limits
+            }
+            _ => return,
+        };
+        if !self.game.is_at_latest_ply()
+            || generate_legal_moves(self.game.get_position()).is_empty()
+            || self.game.is_threefold_repetition()
+        {
+            return;
+        }
+        self.show();
+        // an error here only means the game is over, which the board already shows
+        let (result, elapsed) = match self.take_ponder_hit() {
+            Some(ponder) => {
+                println!("Engine is thinking...");
+                // the search kept going while the player thought, but only the
+                // time since the reply comes off the engine's clock
+                let hit_time = Instant::now();
+                ponder.signals.ponder.store(false, Ordering::Relaxed);
+                match ponder.search_thread.join() {
+                    Ok(result) => (result, hit_time.elapsed()),
+                    Err(_) => return,
+                }
+            }
+            None => match self.play_search(&limits) {
+                Ok(result) => {
+                    let elapsed = result.elapsed;
+                    (result, elapsed)
+                }
+                Err(_) => return,
+            },
+        };
+        self.play_result(&result);
+        self.update_engine_clock(&elapsed);
+        if let Some(ponder_move) = result.ponder_move {
+            self.start_ponder(&ponder_move);
+        }
+    }
+
+    // thinks about the position after the expected reply until the player
+    // moves, with the same limits the engine plays to
+    fn start_ponder(&mut self, expected_move: &Move) {
+        let Some(opponent) = &self.opponent else {
+            return;
+        };
+        let mut game = self.game.clone();
+        game.make_move(expected_move);
+        let position = *game.get_position();
+        if generate_legal_moves(&position).is_empty() || game.is_threefold_repetition() {
+            return;
+        }
+        let history = game.get_history();
+        let limits = opponent.limits;
+        let search_options = self.search_options.clone();
+        let table = Arc::clone(&self.table);
+        let signals = Arc::new(SearchSignals::default());
+        signals.ponder.store(true, Ordering::Relaxed);
+        let search_signals = Arc::clone(&signals);
+        let search_thread = thread::spawn(move || {
+            search_until_stopped(
+                &position,
+                &history,
+                &limits,
+                &search_options,
+                &table,
+                &search_signals,
+                &mut |_| {},
+            )
+        });
+        self.ponder = Some(Ponder {
+            ply: self.game.get_length(),
+            expected_move: *expected_move,
+            signals,
+            search_thread,
+        });
+    }
+
+    fn stop_ponder(&mut self) {
+        if let Some(ponder) = self.ponder.take() {
+            ponder.signals.stop.store(true, Ordering::Relaxed);
+            let _ = ponder.search_thread.join();
+        }
+    }
+
+    // the ponder search, if the player has just made the expected reply
+    fn take_ponder_hit(&mut self) -> Option<Ponder> {
+        let ponder = self.ponder.as_ref()?;
+        let is_hit = self.game.is_at_latest_ply()
+            && self.game.get_length() == ponder.ply + 1
+            && self.game.get_moves().last() == Some(&ponder.expected_move);
+        match is_hit {
+            true => self.ponder.take(),
+            false => None,
+        }
+    }
+
+    // once the game has moved on from where the ponder search expected, or
+    // there is no longer an engine to move, the search is of no more use
+    fn stop_stale_ponder(&mut self) {
+        let is_stale = match &self.ponder {
+            Some(ponder) => {
+                self.opponent.is_none()
+                    || !self.game.is_at_latest_ply()
+                    || self.game.get_length() != ponder.ply
+            }
+            None => false,
+        };
+        if is_stale {
+            self.stop_ponder();
+        }
+    }
+
+    // the engine's clock runs down by however long it thought, then gains
+    // the increment
+    fn update_engine_clock(&mut self, elapsed: &Duration) {
+        let Some(clock) = self
+            .opponent
+            .as_mut()
+            .and_then(|opponent| opponent.limits.clock.as_mut())
+        else {
+            return;
+        };
+        clock.remaining = clock.remaining.saturating_sub(*elapsed) + clock.increment;
+        let seconds = clock.remaining.as_secs();
+        self.messages
+            .push(format!("Engine clock {}:{:02}", seconds / 60, seconds % 60));
+    }
+
+    fn is_engine_turn(&self) -> bool {
+        match &self.opponent {
+            Some(opponent) => opponent.colour == self.game.get_position().get_turn(),
+            None => false,
+        }
+    }
+
+    // against the engine, undo and redo step over its reply as well so it is
+    // the player's turn again
+    fn undo(&mut self) -> Result<(), String> {
+        if !self.game.undo() {
+            return Err("Nothing to undo.".to_string());
+        }
+        // the engine's opening move is left alone, since the player would
+        // only be handed back a position where it is the engine's turn
+        if self.is_engine_turn() && !self.game.undo() {
+            self.game.redo();
+            return Err("Nothing to undo, the engine's first move stays.".to_string());
+        }
+        self.selected_square = 0b0;
+        Ok(())
+    }
+
+    fn redo(&mut self) -> Result<(), String> {
+        if !self.game.redo() {
+            return Err("Nothing to redo.".to_string());
+        }
+        if self.is_engine_turn() {
+            self.game.redo();
+        }
+        self.selected_square = 0b0;
+        Ok(())
+    }
+
+    fn edit(&mut self) {
+        let mut editor = Editor::new(self.game.get_position(), &self.render_options);
+        if let Some(position) = editor.run() {
+            self.stop_ponder();
+            self.table.clear();
+            self.game = Game::new(&position);
+            self.selected_square = 0b0;
+            self.messages
+                .push("Playing from the edited position".to_string());
+            self.play_engine_move();
+        }
+    }
+
+    fn load(&mut self, argument: &str) -> Result<(), String> {
+        if argument.is_empty() {
+            return Err("Usage: load FEN|FILE".to_string());
+        }
+
+        let game = match fen_to_position(argument) {
+            Ok(position) => Game::new(&position),
+            Err(fen_error) => {
+                let text = fs::read_to_string(argument).map_err(|_| {
+                    format!("'{argument}' is neither a FEN ({fen_error}) nor a file.")
+                })?;
+                match fen_to_position(text.trim()) {
+                    Ok(position) => Game::new(&position),
+                    Err(_) => {
+                        let pgn_game = parse_pgn(&text)
+                            .map_err(|e| e.to_string())?
+                            .into_iter()
+                            .next()
+                            .ok_or("No game found in file.")?;
+                        let mut game = Game::new(&pgn_game.start_position);
+                        for mv in &pgn_game.moves {
+                            game.make_move(mv);
+                        }
+                        game
+                    }
+                }
+            }
+        };
+        self.stop_ponder();
+        self.table.clear();
+        self.game = game;
+        self.selected_square = 0b0;
+        self.messages.push(format!("Loaded {argument}"));
+        Ok(())
+    }
+
+    fn load_parameters(&mut self, argument: &str) -> Result<(), String> {
+        let parameters = match argument {
+            "" => return Err("Usage: params FILE|default".to_string()),
+            "default" => Parameters::default(),
+            _ => {
+                let text = fs::read_to_string(argument)
+                    .map_err(|e| format!("Could not read '{argument}': {e}"))?;
+                parse_parameters(&text).map_err(|e| format!("In '{argument}': {e}"))?
+            }
+        };
+        self.search_options.parameters = parameters;
+        self.messages
+            .push(format!("Evaluating with the {argument} parameters"));
+        Ok(())
+    }
+
+    fn set_network(&mut self, argument: &str) -> Result<(), String> {
+        match argument {
+            "" => return Err("Usage: network FILE|on|off".to_string()),
+            "off" => {
+                self.search_options.evaluator = Evaluator::Handcrafted;
+                self.messages
+                    .push("Evaluating with the handcrafted evaluation".to_string());
+                return Ok(());
+            }
+            "on" => (),
+            _ => {
+                let bytes =
+                    fs::read(argument).map_err(|e| format!("Could not read '{argument}': {e}"))?;
+                let network =
+                    Network::from_bytes(&bytes).map_err(|e| format!("In '{argument}': {e}"))?;
+                self.network = Some(Arc::new(network));
+            }
+        }
+        let network = self
+            .network
+            .as_ref()
+            .ok_or("No network loaded yet, use 'network FILE'.")?;
+        self.search_options.evaluator = Evaluator::Network(Arc::clone(network));
+        self.messages
+            .push("Evaluating with the network".to_string());
+        Ok(())
+    }
+}
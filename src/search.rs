@@ -0,0 +1,1301 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::endgame_tables::{Dtm, EndgameTables};
+use crate::evaluation::{evaluate_with_pawn_table, get_piece_value};
+use crate::game::count_repetitions;
+use crate::move_generation::generate_legal_moves;
+use crate::moves::{Move, MoveKind};
+use crate::nnue::{Accumulator, Network};
+use crate::parameters::Parameters;
+use crate::pawn_structure::PawnHashTable;
+use crate::pieces::{Class, Colour};
+use crate::position::Position;
+use crate::see::see_at_least;
+use crate::syzygy::{Tablebases, Wdl};
+use crate::time_management::{Clock, TimeManager};
+use crate::transposition::{encode_move, Bound, TableEntry, TranspositionTable};
+use crate::utils::bitboard_to_index;
+use crate::zobrist::get_hash;
+
+pub const INFINITY: i32 = 32000;
+pub const MATE_SCORE: i32 = 31000;
+// any score beyond this is a forced mate found within the search tree
+pub const MATE_THRESHOLD: i32 = MATE_SCORE - 1000;
+pub const MAX_DEPTH: u8 = 64;
+// a win the tablebases prove, less the ply it was found at, which ranks
+// below any mate the search finds but above any evaluation
+pub const TABLEBASE_WIN_SCORE: i32 = MATE_THRESHOLD - 1 - MAX_DEPTH as i32;
+// evaluations are held below any tablebase win, which also keeps every
+// score the search stores within the transposition table's 16 bits
+pub const MAX_EVALUATION: i32 = TABLEBASE_WIN_SCORE - MAX_DEPTH as i32 - 1;
+pub const DEFAULT_HASH_SIZE: usize = 16;
+
+// how many nodes to search between checks of the clock
+const TIME_CHECK_INTERVAL: u64 = 1024;
+
+// a null move is searched this many plies shallower, and more again the
+// deeper the search still has to go
+const NULL_MOVE_MIN_DEPTH: u8 = 3;
+const NULL_MOVE_REDUCTION: u8 = 3;
+const NULL_MOVE_DEPTH_DIVISOR: u8 = 6;
+// from this depth a null move cutoff is only taken once a normal search to
+// the same reduced depth agrees, which catches the zugzwangs the material
+// check misses
+const NULL_MOVE_VERIFICATION_DEPTH: u8 = 6;
+
+// quiet moves this far down the ordering are searched shallower, by
+// base + ln(depth) * ln(move number) / divisor plies
+const LATE_MOVE_MIN_DEPTH: u8 = 3;
+const LATE_MOVE_MIN_INDEX: usize = 3;
+const LATE_MOVE_REDUCTION_BASE: f64 = 0.75;
+const LATE_MOVE_REDUCTION_DIVISOR: f64 = 2.25;
+
+// a move from the table is tried for a singular extension from this depth,
+// if it was stored no more than the margin shallower, and counts as singular
+// when every other move falls this many centipawns a ply below its score
+const SINGULAR_MIN_DEPTH: u8 = 6;
+const SINGULAR_DEPTH_MARGIN: u8 = 3;
+const SINGULAR_MARGIN: i32 = 2;
+
+// the first window each iteration tries around the score of the one
+// before, doubled on whichever side it fails
+const ASPIRATION_MIN_DEPTH: u8 = 4;
+const ASPIRATION_WINDOW: i32 = 25;
+
+// history scores are held within this, which keeps every quiet move below
+// the good captures and promotions in the ordering
+const HISTORY_MAX: i32 = 4000;
+
+pub const MAX_THREADS: usize = 256;
+pub const MAX_MULTI_PV: usize = 256;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SearchLimits {
+    pub depth: Option<u8>,
+    pub movetime: Option<Duration>,
+    pub nodes: Option<u64>,
+    // playing to a clock, with the time for each move left to a time manager
+    pub clock: Option<Clock>,
+}
+
+// which evaluation the search calls at its leaves
+#[derive(Clone, Debug, Default)]
+pub enum Evaluator {
+    #[default]
+    Handcrafted,
+    Network(Arc<Network>),
+}
+
+// how the engine searches and evaluates, as opposed to how long for. the
+// search techniques can each be turned off to measure what they are worth
+#[derive(Clone, Debug)]
+pub struct SearchOptions {
+    pub parameters: Parameters,
+    pub evaluator: Evaluator,
+    // megabytes for the transposition table
+    pub hash_size: usize,
+    pub principal_variation_search: bool,
+    pub aspiration_windows: bool,
+    pub check_extensions: bool,
+    pub singular_extensions: bool,
+    // searching the same tree at once, sharing what they find through the
+    // transposition table
+    pub threads: usize,
+    // how many of the best root moves to find a line and score for
+    pub multi_pv: usize,
+    // endgame tables, which limit the root to the moves keeping the best
+    // result and end the search wherever a capture or pawn move reaches them
+    pub tablebases: Option<Arc<Tablebases>>,
+    // distance to mate tables of the smallest endings, which give the exact
+    // score of any position they hold
+    pub endgame_tables: Option<Arc<EndgameTables>>,
+}
+
+impl Default for SearchOptions {
+    fn default() -> SearchOptions {
+        SearchOptions {
+            parameters: Parameters::default(),
+            evaluator: Evaluator::default(),
+            hash_size: DEFAULT_HASH_SIZE,
+            principal_variation_search: true,
+            aspiration_windows: true,
+            check_extensions: true,
+            singular_extensions: true,
+            threads: 1,
+            multi_pv: 1,
+            tablebases: None,
+            endgame_tables: None,
+        }
+    }
+}
+
+// one of the root moves of a multipv search, with the line it leads to
+#[derive(Clone, Debug)]
+pub struct SearchLine {
+    pub score: i32,
+    pub pv: Vec<Move>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SearchResult {
+    pub best_move: Option<Move>,
+    pub score: i32,
+    pub depth: u8,
+    pub nodes: u64,
+    pub elapsed: Duration,
+    pub pv: Vec<Move>,
+    // the reply expected to the best move, to think about on the opponent's
+    // time
+    pub ponder_move: Option<Move>,
+    // best first, as many as the multipv option asks for, the first being
+    // the same as the score and pv above
+    pub lines: Vec<SearchLine>,
+}
+
+// raised from another thread while a search runs, e.g. on UCI commands
+#[derive(Debug, Default)]
+pub struct SearchSignals {
+    // stop as soon as possible
+    pub stop: AtomicBool,
+    // thinking on the opponent's time, so no time limit applies until this
+    // is cleared when the expected move is played
+    pub ponder: AtomicBool,
+}
+
+// what every thread searching the same position shares
+struct SharedSearch<'a> {
+    table: &'a TranspositionTable,
+    // hashes of the positions the game passed through before the root
+    history: &'a [u64],
+    start_time: Instant,
+    signals: &'a SearchSignals,
+    // raised by the main thread once it has finished, so the helpers stop
+    // with it
+    threads_stop: AtomicBool,
+    // each thread's nodes, published every so often so that the total can be
+    // reported
+    node_counts: Vec<AtomicU64>,
+}
+
+struct Searcher<'a> {
+    limits: SearchLimits,
+    options: &'a SearchOptions,
+    shared: &'a SharedSearch<'a>,
+    // 0 for the main thread, which alone keeps to the limits and reports
+    thread_index: usize,
+    // the shorter of any fixed movetime and the time manager's hard limit
+    time_limit: Option<Duration>,
+    // the share of the last root search's nodes spent on its best move
+    best_move_effort: f64,
+    // the time manager would have stopped while pondering, so the search
+    // stops as soon as the expected move is played
+    stop_on_ponderhit: bool,
+    nodes: u64,
+    stopped: bool,
+    pawn_table: PawnHashTable,
+    // one per ply, each worked out from the one before it as moves are made.
+    // there is nothing to undo when a move is taken back, since the parent's
+    // accumulator is still in its own slot
+    accumulators: Vec<Accumulator>,
+    // how well each quiet move has done at causing cutoffs, by the side
+    // making it and its origin and destination
+    history: [[[i32; 64]; 64]; 2],
+    // the move each ply is searching without, while checking whether the
+    // move from the table is singular
+    excluded_moves: [Option<Move>; MAX_DEPTH as usize],
+    // whether each ply is passing, so that the ply below knows its position
+    // came from a null move
+    null_moves: [bool; MAX_DEPTH as usize],
+    // hashes of the game before the root followed by those of the current
+    // line, cut back to the ply being searched whenever a node is entered
+    hashes: Vec<u64>,
+    // the moves the root may choose from, all of them unless the tablebases
+    // have ruled some out
+    root_moves: Vec<Move>,
+    // root moves already given a line in this iteration of a multipv search
+    excluded_root_moves: Vec<Move>,
+}
+
+impl<'a> Searcher<'a> {
+    fn new(
+        position: &Position,
+        limits: &SearchLimits,
+        options: &'a SearchOptions,
+        shared: &'a SharedSearch<'a>,
+        thread_index: usize,
+        time_limit: Option<Duration>,
+    ) -> Searcher<'a> {
+        let accumulators = match &options.evaluator {
+            Evaluator::Network(network) => vec![network.new_accumulator(position)],
+            Evaluator::Handcrafted => Vec::new(),
+        };
+        Searcher {
+            limits: *limits,
+            options,
+            shared,
+            thread_index,
+            time_limit,
+            best_move_effort: 0.0,
+            stop_on_ponderhit: false,
+            nodes: 0,
+            stopped: false,
+            pawn_table: PawnHashTable::new(),
+            accumulators,
+            history: [[[0; 64]; 64]; 2],
+            excluded_moves: [None; MAX_DEPTH as usize],
+            null_moves: [false; MAX_DEPTH as usize],
+            hashes: shared.history.to_vec(),
+            root_moves: Vec::new(),
+            excluded_root_moves: Vec::new(),
+        }
+    }
+
+    fn update_accumulator(&mut self, parent_position: &Position, position: &Position, ply: u8) {
+        let Evaluator::Network(network) = &self.options.evaluator else {
+            return;
+        };
+        let ply = ply as usize;
+        if self.accumulators.len() <= ply {
+            self.accumulators.push(network.new_accumulator(position));
+            return;
+        }
+        let (parents, children) = self.accumulators.split_at_mut(ply);
+        network.update(
+            &parents[ply - 1],
+            parent_position,
+            position,
+            &mut children[0],
+        );
+    }
+
+    fn evaluate(&mut self, position: &Position, ply: u8) -> i32 {
+        let score = match &self.options.evaluator {
+            Evaluator::Handcrafted => {
+                evaluate_with_pawn_table(position, &self.options.parameters, &mut self.pawn_table)
+            }
+            Evaluator::Network(network) => {
+                network.evaluate(&self.accumulators[ply as usize], &position.turn)
+            }
+        };
+        score.clamp(-MAX_EVALUATION, MAX_EVALUATION)
+    }
+
+    fn should_stop(&mut self) -> bool {
+        if self.stopped {
+            return true;
+        }
+        if self.nodes.is_multiple_of(TIME_CHECK_INTERVAL) {
+            self.shared.node_counts[self.thread_index].store(self.nodes, Ordering::Relaxed);
+            if self.shared.signals.stop.load(Ordering::Relaxed)
+                || self.shared.threads_stop.load(Ordering::Relaxed)
+            {
+                self.stopped = true;
+            }
+        }
+        if let Some(max_nodes) = self.limits.nodes {
+            if self.nodes >= max_nodes {
+                self.stopped = true;
+            }
+        }
+        if self.nodes.is_multiple_of(TIME_CHECK_INTERVAL)
+            && !self.shared.signals.ponder.load(Ordering::Relaxed)
+        {
+            let is_past_time_limit = self
+                .time_limit
+                .is_some_and(|time_limit| self.shared.start_time.elapsed() >= time_limit);
+            if is_past_time_limit || self.stop_on_ponderhit {
+                self.stopped = true;
+            }
+        }
+        self.stopped
+    }
+
+    // a repeat of any earlier position since the last capture or pawn move
+    // is scored as a draw, since the side that benefits can always repeat
+    // again. positions from before a null move are not real predecessors,
+    // so the look back stops there
+    fn is_repetition(&self, position: &Position, hash: &u64, ply: u8) -> bool {
+        let ply = ply as usize;
+        let earlier_hashes = &self.hashes[..self.shared.history.len() + ply];
+        let plies_back = match self.null_moves[..ply].iter().rposition(|null| *null) {
+            Some(null_ply) => ply - null_ply - 1,
+            None => earlier_hashes.len(),
+        };
+        let plies_back = plies_back.min(position.halfmove_clock as usize);
+        count_repetitions(earlier_hashes, hash, &plies_back) > 0
+    }
+
+    // this thread's own nodes, which are the most up to date, and the last
+    // published by every other
+    fn get_total_nodes(&self) -> u64 {
+        let other_nodes: u64 = (self.shared.node_counts.iter().enumerate())
+            .filter(|(thread_index, _)| *thread_index != self.thread_index)
+            .map(|(_, nodes)| nodes.load(Ordering::Relaxed))
+            .sum();
+        self.nodes + other_nodes
+    }
+
+    fn get_history(&self, colour: &Colour, mv: &Move) -> i32 {
+        self.history[get_colour_index(colour)][bitboard_to_index(&mv.origin_square)]
+            [bitboard_to_index(&mv.destination_square)]
+    }
+
+    // moves the score towards the bonus's end of the range, by less the
+    // closer it already is, so that it never leaves the range
+    fn update_history(&mut self, colour: &Colour, mv: &Move, bonus: i32) {
+        let score = &mut self.history[get_colour_index(colour)]
+            [bitboard_to_index(&mv.origin_square)][bitboard_to_index(&mv.destination_square)];
+        *score += bonus - *score * bonus.abs() / HISTORY_MAX;
+    }
+
+    // the quiet move that caused a cutoff is rewarded and the quiet moves
+    // tried before it, which did not, are penalised
+    fn update_quiet_histories(
+        &mut self,
+        colour: &Colour,
+        cutoff_move: &Move,
+        quiet_moves_tried: &[Move],
+        depth: u8,
+    ) {
+        let bonus = (depth as i32 * depth as i32).min(HISTORY_MAX);
+        self.update_history(colour, cutoff_move, bonus);
+        for mv in quiet_moves_tried {
+            self.update_history(colour, mv, -bonus);
+        }
+    }
+
+    // the table or pv move first, then captures and promotions, then quiet
+    // moves by their history
+    fn order_moves_by_history(
+        &self,
+        position: &Position,
+        moves: &mut [Move],
+        first_move: Option<Move>,
+    ) {
+        moves.sort_by_cached_key(|mv| {
+            if Some(*mv) == first_move {
+                return i32::MIN;
+            }
+            match is_quiet(position, mv) {
+                true => -self.get_history(&position.turn, mv),
+                false => -score_move(position, mv),
+            }
+        });
+    }
+
+    // passing is only worth trying when standing still already looks good
+    // enough to fail high and the side to move has pieces to make use of the
+    // spare tempo with. two passes in a row would just search the same
+    // position again
+    fn can_try_null_move(
+        &mut self,
+        position: &Position,
+        depth: u8,
+        ply: u8,
+        beta: i32,
+        in_check: bool,
+    ) -> bool {
+        ply > 0
+            && self.excluded_moves[ply as usize].is_none()
+            && !self.null_moves[ply as usize - 1]
+            && depth >= NULL_MOVE_MIN_DEPTH
+            && !in_check
+            && beta.abs() < MATE_THRESHOLD
+            && position.has_non_pawn_material(&position.turn)
+            && self.evaluate(position, ply) >= beta
+    }
+
+    // Some with the score to cut off with if giving the opponent a free move
+    // still leaves the side to move at or above beta
+    fn null_move_search(
+        &mut self,
+        position: &Position,
+        depth: u8,
+        ply: u8,
+        beta: i32,
+    ) -> Option<i32> {
+        let reduction = NULL_MOVE_REDUCTION + depth / NULL_MOVE_DEPTH_DIVISOR;
+        let reduced_depth = depth.saturating_sub(1 + reduction);
+        let mut next_position = *position;
+        next_position.make_null_move();
+        self.update_accumulator(position, &next_position, ply + 1);
+        self.null_moves[ply as usize] = true;
+        let score = -self.negamax(
+            &next_position,
+            reduced_depth,
+            ply + 1,
+            -beta,
+            -beta + 1,
+            &mut Vec::new(),
+        );
+        self.null_moves[ply as usize] = false;
+        if self.stopped || score < beta {
+            return None;
+        }
+
+        // a mate found after passing is not a mate the side to move can force
+        let score = match score >= MATE_THRESHOLD {
+            true => beta,
+            false => score,
+        };
+        if depth < NULL_MOVE_VERIFICATION_DEPTH {
+            return Some(score);
+        }
+        let verified_score = self.negamax(
+            position,
+            reduced_depth,
+            ply,
+            beta - 1,
+            beta,
+            &mut Vec::new(),
+        );
+        match !self.stopped && verified_score >= beta {
+            true => Some(score),
+            false => None,
+        }
+    }
+
+    // whether the move from the table is so much better than the rest that
+    // nothing else comes within a margin of its score, searching the others
+    // to half the depth. such a move is worth searching a ply deeper, since
+    // the position hangs on it
+    fn is_singular(
+        &mut self,
+        position: &Position,
+        depth: u8,
+        ply: u8,
+        entry: &TableEntry,
+        table_move: &Move,
+    ) -> bool {
+        let is_candidate = self.options.singular_extensions
+            && ply > 0
+            && depth >= SINGULAR_MIN_DEPTH
+            && self.excluded_moves[ply as usize].is_none()
+            && entry.bound != Bound::Upper
+            && entry.depth + SINGULAR_DEPTH_MARGIN >= depth
+            && entry.score.abs() < MATE_THRESHOLD;
+        if !is_candidate {
+            return false;
+        }
+
+        let singular_beta = entry.score - SINGULAR_MARGIN * depth as i32;
+        self.excluded_moves[ply as usize] = Some(*table_move);
+        let score = self.negamax(
+            position,
+            (depth - 1) / 2,
+            ply,
+            singular_beta - 1,
+            singular_beta,
+            &mut Vec::new(),
+        );
+        self.excluded_moves[ply as usize] = None;
+        !self.stopped && score < singular_beta
+    }
+
+    fn negamax(
+        &mut self,
+        position: &Position,
+        depth: u8,
+        ply: u8,
+        mut alpha: i32,
+        beta: i32,
+        pv: &mut Vec<Move>,
+    ) -> i32 {
+        // the incoming line is only a hint for ordering, it is rebuilt below
+        let pv_move = pv_move_at(pv, 0);
+        pv.clear();
+        if depth == 0 || ply >= MAX_DEPTH {
+            return self.quiescence(position, ply, alpha, beta);
+        }
+
+        self.nodes += 1;
+        if self.should_stop() {
+            return 0;
+        }
+        let hash = get_hash(position);
+        if ply > 0 && self.is_repetition(position, &hash, ply) {
+            return 0;
+        }
+        self.hashes
+            .truncate(self.shared.history.len() + ply as usize);
+        self.hashes.push(hash);
+
+        // a search with a move left out is of a different tree from the full
+        // one, so it neither reads nor writes the table
+        let excluded_move = self.excluded_moves[ply as usize];
+        let is_partial_search =
+            excluded_move.is_some() || (ply == 0 && !self.excluded_root_moves.is_empty());
+        let entry = match excluded_move {
+            Some(_) => None,
+            None => self.shared.table.probe(&hash, &ply),
+        };
+        // the pv is kept whole by only cutting off on a zero window
+        let is_pv_node = beta - alpha > 1;
+        if let Some(entry) = entry {
+            let is_usable = match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => entry.score >= beta,
+                Bound::Upper => entry.score <= alpha,
+            };
+            if ply > 0 && !is_pv_node && entry.depth >= depth && is_usable {
+                return entry.score;
+            }
+        }
+        if ply > 0 {
+            if let Some(score) = self.probe_endgame_tables(position, ply) {
+                return score;
+            }
+            if let Some(score) = self.probe_tablebases(position, ply, alpha, beta) {
+                return score;
+            }
+        }
+
+        let in_check = position.is_in_check(&position.turn);
+        let mut moves = generate_legal_moves(position);
+        if moves.is_empty() {
+            return match in_check {
+                true => -MATE_SCORE + ply as i32,
+                false => 0,
+            };
+        }
+        // checked after the mate test, as a mate on the hundredth halfmove
+        // still stands
+        if ply > 0 && position.halfmove_clock >= 100 {
+            return 0;
+        }
+        let table_move = entry.and_then(|entry| {
+            moves
+                .iter()
+                .find(|mv| encode_move(mv) == entry.best_move)
+                .copied()
+        });
+        if ply == 0 {
+            moves.retain(|mv| {
+                self.root_moves.contains(mv) && !self.excluded_root_moves.contains(mv)
+            });
+        }
+
+        if self.can_try_null_move(position, depth, ply, beta, in_check) {
+            if let Some(score) = self.null_move_search(position, depth, ply, beta) {
+                return score;
+            }
+            if self.stopped {
+                return 0;
+            }
+        }
+
+        let mut singular_move = None;
+        if let (Some(entry), Some(table_move)) = (entry, table_move) {
+            if self.is_singular(position, depth, ply, &entry, &table_move) {
+                singular_move = Some(table_move);
+            }
+            if self.stopped {
+                return 0;
+            }
+        }
+        self.order_moves_by_history(position, &mut moves, table_move.or(pv_move));
+
+        let mut best_move = None;
+        let mut quiet_moves_tried = Vec::new();
+        let mut best_move_nodes = 0;
+        let nodes_at_start = self.nodes;
+        let mut child_pv = Vec::new();
+        let moves = moves.into_iter().filter(|mv| Some(*mv) != excluded_move);
+        for (move_index, mv) in moves.enumerate() {
+            let nodes_before_move = self.nodes;
+            let mut next_position = *position;
+            next_position.make_move(&mv);
+            self.update_accumulator(position, &next_position, ply + 1);
+            let gives_check = next_position.is_in_check(&next_position.turn);
+            let is_quiet_move = is_quiet(position, &mv);
+
+            let is_extended =
+                (self.options.check_extensions && gives_check) || singular_move == Some(mv);
+            let next_depth = match is_extended {
+                true => depth,
+                false => depth - 1,
+            };
+
+            // captures, promotions and checks are left alone, as are all moves
+            // when in check, since those are where the tactics are
+            let is_late_quiet_move = depth >= LATE_MOVE_MIN_DEPTH
+                && move_index >= LATE_MOVE_MIN_INDEX
+                && !in_check
+                && is_quiet_move
+                && !gives_check;
+            let reduction = match is_late_quiet_move {
+                true => get_late_move_reduction(depth, move_index),
+                false => 0,
+            };
+
+            // with pvs every move after the first is expected to fail low, which
+            // a zero window proves more cheaply. a reduced move only gets a full
+            // depth search if it looks like it could raise alpha, which also
+            // keeps any mate it finds exact
+            let is_zero_window_first = self.options.principal_variation_search && move_index > 0;
+            let mut score = alpha + 1;
+            if reduction > 0 {
+                score = -self.negamax(
+                    &next_position,
+                    next_depth - reduction,
+                    ply + 1,
+                    -alpha - 1,
+                    -alpha,
+                    &mut child_pv,
+                );
+            }
+            if score > alpha && is_zero_window_first && !self.stopped {
+                score = -self.negamax(
+                    &next_position,
+                    next_depth,
+                    ply + 1,
+                    -alpha - 1,
+                    -alpha,
+                    &mut child_pv,
+                );
+            }
+            let needs_full_window = match is_zero_window_first {
+                true => score > alpha && score < beta,
+                false => score > alpha,
+            };
+            if needs_full_window && !self.stopped {
+                score = -self.negamax(
+                    &next_position,
+                    next_depth,
+                    ply + 1,
+                    -beta,
+                    -alpha,
+                    &mut child_pv,
+                );
+            }
+            if self.stopped {
+                // the root keeps whatever it had fully searched before time ran out
+                return match ply {
+                    0 => alpha,
+                    _ => 0,
+                };
+            }
+
+            if score > alpha {
+                alpha = score;
+                best_move = Some(mv);
+                best_move_nodes = self.nodes - nodes_before_move;
+                pv.clear();
+                pv.push(mv);
+                pv.extend_from_slice(&child_pv);
+                if alpha >= beta {
+                    if is_quiet_move {
+                        self.update_quiet_histories(&position.turn, &mv, &quiet_moves_tried, depth);
+                    }
+                    break;
+                }
+            }
+            if is_quiet_move {
+                quiet_moves_tried.push(mv);
+            }
+        }
+
+        if ply == 0 && !is_partial_search {
+            let root_nodes = (self.nodes - nodes_at_start).max(1);
+            self.best_move_effort = best_move_nodes as f64 / root_nodes as f64;
+        }
+        if !is_partial_search {
+            let bound = match (alpha >= beta, best_move) {
+                (true, _) => Bound::Lower,
+                (false, Some(_)) => Bound::Exact,
+                (false, None) => Bound::Upper,
+            };
+            let entry = TableEntry {
+                best_move: best_move.map_or(0, |mv| encode_move(&mv)),
+                score: alpha,
+                depth,
+                bound,
+            };
+            self.shared.table.store(&hash, &ply, &entry);
+        }
+        alpha
+    }
+
+    // the tables leave out the fifty move rule, so a mate that may run into
+    // it is left to the search
+    fn probe_endgame_tables(&self, position: &Position, ply: u8) -> Option<i32> {
+        let tables = self.options.endgame_tables.as_ref()?;
+        let dtm = tables.probe(position)?;
+        let plies = match dtm {
+            Dtm::Win(plies) | Dtm::Loss(plies) => plies,
+            Dtm::Draw => 0,
+        };
+        match position.halfmove_clock + plies < 100 {
+            true => Some(dtm.get_score(&(ply as u32))),
+            false => None,
+        }
+    }
+
+    // the tables assume the fifty move count starts at zero, so they are
+    // only probed right after a capture or pawn move. a win only shows the
+    // score is at least this, since the search may yet find a mate, so it
+    // only cuts off at beta, and a loss likewise at alpha
+    fn probe_tablebases(&self, position: &Position, ply: u8, alpha: i32, beta: i32) -> Option<i32> {
+        let tablebases = self.options.tablebases.as_ref()?;
+        if position.halfmove_clock != 0 {
+            return None;
+        }
+        let (score, is_cutoff) = match tablebases.probe_wdl(position)? {
+            Wdl::Win => {
+                let score = TABLEBASE_WIN_SCORE - ply as i32;
+                (score, score >= beta)
+            }
+            Wdl::Loss => {
+                let score = -TABLEBASE_WIN_SCORE + ply as i32;
+                (score, score <= alpha)
+            }
+            // drawn, if only by the fifty move rule for the cursed and blessed
+            wdl => (wdl as i32, true),
+        };
+        is_cutoff.then_some(score)
+    }
+
+    // starts from a narrow window around the last iteration's score, which
+    // cuts off far more, and widens it step by step whenever the score falls
+    // outside
+    fn search_root(
+        &mut self,
+        position: &Position,
+        depth: u8,
+        previous_score: &i32,
+        pv: &mut Vec<Move>,
+    ) -> i32 {
+        let use_window = self.options.aspiration_windows
+            && depth >= ASPIRATION_MIN_DEPTH
+            && previous_score.abs() < MATE_THRESHOLD;
+        if !use_window {
+            return self.negamax(position, depth, 0, -INFINITY, INFINITY, pv);
+        }
+
+        let mut window = ASPIRATION_WINDOW;
+        let mut alpha = previous_score - window;
+        let mut beta = previous_score + window;
+        loop {
+            let score = self.negamax(position, depth, 0, alpha, beta, pv);
+            if self.stopped {
+                return score;
+            }
+            window *= 2;
+            if score <= alpha {
+                alpha = (previous_score - window).max(-INFINITY);
+            } else if score >= beta {
+                beta = (previous_score + window).min(INFINITY);
+            } else {
+                return score;
+            }
+        }
+    }
+
+    // one iteration a ply deeper than the last until a limit is hit. helpers
+    // on odd threads search each depth a ply ahead of the main thread, so
+    // that the threads spread over more of the tree, and only look for the
+    // best line whatever the multipv option
+    fn iterative_deepening(
+        &mut self,
+        position: &Position,
+        legal_moves: &[Move],
+        mut time_manager: Option<TimeManager>,
+        report: &mut dyn FnMut(&SearchResult),
+    ) -> SearchResult {
+        self.root_moves = legal_moves.to_vec();
+        let mut result = SearchResult {
+            best_move: legal_moves.first().copied(),
+            score: 0,
+            depth: 0,
+            nodes: 0,
+            elapsed: Duration::ZERO,
+            pv: Vec::new(),
+            ponder_move: None,
+            lines: Vec::new(),
+        };
+        let line_count = match self.thread_index {
+            0 => self.options.multi_pv.clamp(1, legal_moves.len()),
+            _ => 1,
+        };
+        let max_depth = self.limits.depth.unwrap_or(MAX_DEPTH).clamp(1, MAX_DEPTH);
+        let depth_offset = (self.thread_index % 2) as u8;
+        for depth in 1..=max_depth {
+            let depth = (depth + depth_offset).min(max_depth);
+            let lines = self.search_lines(position, depth, &result.lines, &line_count);
+            // a partial iteration is only trusted if it has already found a move
+            let Some(best_line) = lines.first() else {
+                break;
+            };
+            result.best_move = best_line.pv.first().copied();
+            result.pv = best_line.pv.clone();
+            result.score = best_line.score;
+            // lines the partial iteration did not get to keep their last result
+            let previous_lines = std::mem::take(&mut result.lines);
+            result.lines = lines;
+            for line in previous_lines {
+                let is_searched = result
+                    .lines
+                    .iter()
+                    .any(|searched| searched.pv[0] == line.pv[0]);
+                if result.lines.len() < line_count && !is_searched {
+                    result.lines.push(line);
+                }
+            }
+            if !self.stopped {
+                result.depth = depth;
+                result.nodes = self.get_total_nodes();
+                result.elapsed = self.shared.start_time.elapsed();
+                report(&result);
+            }
+            // a mate is only sure to be the quickest once every line as long as
+            // it has been searched, since extensions can find longer ones sooner
+            let is_shortest_mate = |line: &SearchLine| {
+                line.score.abs() >= MATE_THRESHOLD && MATE_SCORE - line.score.abs() <= depth as i32
+            };
+            let are_all_mates = result.lines.iter().all(is_shortest_mate);
+            if self.stopped || are_all_mates || depth == max_depth {
+                break;
+            }
+            // with only one legal move there is nothing to spend the clock on.
+            // while pondering the time manager still follows the search, but
+            // the clock is not yet running
+            if let Some(time_manager) = &mut time_manager {
+                let is_out_of_time = time_manager.should_stop(
+                    &self.shared.start_time.elapsed(),
+                    &result.best_move,
+                    &result.score,
+                    &self.best_move_effort,
+                );
+                let should_stop = is_out_of_time || legal_moves.len() == 1;
+                if self.shared.signals.ponder.load(Ordering::Relaxed) {
+                    self.stop_on_ponderhit |= should_stop;
+                } else if should_stop {
+                    break;
+                }
+            }
+        }
+        result.nodes = self.get_total_nodes();
+        result.elapsed = self.shared.start_time.elapsed();
+        result
+    }
+
+    // the best root move, then the best of the rest, and so on, each searched
+    // around the score of the same line in the previous iteration. stops
+    // early with the lines found so far if the search is stopped
+    fn search_lines(
+        &mut self,
+        position: &Position,
+        depth: u8,
+        previous_lines: &[SearchLine],
+        line_count: &usize,
+    ) -> Vec<SearchLine> {
+        let mut lines: Vec<SearchLine> = Vec::new();
+        self.excluded_root_moves.clear();
+        while lines.len() < *line_count {
+            let previous_line = previous_lines.get(lines.len());
+            let previous_score = previous_line.map_or(0, |line| line.score);
+            let mut pv = previous_line.map_or(Vec::new(), |line| line.pv.clone());
+            let score = self.search_root(position, depth, &previous_score, &mut pv);
+            let Some(mv) = pv.first().copied() else {
+                break;
+            };
+            lines.push(SearchLine { score, pv });
+            self.excluded_root_moves.push(mv);
+            if self.stopped {
+                break;
+            }
+        }
+        self.excluded_root_moves.clear();
+        // a later line can come out ahead of an earlier one when the earlier
+        // one's score was only a bound
+        lines.sort_by_key(|line| -line.score);
+        lines
+    }
+
+    fn quiescence(&mut self, position: &Position, ply: u8, mut alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+        if self.should_stop() {
+            return 0;
+        }
+
+        let stand_pat = self.evaluate(position, ply);
+        if ply >= MAX_DEPTH {
+            return stand_pat;
+        }
+        if stand_pat >= beta {
+            return stand_pat;
+        }
+        if stand_pat > alpha {
+            alpha = stand_pat;
+        }
+
+        let mut moves: Vec<Move> = generate_legal_moves(position)
+            .into_iter()
+            .filter(|mv| is_capture(position, mv) || mv.promotion == Some(Class::Queen))
+            .collect();
+        order_moves(position, &mut moves, None);
+
+        for mv in moves {
+            // a capture that loses material cannot raise a stand pat score
+            if !see_at_least(position, &mv, &0) {
+                continue;
+            }
+            let mut next_position = *position;
+            next_position.make_move(&mv);
+            self.update_accumulator(position, &next_position, ply + 1);
+            let score = -self.quiescence(&next_position, ply + 1, -beta, -alpha);
+            if self.stopped {
+                return 0;
+            }
+            if score >= beta {
+                return score;
+            }
+            if score > alpha {
+                alpha = score;
+            }
+        }
+        alpha
+    }
+}
+
+// never so much that the move skips straight to the quiescence search
+fn get_late_move_reduction(depth: u8, move_index: usize) -> u8 {
+    let reduction = LATE_MOVE_REDUCTION_BASE
+        + (depth as f64).ln() * ((move_index + 1) as f64).ln() / LATE_MOVE_REDUCTION_DIVISOR;
+    (reduction as u8).min(depth - 2)
+}
+
+fn pv_move_at(pv: &[Move], index: usize) -> Option<Move> {
+    pv.get(index).copied()
+}
+
+fn get_colour_index(colour: &Colour) -> usize {
+    match colour {
+        Colour::White => 0,
+        Colour::Black => 1,
+    }
+}
+
+// neither a capture nor a promotion
+fn is_quiet(position: &Position, mv: &Move) -> bool {
+    !is_capture(position, mv) && mv.promotion.is_none()
+}
+
+pub(crate) fn is_capture(position: &Position, mv: &Move) -> bool {
+    mv.kind == MoveKind::EnPassant
+        || (!mv.is_castle() && position.get_occupancy() & mv.destination_square != 0)
+}
+
+// most valuable victim, least valuable attacker, except that captures which
+// lose material once the exchange plays out go after the quiet moves
+fn score_move(position: &Position, mv: &Move) -> i32 {
+    let mut score = 0;
+    if is_capture(position, mv) {
+        let victim = match position.get_piece_at(&mv.destination_square) {
+            Some(piece) => get_piece_value(&piece.class()),
+            None => get_piece_value(&Class::Pawn),
+        };
+        let attacker = match position.get_piece_at(&mv.origin_square) {
+            Some(piece) => get_piece_value(&piece.class()),
+            None => 0,
+        };
+        let exchange_order = match see_at_least(position, mv, &0) {
+            true => 10_000,
+            false => -10_000,
+        };
+        score += exchange_order + victim * 10 - attacker;
+    }
+    if let Some(class) = mv.promotion {
+        score += 5_000 + get_piece_value(&class);
+    }
+    score
+}
+
+pub(crate) fn order_moves(position: &Position, moves: &mut [Move], first_move: Option<Move>) {
+    moves.sort_by_cached_key(|mv| {
+        if Some(*mv) == first_move {
+            return i32::MIN;
+        }
+        -score_move(position, mv)
+    });
+}
+
+// with a table of its own, which is dropped along with what it learned
+pub fn search(
+    position: &Position,
+    history: &[u64],
+    limits: &SearchLimits,
+    options: &SearchOptions,
+) -> SearchResult {
+    search_until_stopped(
+        position,
+        history,
+        limits,
+        options,
+        &TranspositionTable::new(&options.hash_size),
+        &SearchSignals::default(),
+        &mut |_| {},
+    )
+}
+
+// the reply the search expects to its best move, which can still be looked
+// up in the table when the table cut the pv short
+fn get_ponder_move(
+    table: &TranspositionTable,
+    position: &Position,
+    result: &SearchResult,
+) -> Option<Move> {
+    if let Some(mv) = result.pv.get(1) {
+        return Some(*mv);
+    }
+    let mut next_position = *position;
+    next_position.make_move(&result.best_move?);
+    let entry = table.probe(&get_hash(&next_position), &1)?;
+    generate_legal_moves(&next_position)
+        .into_iter()
+        .find(|mv| encode_move(mv) == entry.best_move)
+}
+
+// searches until a limit is hit or the stop signal is raised, reporting the
+// result of every completed iteration as it goes. with more than one thread,
+// the helpers search the same position alongside the main thread, and
+// whichever thread got furthest gives the move. the table is the caller's,
+// so that it carries over from one move of a game to the next
+pub fn search_until_stopped(
+    position: &Position,
+    history: &[u64],
+    limits: &SearchLimits,
+    options: &SearchOptions,
+    table: &TranspositionTable,
+    signals: &SearchSignals,
+    report: &mut dyn FnMut(&SearchResult),
+) -> SearchResult {
+    let mut legal_moves = generate_legal_moves(position);
+    if let Some(tablebases) = &options.tablebases {
+        if let Some(moves) = tablebases.filter_root_moves(position, &legal_moves) {
+            legal_moves = moves;
+        }
+    }
+    if legal_moves.is_empty() {
+        return SearchResult {
+            best_move: None,
+            score: 0,
+            depth: 0,
+            nodes: 0,
+            elapsed: Duration::ZERO,
+            pv: Vec::new(),
+            ponder_move: None,
+            lines: Vec::new(),
+        };
+    }
+
+    let thread_count = options.threads.clamp(1, MAX_THREADS);
+    let shared = SharedSearch {
+        table,
+        history,
+        start_time: Instant::now(),
+        signals,
+        threads_stop: AtomicBool::new(false),
+        node_counts: (0..thread_count).map(|_| AtomicU64::new(0)).collect(),
+    };
+    let time_manager = limits.clock.map(|clock| TimeManager::new(&clock));
+    let hard_limit = time_manager.as_ref().map(TimeManager::get_hard_limit);
+    let time_limit = match (limits.movetime, hard_limit) {
+        (Some(movetime), Some(hard_limit)) => Some(movetime.min(hard_limit)),
+        (movetime, hard_limit) => movetime.or(hard_limit),
+    };
+    // the helpers search until the main thread is done, only keeping to a
+    // fixed depth
+    let helper_limits = SearchLimits {
+        depth: limits.depth,
+        ..SearchLimits::default()
+    };
+
+    thread::scope(|scope| {
+        let helpers: Vec<_> = (1..thread_count)
+            .map(|thread_index| {
+                let (shared, legal_moves, helper_limits) = (&shared, &legal_moves, &helper_limits);
+                // every thread makes its moves on its own copy
+                let position = *position;
+                scope.spawn(move || {
+                    let mut searcher = Searcher::new(
+                        &position,
+                        helper_limits,
+                        options,
+                        shared,
+                        thread_index,
+                        None,
+                    );
+                    searcher.iterative_deepening(&position, legal_moves, None, &mut |_| {})
+                })
+            })
+            .collect();
+
+        let mut searcher = Searcher::new(position, limits, options, &shared, 0, time_limit);
+        let main_result =
+            searcher.iterative_deepening(position, &legal_moves, time_manager, report);
+        shared.threads_stop.store(true, Ordering::Relaxed);
+        let helper_results: Vec<SearchResult> = helpers
+            .into_iter()
+            .filter_map(|helper| helper.join().ok())
+            .collect();
+
+        // the deepest completed iteration, then the best score, with the main
+        // thread winning any tie. only the main thread has every line of a
+        // multipv search
+        let best_helper_result = helper_results
+            .into_iter()
+            .filter(|result| result.best_move.is_some() && options.multi_pv <= 1)
+            .max_by_key(|result| (result.depth, result.score))
+            .filter(|result| (result.depth, result.score) > (main_result.depth, main_result.score));
+        let is_from_helper = best_helper_result.is_some();
+        let mut result = best_helper_result.unwrap_or(main_result);
+        result.nodes = searcher.get_total_nodes();
+        result.elapsed = shared.start_time.elapsed();
+        result.ponder_move = get_ponder_move(shared.table, position, &result);
+        // the main thread has only reported its own lines so far
+        if is_from_helper {
+            report(&result);
+        }
+        result
+    })
+}
+
+pub fn format_score(score: &i32) -> String {
+    if score.abs() > TABLEBASE_WIN_SCORE - MAX_DEPTH as i32 && score.abs() < MATE_THRESHOLD {
+        match *score > 0 {
+            true => "tablebase win".to_string(),
+            false => "tablebase loss".to_string(),
+        }
+    } else if score.abs() >= MATE_THRESHOLD {
+        let plies = MATE_SCORE - score.abs();
+        let moves = (plies + 1) / 2;
+        match *score > 0 {
+            true => format!("mate in {moves}"),
+            false => format!("mated in {moves}"),
+        }
+    } else {
+        format!("{:+.2}", *score as f32 / 100.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Game;
+    use crate::notation::{fen_to_position, uci_to_move};
+
+    fn search_with_options(
+        position: &Position,
+        history: &[u64],
+        depth: u8,
+        options: &SearchOptions,
+    ) -> SearchResult {
+        let limits = SearchLimits {
+            depth: Some(depth),
+            ..SearchLimits::default()
+        };
+        search(position, history, &limits, options)
+    }
+
+    fn search_to_depth(position: &Position, history: &[u64], depth: u8) -> SearchResult {
+        search_with_options(position, history, depth, &SearchOptions::default())
+    }
+
+    // every combination of the switchable parts of the search, with a name
+    // for each
+    fn get_option_matrix() -> Vec<(String, SearchOptions)> {
+        (0..16)
+            .map(|bits| {
+                let options = SearchOptions {
+                    principal_variation_search: bits & 1 != 0,
+                    aspiration_windows: bits & 2 != 0,
+                    check_extensions: bits & 4 != 0,
+                    singular_extensions: bits & 8 != 0,
+                    ..SearchOptions::default()
+                };
+                let name = format!(
+                    "pvs {}, aspiration {}, check extensions {}, singular extensions {}",
+                    options.principal_variation_search,
+                    options.aspiration_windows,
+                    options.check_extensions,
+                    options.singular_extensions
+                );
+                (name, options)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn forced_mates_score_exactly_with_any_options() {
+        // the side to move mates in this many plies
+        let mates = [
+            ("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 1),
+            ("6k1/8/8/8/8/8/R7/1R4K1 w - - 0 1", 3),
+            ("1r4k1/r7/8/8/8/8/8/6K1 b - - 0 1", 3),
+            ("k7/8/8/3K4/8/8/8/7R w - - 0 1", 5),
+        ];
+        // deep enough for the reductions to let every mate through
+        let depth = 10;
+        for (name, options) in get_option_matrix() {
+            for (fen, plies) in mates {
+                let position = fen_to_position(fen).unwrap();
+                let result = search_with_options(&position, &[], depth, &options);
+                assert_eq!(result.score, MATE_SCORE - plies, "{fen} with {name}");
+
+                // any move keeping the same mate will do
+                let mut next_position = position;
+                next_position.make_move(&result.best_move.unwrap());
+                match plies {
+                    1 => {
+                        assert!(generate_legal_moves(&next_position).is_empty());
+                        assert!(next_position.is_in_check(&next_position.turn));
+                    }
+                    _ => assert_eq!(
+                        search_with_options(&next_position, &[], depth, &options).score,
+                        -(MATE_SCORE - plies + 1),
+                        "{fen} with {name}"
+                    ),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn mate_on_the_hundredth_halfmove_stands() {
+        let position = fen_to_position("6k1/5ppp/8/8/8/8/8/R5K1 w - - 99 80").unwrap();
+        let result = search_to_depth(&position, &[], 2);
+        assert_eq!(result.score, MATE_SCORE - 1);
+        assert_eq!(result.best_move, uci_to_move(&position, "a1a8").ok());
+    }
+
+    #[test]
+    fn pawn_endings_are_searched_without_null_moves() {
+        // only 1. Kb6 mates in three, with black made to move into it. were
+        // black allowed to pass, as null-move pruning assumes, the search
+        // would score it as a plain win
+        let position = fen_to_position("k7/2K5/2pP4/3P4/8/8/8/8 w - - 0 1").unwrap();
+        let result = search_to_depth(&position, &[], 8);
+        assert_eq!(result.score, MATE_SCORE - 5);
+        assert_eq!(result.best_move, uci_to_move(&position, "c7b6").ok());
+    }
+
+    #[test]
+    fn repeating_a_position_is_a_draw() {
+        let mut game = Game::new(&fen_to_position("6k1/8/8/8/8/8/8/3Q2K1 w - - 0 1").unwrap());
+        for text in ["d1d2", "g8h8", "d2d1"] {
+            let mv = uci_to_move(game.get_position(), text).unwrap();
+            game.make_move(&mv);
+        }
+        let position = *game.get_position();
+
+        // black is lost unless the king goes back to where it started
+        assert!(search_to_depth(&position, &[], 3).score < -500);
+        let result = search_to_depth(&position, &game.get_history(), 3);
+        assert_eq!(result.score, 0);
+        assert_eq!(result.best_move, uci_to_move(&position, "h8g8").ok());
+    }
+}
@@ -0,0 +1,278 @@
+use crate::evaluation::get_piece_value;
+use crate::move_generation::{
+    generate_bishop_attacks, generate_king_attacks, generate_knight_attacks,
+    generate_pawn_attacks_of_colour, generate_ray_attacks, generate_rook_attacks,
+    get_direction_between, is_diagonal_direction,
+};
+use crate::moves::{Move, MoveKind};
+use crate::pieces::{Class, Colour, Piece};
+use crate::position::Position;
+
+// cheapest first, the order in which each side joins the exchange
+const EXCHANGE_ORDER: [Class; 6] = [
+    Class::Pawn,
+    Class::Knight,
+    Class::Bishop,
+    Class::Rook,
+    Class::Queen,
+    Class::King,
+];
+
+// the longest possible exchange, every piece on the board taking in turn
+const MAX_EXCHANGE_LENGTH: usize = 32;
+
+// every piece of either colour that attacks the square through the given
+// occupancy, looking outwards from the square as each piece type
+fn get_attackers_of_square(position: &Position, square: &u64, occupancy: &u64) -> u64 {
+    let get_pieces = |class: &Class| {
+        position.get_bitboard(&Piece::new(class, &Colour::White))
+            | position.get_bitboard(&Piece::new(class, &Colour::Black))
+    };
+    let diagonal_sliders = get_pieces(&Class::Bishop) | get_pieces(&Class::Queen);
+    let straight_sliders = get_pieces(&Class::Rook) | get_pieces(&Class::Queen);
+
+    let white_pawns = position.get_bitboard(&Piece::WhitePawn);
+    let black_pawns = position.get_bitboard(&Piece::BlackPawn);
+    let attackers = generate_pawn_attacks_of_colour(square, &Colour::Black) & white_pawns
+        | generate_pawn_attacks_of_colour(square, &Colour::White) & black_pawns
+        | generate_knight_attacks(square) & get_pieces(&Class::Knight)
+        | generate_king_attacks(square) & get_pieces(&Class::King)
+        | generate_bishop_attacks(square, occupancy) & diagonal_sliders
+        | generate_rook_attacks(square, occupancy) & straight_sliders;
+    attackers & occupancy
+}
+
+// once a piece has left the exchange, a slider lined up behind it along the
+// same ray can join in. the ray only reaches as far as the first piece, so
+// that piece is the only one that can be revealed
+fn get_x_ray_attacker(
+    position: &Position,
+    target_square: &u64,
+    removed_square: &u64,
+    occupancy: &u64,
+) -> u64 {
+    let Some(direction) = get_direction_between(target_square, removed_square) else {
+        return 0b0;
+    };
+    let sliders = match is_diagonal_direction(&direction) {
+        true => [Class::Bishop, Class::Queen],
+        false => [Class::Rook, Class::Queen],
+    };
+    let mut revealed = 0b0;
+    for colour in [Colour::White, Colour::Black] {
+        for class in &sliders {
+            revealed |= position.get_bitboard(&Piece::new(class, &colour));
+        }
+    }
+    generate_ray_attacks(&direction, removed_square, occupancy) & occupancy & revealed
+}
+
+fn get_least_valuable_attacker(
+    position: &Position,
+    attackers: &u64,
+    colour: &Colour,
+) -> Option<(u64, Class)> {
+    EXCHANGE_ORDER.iter().find_map(|class| {
+        let pieces = attackers & position.get_bitboard(&Piece::new(class, colour));
+        match pieces {
+            0 => None,
+            // any one of them will do
+            _ => Some((pieces & pieces.wrapping_neg(), *class)),
+        }
+    })
+}
+
+// what the move captures, with a promotion counted as winning the difference
+// between the new piece and the pawn
+fn get_capture_gain(position: &Position, mv: &Move) -> i32 {
+    let captured = match mv.kind {
+        MoveKind::EnPassant => get_piece_value(&Class::Pawn),
+        _ => match position.get_piece_at(&mv.destination_square) {
+            Some(piece) => get_piece_value(&piece.class()),
+            None => 0,
+        },
+    };
+    let promotion = match mv.promotion {
+        Some(class) => get_piece_value(&class) - get_piece_value(&Class::Pawn),
+        None => 0,
+    };
+    captured + promotion
+}
+
+// the occupancy and attackers of the destination once the moving piece, and
+// the pawn it takes en passant, have left their squares, which already lets
+// any slider behind them through
+fn get_exchange_start(position: &Position, mv: &Move) -> (u64, u64) {
+    let mut occupancy = position.get_occupancy() & !mv.origin_square;
+    if mv.kind == MoveKind::EnPassant {
+        let captured_square = match position.turn {
+            Colour::White => mv.destination_square >> 8,
+            Colour::Black => mv.destination_square << 8,
+        };
+        occupancy &= !captured_square;
+    }
+    let attackers = get_attackers_of_square(position, &mv.destination_square, &occupancy);
+    (occupancy, attackers)
+}
+
+fn get_moving_class(position: &Position, mv: &Move) -> Class {
+    match (mv.promotion, position.get_piece_at(&mv.origin_square)) {
+        (Some(class), _) => class,
+        (None, Some(piece)) => piece.class(),
+        (None, None) => Class::Pawn,
+    }
+}
+
+// static exchange evaluation: the material the side to move comes out of the
+// move with, once both sides have taken back and forth on the destination
+// for as long as it pays them, cheapest piece first. pins and checks are
+// not considered
+pub fn see(position: &Position, mv: &Move) -> i32 {
+    if mv.is_castle() {
+        return 0;
+    }
+    let target_square = mv.destination_square;
+    let (mut occupancy, mut attackers) = get_exchange_start(position, mv);
+
+    // gains[n] is what the side making the nth capture is up by if it is the
+    // last, worked back from the end to find where either side should stop
+    let mut gains = [0; MAX_EXCHANGE_LENGTH];
+    gains[0] = get_capture_gain(position, mv);
+    let mut piece_on_target = get_moving_class(position, mv);
+    let mut colour = position.turn;
+    let mut depth = 0;
+    loop {
+        depth += 1;
+        colour = !colour;
+        gains[depth] = get_piece_value(&piece_on_target) - gains[depth - 1];
+        if depth + 1 >= MAX_EXCHANGE_LENGTH {
+            break;
+        }
+        let Some((square, class)) = get_least_valuable_attacker(position, &attackers, &colour)
+        else {
+            break;
+        };
+        // a king can only take if nothing can take it back
+        let opponent_attackers = attackers & position.get_colour_occupancy(&!colour);
+        if class == Class::King && opponent_attackers != 0 {
+            break;
+        }
+        occupancy &= !square;
+        attackers &= !square;
+        attackers |= get_x_ray_attacker(position, &target_square, &square, &occupancy);
+        piece_on_target = class;
+    }
+
+    // the last gain only stands if someone could have made that capture
+    while depth > 1 {
+        depth -= 1;
+        gains[depth - 1] = -(-gains[depth - 1]).max(gains[depth]);
+    }
+    gains[0]
+}
+
+// whether the move comes out of the exchange at least threshold ahead, for
+// pruning decisions that only need a yes or no. stops as soon as the answer
+// is known, without working out the whole exchange
+pub fn see_at_least(position: &Position, mv: &Move, threshold: &i32) -> bool {
+    if mv.is_castle() {
+        return 0 >= *threshold;
+    }
+    // what the side to move is up by after the capture, beyond the threshold,
+    // if the other side takes back
+    let mut balance = get_capture_gain(position, mv) - threshold;
+    if balance < 0 {
+        return false;
+    }
+    balance = get_piece_value(&get_moving_class(position, mv)) - balance;
+    if balance <= 0 {
+        return true;
+    }
+
+    let target_square = mv.destination_square;
+    let (mut occupancy, mut attackers) = get_exchange_start(position, mv);
+    let mut colour = position.turn;
+    // whether the side to move is ahead if the exchange stops here
+    let mut is_ahead = true;
+    loop {
+        colour = !colour;
+        let Some((square, class)) = get_least_valuable_attacker(position, &attackers, &colour)
+        else {
+            break;
+        };
+        is_ahead = !is_ahead;
+        if class == Class::King {
+            // taking with the king only works if nothing can take it back
+            let opponent_attackers = attackers & position.get_colour_occupancy(&!colour);
+            return match opponent_attackers != 0 {
+                true => !is_ahead,
+                false => is_ahead,
+            };
+        }
+        balance = get_piece_value(&class) - balance;
+        if balance < is_ahead as i32 {
+            break;
+        }
+        occupancy &= !square;
+        attackers &= !square;
+        attackers |= get_x_ray_attacker(position, &target_square, &square, &occupancy);
+    }
+    is_ahead
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluation::{BISHOP_VALUE, KNIGHT_VALUE, PAWN_VALUE, ROOK_VALUE};
+    use crate::notation::{fen_to_position, uci_to_move};
+
+    // the exchange, and the threshold variant agreeing with it either side
+    fn assert_see(fen: &str, uci: &str, expected: i32) {
+        let position = fen_to_position(fen).unwrap();
+        let mv = uci_to_move(&position, uci).unwrap();
+        assert_eq!(see(&position, &mv), expected, "{uci} in {fen}");
+        assert!(see_at_least(&position, &mv, &expected), "{uci} in {fen}");
+        assert!(
+            !see_at_least(&position, &mv, &(expected + 1)),
+            "{uci} in {fen}"
+        );
+    }
+
+    #[test]
+    fn swap_off() {
+        // an undefended pawn
+        assert_see(
+            "1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1",
+            "e1e5",
+            PAWN_VALUE,
+        );
+        // knight for pawn, after which neither side gains by going on
+        assert_see(
+            "1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1",
+            "d3e5",
+            PAWN_VALUE - KNIGHT_VALUE,
+        );
+        // a quiet move onto a square a pawn guards
+        assert_see("4k3/8/8/8/4p3/8/8/4K1N1 w - - 0 1", "g1f3", -KNIGHT_VALUE);
+        // a king may take what nothing defends
+        assert_see("4k3/8/8/8/8/8/3p4/4K3 w - - 0 1", "e1d2", PAWN_VALUE);
+    }
+
+    #[test]
+    fn x_ray_attackers_join_in() {
+        // the rook behind wins back the first
+        assert_see("3r2k1/8/8/3p4/8/8/3R4/3R2K1 w - - 0 1", "d2d5", PAWN_VALUE);
+        // the queen behind the bishop takes back on the diagonal
+        assert_see(
+            "7k/8/4p3/3p4/8/5B2/6Q1/6K1 w - - 0 1",
+            "f3d5",
+            2 * PAWN_VALUE - BISHOP_VALUE,
+        );
+        // a defending rook behind another defends too
+        assert_see(
+            "3r2k1/3r4/8/3p4/8/8/8/3R2K1 w - - 0 1",
+            "d1d5",
+            PAWN_VALUE - ROOK_VALUE,
+        );
+    }
+}
@@ -0,0 +1,2137 @@
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::ops::Neg;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+use crate::move_generation::generate_legal_moves;
+use crate::moves::Move;
+use crate::pieces::{Class, Colour, Piece};
+use crate::position::Position;
+use crate::search::is_capture;
+use crate::utils::{bitboard_to_index, pop_lsb};
+
+// syzygy tables come in pairs named after their material, strongest side
+// first, like KRvK.rtbw and KRvK.rtbz. the wdl table holds whether each
+// position is won, drawn or lost under the fifty move rule, and the dtz table
+// how many plies it takes to the next capture or pawn move on the way there.
+// positions are turned into an index by placing the pieces in groups, after
+// mirroring the board so that the leading piece sits in as small a corner as
+// possible, and the values are compressed in blocks with recursive pairing
+// and canonical huffman codes
+//
+// the files are little endian apart from the compressed blocks, and begin:
+//   4 bytes   magic
+//   u8        1 if both sides to move are stored, 2 if there are pawns
+//   then for each file a to d the leading pawn can be on, or once without
+//   pawns, the order the groups are encoded in and the pieces of each group,
+//   followed by each side's huffman codes, any dtz value maps, the sparse
+//   indices, the block lengths and the blocks themselves
+const WDL_MAGIC: [u8; 4] = [0x71, 0xE8, 0x23, 0x5D];
+const DTZ_MAGIC: [u8; 4] = [0xD7, 0x66, 0x0C, 0xA5];
+
+// the most pieces, kings included, any syzygy table holds
+const MAX_PIECES: usize = 7;
+// the most of one piece a group can hold, like the leading pawns of KPPPPPvK
+const MAX_GROUP_LENGTH: usize = 5;
+
+// flags stored with each table's huffman codes
+const SIDE_TO_MOVE_FLAG: u8 = 1;
+const MAPPED_FLAG: u8 = 2;
+const WIN_PLIES_FLAG: u8 = 4;
+const LOSS_PLIES_FLAG: u8 = 8;
+const WIDE_FLAG: u8 = 16;
+const SINGLE_VALUE_FLAG: u8 = 128;
+
+// a symbol with this on its right stands for a single value, not a pair
+const LEAF_SYMBOL: usize = 0xFFF;
+
+// the number of positions the leading group of a pawnless table can be in:
+// three unique pieces, or the two kings
+const UNIQUE_PIECES_SIZE: u64 = 31332;
+const KINGS_SIZE: u64 = 462;
+
+// how much of a file is read at first to find the end of its header, which
+// is doubled until the header fits
+const HEADER_READ_SIZE: usize = 4096;
+
+// keeps every ranked root move inside the range, well clear of any distance
+const MAX_DTZ: i32 = 1 << 18;
+
+// the result for the side to move. a cursed win or blessed loss would be a
+// win or loss if not for the fifty move rule
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Wdl {
+    Loss = -2,
+    BlessedLoss = -1,
+    Draw = 0,
+    CursedWin = 1,
+    Win = 2,
+}
+
+impl Wdl {
+    fn from_value(value: &i32) -> Option<Wdl> {
+        match value {
+            -2 => Some(Wdl::Loss),
+            -1 => Some(Wdl::BlessedLoss),
+            0 => Some(Wdl::Draw),
+            1 => Some(Wdl::CursedWin),
+            2 => Some(Wdl::Win),
+            _ => None,
+        }
+    }
+
+    fn signum(&self) -> i32 {
+        (*self as i32).signum()
+    }
+}
+
+impl Neg for Wdl {
+    type Output = Wdl;
+
+    fn neg(self) -> Wdl {
+        match self {
+            Wdl::Loss => Wdl::Win,
+            Wdl::BlessedLoss => Wdl::CursedWin,
+            Wdl::Draw => Wdl::Draw,
+            Wdl::CursedWin => Wdl::BlessedLoss,
+            Wdl::Win => Wdl::Loss,
+        }
+    }
+}
+
+// how many of each class, pawn to king, white's then black's
+type Material = [[u8; 6]; 2];
+
+// the lookup tables every index is built from
+struct Indices {
+    // a2 to h7 as 0 to 47, the squares nearest the edge and lowest highest
+    pawns: [usize; 64],
+    // the squares below the a1-h8 diagonal as 0 to 27
+    below_diagonal: [usize; 64],
+    // the a1-d1-d4 triangle as 0 to 9, the diagonal last
+    triangle: [usize; 64],
+    // both kings, the first in the triangle, as 0 to 461
+    kings: [[usize; 64]; 10],
+    // [k][n] ways to choose k things from n
+    binomial: [[u64; 64]; MAX_GROUP_LENGTH + 1],
+    // [leading pawns][square of the leading pawn]
+    lead_pawns: [[u64; 64]; MAX_GROUP_LENGTH + 1],
+    // [leading pawns][file a to d]
+    lead_pawns_size: [[u64; 4]; MAX_GROUP_LENGTH + 1],
+}
+
+// the index and compression data of one side to move, and one leading pawn
+// file, of a table. the regions are offsets into the file's bytes
+#[derive(Clone, Default)]
+struct PairsData {
+    flags: u8,
+    // also the value of every position when the table has only one
+    min_symbol_length: u8,
+    block_size: usize,
+    // a sparse index entry is kept for every span values
+    span: u64,
+    block_count: usize,
+    block_lengths_size: usize,
+    sparse_index_size: usize,
+    lowest_symbols: usize,
+    symbol_tree: usize,
+    sparse_index: usize,
+    block_lengths: usize,
+    blocks: usize,
+    // the lowest code of each length, padded out to 64 bits
+    base: Vec<u64>,
+    // how many values, less one, each symbol stands for
+    symbol_lengths: Vec<usize>,
+    // in the order they are encoded, as 1 to 6 for white's pawn to king and
+    // 9 to 14 for black's
+    pieces: [u8; MAX_PIECES],
+    group_lengths: [usize; MAX_PIECES + 1],
+    group_factors: [u64; MAX_PIECES + 1],
+    // where each result's dtz values start in the value maps
+    map_starts: [usize; 4],
+}
+
+// a file is only kept open, and of its contents only the header, which is
+// everything before the sparse indices. the rest is read as it is probed
+struct TableFile {
+    file: Mutex<File>,
+    header: Vec<u8>,
+    // [side to move][leading pawn file]
+    pairs: Vec<Vec<PairsData>>,
+    // the start of the dtz value maps
+    map: usize,
+}
+
+// where everything in a file is, as read from its header
+struct Layout {
+    pairs: Vec<Vec<PairsData>>,
+    map: usize,
+    header_size: usize,
+    file_size: usize,
+}
+
+struct Table {
+    wdl_path: PathBuf,
+    dtz_path: Option<PathBuf>,
+    // with the side named first as white
+    material: Material,
+    piece_count: usize,
+    has_pawns: bool,
+    // some piece other than a king is the only one of its kind
+    has_unique_pieces: bool,
+    // the leading colour's pawns, which is the side with fewer but some
+    // pawns, and then the other side's
+    pawn_counts: [usize; 2],
+    // opened and their headers read the first time they are probed, None if
+    // that failed
+    wdl: OnceLock<Option<TableFile>>,
+    dtz: OnceLock<Option<TableFile>>,
+}
+
+// the squares and pieces of a position, mirrored to how the table sees it
+struct Encoding {
+    side: usize,
+    file: usize,
+    squares: [usize; MAX_PIECES],
+    pieces: [u8; MAX_PIECES],
+    count: usize,
+    lead_pawn_count: usize,
+}
+
+enum DtzProbe {
+    Found(i32),
+    // the table only holds the other side to move
+    OtherSide,
+}
+
+pub struct Tablebases {
+    tables: Vec<Table>,
+    // both ways round for each table
+    table_indices: HashMap<Material, usize>,
+    max_pieces: usize,
+    indices: Indices,
+}
+
+impl fmt::Debug for Tablebases {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Tablebases {{ tables: {}, max_pieces: {} }}",
+            self.tables.len(),
+            self.max_pieces
+        )
+    }
+}
+
+fn read_u8(bytes: &[u8], offset: usize) -> Option<u8> {
+    bytes.get(offset).copied()
+}
+
+fn read_u16_le(bytes: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        bytes.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32_le(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        bytes.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32_be(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        bytes.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn read_u64_be(bytes: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(
+        bytes.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
+
+// the two symbols a symbol expands to, twelve bits each
+fn read_symbol_pair(bytes: &[u8], symbol_tree: &usize, symbol: &usize) -> Option<(usize, usize)> {
+    let offset = symbol_tree + 3 * symbol;
+    let pair = bytes.get(offset..offset + 3)?;
+    let left = ((pair[1] as usize & 0xF) << 8) | pair[0] as usize;
+    let right = ((pair[2] as usize) << 4) | (pair[1] as usize >> 4);
+    Some((left, right))
+}
+
+// how far above the a1-h8 diagonal the square is, negative below it
+fn get_diagonal_offset(square: &usize) -> i32 {
+    (square / 8) as i32 - (square % 8) as i32
+}
+
+fn get_class_index(class: &Class) -> usize {
+    match class {
+        Class::Pawn => 0,
+        Class::Knight => 1,
+        Class::Bishop => 2,
+        Class::Rook => 3,
+        Class::Queen => 4,
+        Class::King => 5,
+    }
+}
+
+fn get_colour_index(colour: &Colour) -> usize {
+    match colour {
+        Colour::White => 0,
+        Colour::Black => 1,
+    }
+}
+
+fn get_table_piece(piece: &Piece) -> u8 {
+    get_class_index(&piece.class()) as u8 + 1 + 8 * get_colour_index(&piece.colour()) as u8
+}
+
+fn get_material(position: &Position) -> Material {
+    let mut material = [[0; 6]; 2];
+    for piece in Piece::iter() {
+        material[get_colour_index(&piece.colour())][get_class_index(&piece.class())] =
+            position.get_bitboard(piece).count_ones() as u8;
+    }
+    material
+}
+
+// e.g. "KRPvKR", or None if the name is not of a table
+fn parse_material(name: &str) -> Option<Material> {
+    let (white, black) = name.split_once('v')?;
+    let mut material = [[0; 6]; 2];
+    for (colour_index, pieces) in [white, black].iter().enumerate() {
+        for c in pieces.chars() {
+            let class_index = "PNBRQK".find(c)?;
+            material[colour_index][class_index] += 1;
+        }
+    }
+    let piece_count: u8 = material.iter().flatten().sum();
+    let has_one_king = material.iter().all(|side| side[5] == 1);
+    match has_one_king && piece_count as usize <= MAX_PIECES {
+        true => Some(material),
+        false => None,
+    }
+}
+
+fn is_pawn_move(position: &Position, mv: &Move) -> bool {
+    position
+        .get_piece_at(&mv.origin_square)
+        .is_some_and(|piece| piece.class() == Class::Pawn)
+}
+
+fn is_checkmate(position: &Position) -> bool {
+    position.is_in_check(&position.turn) && generate_legal_moves(position).is_empty()
+}
+
+// the dtz of a position with the given result whose best move is a capture
+// or pawn move, which is the move before the count starts again
+fn get_dtz_before_zeroing(wdl: &Wdl) -> i32 {
+    match wdl {
+        Wdl::Win => 1,
+        Wdl::CursedWin => 101,
+        Wdl::Draw => 0,
+        Wdl::BlessedLoss => -101,
+        Wdl::Loss => -1,
+    }
+}
+
+impl Indices {
+    fn new() -> Indices {
+        let mut below_diagonal = [0; 64];
+        let mut code = 0;
+        for (square, value) in below_diagonal.iter_mut().enumerate() {
+            if get_diagonal_offset(&square) < 0 {
+                *value = code;
+                code += 1;
+            }
+        }
+
+        let mut triangle = [0; 64];
+        let mut diagonal = Vec::new();
+        code = 0;
+        for (square, value) in triangle.iter_mut().enumerate().take(28) {
+            if square % 8 > 3 {
+                continue;
+            }
+            match get_diagonal_offset(&square) {
+                0 => diagonal.push(square),
+                offset if offset < 0 => {
+                    *value = code;
+                    code += 1;
+                }
+                _ => (),
+            }
+        }
+        for square in diagonal {
+            triangle[square] = code;
+            code += 1;
+        }
+
+        // the second king can't be beside the first, nor above the diagonal
+        // when the first is on it. pairs both on the diagonal go last
+        let mut kings = [[0; 64]; 10];
+        let mut both_on_diagonal = Vec::new();
+        code = 0;
+        for (triangle_index, king_squares) in kings.iter_mut().enumerate() {
+            for first in 0..28 {
+                let is_in_triangle = square_in_triangle(&triangle, &first, &triangle_index);
+                if !is_in_triangle {
+                    continue;
+                }
+                for (second, value) in king_squares.iter_mut().enumerate() {
+                    let is_adjacent = (first / 8).abs_diff(second / 8) <= 1
+                        && (first % 8).abs_diff(second % 8) <= 1;
+                    let first_offset = get_diagonal_offset(&first);
+                    let second_offset = get_diagonal_offset(&second);
+                    if is_adjacent || (first_offset == 0 && second_offset > 0) {
+                        continue;
+                    }
+                    if first_offset == 0 && second_offset == 0 {
+                        both_on_diagonal.push((triangle_index, second));
+                    } else {
+                        *value = code;
+                        code += 1;
+                    }
+                }
+            }
+        }
+        for (triangle_index, second) in both_on_diagonal {
+            kings[triangle_index][second] = code;
+            code += 1;
+        }
+
+        let mut binomial = [[0; 64]; MAX_GROUP_LENGTH + 1];
+        binomial[0][0] = 1;
+        for n in 1..64 {
+            for k in 0..=n.min(MAX_GROUP_LENGTH) {
+                let with = match k {
+                    0 => 0,
+                    _ => binomial[k - 1][n - 1],
+                };
+                let without = match k < n {
+                    true => binomial[k][n - 1],
+                    false => 0,
+                };
+                binomial[k][n] = with + without;
+            }
+        }
+
+        // the leading pawn is the one with the highest value here, so any
+        // other is no nearer the edge, nor lower on the same file
+        let mut pawns = [0; 64];
+        let mut lead_pawns = [[0; 64]; MAX_GROUP_LENGTH + 1];
+        let mut lead_pawns_size = [[0; 4]; MAX_GROUP_LENGTH + 1];
+        let mut available_squares = 48;
+        for lead_pawn_count in 1..=MAX_GROUP_LENGTH {
+            for (file, size) in lead_pawns_size[lead_pawn_count].iter_mut().enumerate() {
+                let mut index = 0;
+                for rank in 1..7 {
+                    let square = 8 * rank + file;
+                    if lead_pawn_count == 1 {
+                        available_squares -= 1;
+                        pawns[square] = available_squares;
+                        available_squares -= 1;
+                        pawns[square ^ 7] = available_squares;
+                    }
+                    lead_pawns[lead_pawn_count][square] = index;
+                    index += binomial[lead_pawn_count - 1][pawns[square]];
+                }
+                *size = index;
+            }
+        }
+
+        Indices {
+            pawns,
+            below_diagonal,
+            triangle,
+            kings,
+            binomial,
+            lead_pawns,
+            lead_pawns_size,
+        }
+    }
+}
+
+// b1 is both the first square of the triangle and the value every square
+// outside it has
+fn square_in_triangle(triangle: &[usize; 64], square: &usize, index: &usize) -> bool {
+    let is_in_triangle = square % 8 <= 3 && get_diagonal_offset(square) <= 0;
+    is_in_triangle && triangle[*square] == *index
+}
+
+impl Table {
+    fn new(material: &Material, wdl_path: PathBuf, dtz_path: Option<PathBuf>) -> Table {
+        let counts: Vec<u8> = material.iter().flatten().copied().collect();
+        let [white_pawns, black_pawns] =
+            [material[0][0], material[1][0]].map(|count| count as usize);
+        // the side with fewer pawns leads, since that compresses better
+        let is_white_leading = black_pawns == 0 || (white_pawns > 0 && black_pawns >= white_pawns);
+        let pawn_counts = match is_white_leading {
+            true => [white_pawns, black_pawns],
+            false => [black_pawns, white_pawns],
+        };
+        Table {
+            wdl_path,
+            dtz_path,
+            material: *material,
+            piece_count: counts.iter().map(|count| *count as usize).sum(),
+            has_pawns: white_pawns + black_pawns > 0,
+            has_unique_pieces: material.iter().any(|side| side[..5].contains(&1)),
+            pawn_counts,
+            wdl: OnceLock::new(),
+            dtz: OnceLock::new(),
+        }
+    }
+
+    fn is_symmetric(&self) -> bool {
+        self.material[0] == self.material[1]
+    }
+
+    fn both_sides_have_pawns(&self) -> bool {
+        self.has_pawns && self.pawn_counts[1] > 0
+    }
+}
+
+impl TableFile {
+    fn read_bytes(&self, offset: &usize, length: &usize) -> Option<Vec<u8>> {
+        let mut file = self.file.lock().ok()?;
+        let mut bytes = vec![0; *length];
+        file.seek(SeekFrom::Start(*offset as u64)).ok()?;
+        file.read_exact(&mut bytes).ok()?;
+        Some(bytes)
+    }
+
+    fn get_pairs(&self, side: &usize, file: &usize) -> &PairsData {
+        let sides = &self.pairs[side % self.pairs.len()];
+        &sides[file % sides.len()]
+    }
+}
+
+// how the pieces fall into groups, and what each group's index is
+// multiplied by. the leading group is the pawns of the leading colour, or
+// without pawns the kings and any third unique piece, and every other
+// group is the pieces of one kind
+fn set_groups(
+    table: &Table,
+    pairs: &mut PairsData,
+    order: &[u8; 2],
+    file: &usize,
+    indices: &Indices,
+) {
+    let mut first_length: i32 = match (table.has_pawns, table.has_unique_pieces) {
+        (true, _) => 0,
+        (false, true) => 3,
+        (false, false) => 2,
+    };
+    let mut group_count = 0;
+    pairs.group_lengths[0] = 1;
+    for i in 1..table.piece_count {
+        first_length -= 1;
+        if first_length > 0 || pairs.pieces[i] == pairs.pieces[i - 1] {
+            pairs.group_lengths[group_count] += 1;
+        } else {
+            group_count += 1;
+            pairs.group_lengths[group_count] = 1;
+        }
+    }
+    group_count += 1;
+    pairs.group_lengths[group_count] = 0;
+
+    // the groups are encoded in the order the table gives, with the leading
+    // group at order[0] and the other side's pawns at order[1]
+    let both_sides_have_pawns = table.both_sides_have_pawns();
+    let mut next = match both_sides_have_pawns {
+        true => 2,
+        false => 1,
+    };
+    let mut free_squares = 64 - pairs.group_lengths[0];
+    if both_sides_have_pawns {
+        free_squares -= pairs.group_lengths[1];
+    }
+    let mut factor = 1;
+    let mut k = 0;
+    while next < group_count || k == order[0] as usize || k == order[1] as usize {
+        if k == order[0] as usize {
+            pairs.group_factors[0] = factor;
+            factor *= match (table.has_pawns, table.has_unique_pieces) {
+                (true, _) => indices.lead_pawns_size[pairs.group_lengths[0]][*file],
+                (false, true) => UNIQUE_PIECES_SIZE,
+                (false, false) => KINGS_SIZE,
+            };
+        } else if k == order[1] as usize {
+            pairs.group_factors[1] = factor;
+            factor *= indices.binomial[pairs.group_lengths[1]][48 - pairs.group_lengths[0]];
+        } else {
+            pairs.group_factors[next] = factor;
+            factor *= indices.binomial[pairs.group_lengths[next]][free_squares];
+            free_squares -= pairs.group_lengths[next];
+            next += 1;
+        }
+        k += 1;
+    }
+    pairs.group_factors[group_count] = factor;
+}
+
+// every symbol's number of values follows from its two halves
+fn set_symbol_length(
+    bytes: &[u8],
+    pairs: &mut PairsData,
+    symbol: &usize,
+    visited: &mut [bool],
+) -> Option<()> {
+    visited[*symbol] = true;
+    let (left, right) = read_symbol_pair(bytes, &pairs.symbol_tree, symbol)?;
+    if right == LEAF_SYMBOL {
+        pairs.symbol_lengths[*symbol] = 0;
+        return Some(());
+    }
+    for half in [left, right] {
+        if !*visited.get(half)? {
+            set_symbol_length(bytes, pairs, &half, visited)?;
+        }
+    }
+    pairs.symbol_lengths[*symbol] =
+        pairs.symbol_lengths[left].saturating_add(pairs.symbol_lengths[right]) + 1;
+    Some(())
+}
+
+// reads the huffman codes and block layout of one side and file, returning
+// where the next begins
+fn read_sizes(bytes: &[u8], mut offset: usize, pairs: &mut PairsData) -> Option<usize> {
+    pairs.flags = read_u8(bytes, offset)?;
+    if pairs.flags & SINGLE_VALUE_FLAG != 0 {
+        pairs.min_symbol_length = read_u8(bytes, offset + 1)?;
+        return Some(offset + 2);
+    }
+
+    let group_count = pairs.group_lengths.iter().position(|length| *length == 0)?;
+    let table_size = pairs.group_factors[group_count];
+    pairs.block_size = 1usize.checked_shl(read_u8(bytes, offset + 1)? as u32)?;
+    pairs.span = 1u64.checked_shl(read_u8(bytes, offset + 2)? as u32)?;
+    pairs.sparse_index_size = table_size.div_ceil(pairs.span) as usize;
+    let padding = read_u8(bytes, offset + 3)? as usize;
+    pairs.block_count = read_u32_le(bytes, offset + 4)? as usize;
+    // padded so that the sparse index never points past the end
+    pairs.block_lengths_size = pairs.block_count + padding;
+    let max_symbol_length = read_u8(bytes, offset + 8)?;
+    pairs.min_symbol_length = read_u8(bytes, offset + 9)?;
+    if max_symbol_length < pairs.min_symbol_length || max_symbol_length > 64 {
+        return None;
+    }
+    offset += 10;
+    pairs.lowest_symbols = offset;
+
+    // canonical huffman codes of one length are consecutive numbers, so the
+    // lowest code of each length is enough to tell a code's length and value
+    let length_count = (max_symbol_length - pairs.min_symbol_length) as usize + 1;
+    pairs.base = vec![0; length_count];
+    for i in (0..length_count - 1).rev() {
+        let lowest = read_u16_le(bytes, pairs.lowest_symbols + 2 * i)? as u64;
+        let next_lowest = read_u16_le(bytes, pairs.lowest_symbols + 2 * (i + 1))? as u64;
+        pairs.base[i] = pairs.base[i + 1]
+            .wrapping_add(lowest)
+            .wrapping_sub(next_lowest)
+            / 2;
+    }
+    for (i, base) in pairs.base.iter_mut().enumerate() {
+        let padding = 64 - i as u32 - pairs.min_symbol_length as u32;
+        *base = base.checked_shl(padding).unwrap_or(0);
+    }
+    offset += 2 * length_count;
+
+    let symbol_count = read_u16_le(bytes, offset)? as usize;
+    offset += 2;
+    pairs.symbol_tree = offset;
+    pairs.symbol_lengths = vec![0; symbol_count];
+    let mut visited = vec![false; symbol_count];
+    for symbol in 0..symbol_count {
+        if !visited[symbol] {
+            set_symbol_length(bytes, pairs, &symbol, &mut visited)?;
+        }
+    }
+    Some(offset + 3 * symbol_count + (symbol_count & 1))
+}
+
+// dtz values are stored by how common they are, so each result has a map
+// back to the real values
+fn read_dtz_maps(bytes: &[u8], mut offset: usize, layout: &mut Layout) -> Option<usize> {
+    layout.map = offset;
+    for pairs in layout.pairs[0].iter_mut() {
+        if pairs.flags & MAPPED_FLAG == 0 {
+            continue;
+        }
+        if pairs.flags & WIDE_FLAG != 0 {
+            offset += offset & 1;
+            for start in pairs.map_starts.iter_mut() {
+                *start = (offset - layout.map) / 2 + 1;
+                offset += 2 * read_u16_le(bytes, offset)? as usize + 2;
+            }
+        } else {
+            for start in pairs.map_starts.iter_mut() {
+                *start = offset - layout.map + 1;
+                offset += read_u8(bytes, offset)? as usize + 1;
+            }
+        }
+    }
+    Some(offset + (offset & 1))
+}
+
+// the layout of a file from its header, or None if the bytes don't hold all
+// of the header or it is damaged
+fn read_layout(table: &Table, bytes: &[u8], is_dtz: bool, indices: &Indices) -> Option<Layout> {
+    let flags = read_u8(bytes, 4)?;
+    let sides = match is_dtz || table.is_symmetric() {
+        true => 1,
+        false => 2,
+    };
+    if (flags & 2 != 0) != table.has_pawns || (flags & 1 != 0) == table.is_symmetric() {
+        return None;
+    }
+    let file_count = match table.has_pawns {
+        true => 4,
+        false => 1,
+    };
+    let mut layout = Layout {
+        pairs: vec![vec![PairsData::default(); file_count]; sides],
+        map: 0,
+        header_size: 0,
+        file_size: 0,
+    };
+
+    let mut offset = 5;
+    let both_sides_have_pawns = table.both_sides_have_pawns();
+    for pawn_file in 0..file_count {
+        let order = read_u8(bytes, offset)?;
+        let pawn_order = match both_sides_have_pawns {
+            true => read_u8(bytes, offset + 1)?,
+            false => 0xFF,
+        };
+        let orders = [
+            [order & 0xF, pawn_order & 0xF],
+            [order >> 4, pawn_order >> 4],
+        ];
+        offset += 1 + both_sides_have_pawns as usize;
+        for k in 0..table.piece_count {
+            let pieces = read_u8(bytes, offset)?;
+            for (side, side_pairs) in layout.pairs.iter_mut().enumerate() {
+                side_pairs[pawn_file].pieces[k] = match side {
+                    0 => pieces & 0xF,
+                    _ => pieces >> 4,
+                };
+            }
+            offset += 1;
+        }
+        for (side, side_pairs) in layout.pairs.iter_mut().enumerate() {
+            let pairs = &mut side_pairs[pawn_file];
+            set_groups(table, pairs, &orders[side], &pawn_file, indices);
+            if pairs
+                .group_lengths
+                .iter()
+                .any(|length| *length > MAX_GROUP_LENGTH)
+            {
+                return None;
+            }
+        }
+    }
+    offset += offset & 1;
+
+    for pawn_file in 0..file_count {
+        for side in 0..sides {
+            offset = read_sizes(bytes, offset, &mut layout.pairs[side][pawn_file])?;
+        }
+    }
+    if is_dtz {
+        offset = read_dtz_maps(bytes, offset, &mut layout)?;
+    }
+    layout.header_size = offset;
+    for pawn_file in 0..file_count {
+        for side in 0..sides {
+            let pairs = &mut layout.pairs[side][pawn_file];
+            pairs.sparse_index = offset;
+            offset += 6 * pairs.sparse_index_size;
+        }
+    }
+    for pawn_file in 0..file_count {
+        for side in 0..sides {
+            let pairs = &mut layout.pairs[side][pawn_file];
+            pairs.block_lengths = offset;
+            offset += 2 * pairs.block_lengths_size;
+        }
+    }
+    for pawn_file in 0..file_count {
+        for side in 0..sides {
+            let pairs = &mut layout.pairs[side][pawn_file];
+            // blocks start on a cache line
+            offset = (offset + 0x3F) & !0x3F;
+            pairs.blocks = offset;
+            offset += pairs.block_count * pairs.block_size;
+        }
+    }
+    layout.file_size = offset;
+    Some(layout)
+}
+
+// reads more of the file each time the header turns out not to fit in what
+// has been read, until it is all read
+fn read_table_file(
+    table: &Table,
+    mut file: File,
+    is_dtz: bool,
+    indices: &Indices,
+) -> Option<TableFile> {
+    let magic = match is_dtz {
+        true => DTZ_MAGIC,
+        false => WDL_MAGIC,
+    };
+    let file_size = file.metadata().ok()?.len() as usize;
+    let mut header = Vec::new();
+    let mut read_size = HEADER_READ_SIZE.min(file_size);
+    let layout = loop {
+        header.resize(read_size, 0);
+        file.seek(SeekFrom::Start(0)).ok()?;
+        file.read_exact(&mut header).ok()?;
+        if header.get(..4)? != magic {
+            return None;
+        }
+        match read_layout(table, &header, is_dtz, indices) {
+            Some(layout) if layout.header_size <= read_size => break layout,
+            _ if read_size < file_size => read_size = (2 * read_size).min(file_size),
+            _ => return None,
+        }
+    };
+    if layout.file_size > file_size {
+        return None;
+    }
+    header.truncate(layout.header_size);
+    Some(TableFile {
+        file: Mutex::new(file),
+        header,
+        pairs: layout.pairs,
+        map: layout.map,
+    })
+}
+
+// the value stored at the index. the blocks each hold a run of values, and
+// the sparse index gives a block near the one holding any index
+fn decompress_pairs(file: &TableFile, pairs: &PairsData, index: &u64) -> Option<usize> {
+    if pairs.flags & SINGLE_VALUE_FLAG != 0 {
+        return Some(pairs.min_symbol_length as usize);
+    }
+
+    let entry = file.read_bytes(
+        &(pairs.sparse_index + 6 * (index / pairs.span) as usize),
+        &6,
+    )?;
+    let mut block = read_u32_le(&entry, 0)? as usize;
+    let mut offset = read_u16_le(&entry, 4)? as i64;
+    // the entry is for the middle of its span
+    offset += (index % pairs.span) as i64 - (pairs.span / 2) as i64;
+    let get_block_length = |block: usize| {
+        let length = file.read_bytes(&(pairs.block_lengths + 2 * block), &2)?;
+        read_u16_le(&length, 0).map(|length| length as i64)
+    };
+    while offset < 0 {
+        block = block.checked_sub(1)?;
+        offset += get_block_length(block)? + 1;
+    }
+    while offset > get_block_length(block)? {
+        offset -= get_block_length(block)? + 1;
+        block += 1;
+    }
+
+    // the codes are read from the front of a 64 bit buffer, which is topped
+    // up 32 bits at a time
+    let min_length = pairs.min_symbol_length as usize;
+    let bytes = &file.header;
+    let block_bytes = file.read_bytes(
+        &(pairs.blocks + block * pairs.block_size),
+        &pairs.block_size,
+    )?;
+    let mut buffer = read_u64_be(&block_bytes, 0)?;
+    let mut position = 8;
+    let mut buffer_bits: usize = 64;
+    let mut symbol;
+    loop {
+        let mut length = 0;
+        while buffer < *pairs.base.get(length)? {
+            length += 1;
+        }
+        let code = (buffer - pairs.base[length])
+            .checked_shr((64 - length - min_length) as u32)
+            .unwrap_or(0);
+        let lowest = read_u16_le(bytes, pairs.lowest_symbols + 2 * length)?;
+        symbol = (code as u16).wrapping_add(lowest) as usize;
+        let symbol_length = *pairs.symbol_lengths.get(symbol)? as i64;
+        if offset <= symbol_length {
+            break;
+        }
+        offset -= symbol_length + 1;
+        let code_length = length + min_length;
+        buffer = buffer.checked_shl(code_length as u32).unwrap_or(0);
+        buffer_bits = buffer_bits.checked_sub(code_length)?;
+        if buffer_bits <= 32 {
+            buffer_bits += 32;
+            // the last block can end before the buffer is full
+            let next = read_u32_be(&block_bytes, position).unwrap_or(0) as u64;
+            buffer |= next << (64 - buffer_bits);
+            position += 4;
+        }
+    }
+
+    // the symbol stands for a run of values made up of its two halves, so
+    // the value is found by going down whichever half holds the offset
+    while pairs.symbol_lengths[symbol] != 0 {
+        let (left, right) = read_symbol_pair(bytes, &pairs.symbol_tree, &symbol)?;
+        let left_length = *pairs.symbol_lengths.get(left)?;
+        let right_length = *pairs.symbol_lengths.get(right)?;
+        // each half is shorter than the whole, unless the file is damaged
+        let length = pairs.symbol_lengths[symbol];
+        if left_length >= length || right_length >= length {
+            return None;
+        }
+        if offset <= left_length as i64 {
+            symbol = left;
+        } else {
+            offset -= left_length as i64 + 1;
+            symbol = right;
+        }
+    }
+    Some(read_symbol_pair(bytes, &pairs.symbol_tree, &symbol)?.0)
+}
+
+// the dtz stored for the value, in plies
+fn get_dtz_from_value(
+    file: &TableFile,
+    pairs: &PairsData,
+    value: &usize,
+    wdl: &Wdl,
+) -> Option<i32> {
+    let mut value = *value;
+    if pairs.flags & MAPPED_FLAG != 0 {
+        let map = match wdl {
+            Wdl::Loss => 1,
+            Wdl::BlessedLoss => 3,
+            Wdl::CursedWin => 2,
+            Wdl::Win | Wdl::Draw => 0,
+        };
+        let index = pairs.map_starts[map] + value;
+        value = match pairs.flags & WIDE_FLAG != 0 {
+            true => read_u16_le(&file.header, file.map + 2 * index)? as usize,
+            false => read_u8(&file.header, file.map + index)? as usize,
+        };
+    }
+    let is_in_moves = match wdl {
+        Wdl::Win => pairs.flags & WIN_PLIES_FLAG == 0,
+        Wdl::Loss => pairs.flags & LOSS_PLIES_FLAG == 0,
+        Wdl::CursedWin | Wdl::BlessedLoss => true,
+        Wdl::Draw => false,
+    };
+    if is_in_moves {
+        value *= 2;
+    }
+    Some(value as i32 + 1)
+}
+
+impl Tablebases {
+    // the tables in any of the directories, separated as in PATH. missing
+    // directories are skipped, and a table that can't be read is only found
+    // out when it is first probed
+    pub fn open(paths: &str) -> Tablebases {
+        let directories: Vec<PathBuf> = env::split_paths(paths).collect();
+        let find_file = |name: &str| {
+            directories
+                .iter()
+                .map(|directory| directory.join(name))
+                .find(|path| path.is_file())
+        };
+
+        let mut tablebases = Tablebases {
+            tables: Vec::new(),
+            table_indices: HashMap::new(),
+            max_pieces: 0,
+            indices: Indices::new(),
+        };
+        for directory in &directories {
+            let Ok(entries) = fs::read_dir(directory) else {
+                continue;
+            };
+            let mut names: Vec<String> = entries
+                .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+                .collect();
+            names.sort();
+            for name in names {
+                let Some(stem) = name.strip_suffix(".rtbw") else {
+                    continue;
+                };
+                let Some(material) = parse_material(stem) else {
+                    continue;
+                };
+                if tablebases.table_indices.contains_key(&material) {
+                    continue;
+                }
+                let table = Table::new(
+                    &material,
+                    directory.join(&name),
+                    find_file(&format!("{stem}.rtbz")),
+                );
+                tablebases.max_pieces = tablebases.max_pieces.max(table.piece_count);
+                let index = tablebases.tables.len();
+                tablebases.table_indices.insert(material, index);
+                tablebases
+                    .table_indices
+                    .insert([material[1], material[0]], index);
+                tablebases.tables.push(table);
+            }
+        }
+        tablebases
+    }
+
+    pub fn len(&self) -> usize {
+        self.tables.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+    }
+
+    pub fn get_max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    // the tables know nothing of castling, and only hold so many pieces
+    pub fn can_probe(&self, position: &Position) -> bool {
+        position.castling_rights == 0
+            && position.get_occupancy().count_ones() as usize <= self.max_pieces
+    }
+
+    fn get_table_file<'a>(&'a self, table: &'a Table, is_dtz: bool) -> Option<&'a TableFile> {
+        let (file, path) = match is_dtz {
+            true => (&table.dtz, table.dtz_path.as_ref()),
+            false => (&table.wdl, Some(&table.wdl_path)),
+        };
+        file.get_or_init(|| {
+            let file = File::open(path?).ok()?;
+            read_table_file(table, file, is_dtz, &self.indices)
+        })
+        .as_ref()
+    }
+
+    // the pieces as the table has them, which is with the side named first
+    // as white, and the pawns the table leads with first
+    fn get_encoding(
+        &self,
+        table: &Table,
+        file: &TableFile,
+        position: &Position,
+    ) -> Option<Encoding> {
+        let is_black_to_move = position.turn == Colour::Black;
+        let is_flipped =
+            (table.is_symmetric() && is_black_to_move) || get_material(position) != table.material;
+        let (colour_flip, square_flip) = match is_flipped {
+            true => (8, 56),
+            false => (0, 0),
+        };
+        let mut encoding = Encoding {
+            side: (is_flipped ^ is_black_to_move) as usize,
+            file: 0,
+            squares: [0; MAX_PIECES],
+            pieces: [0; MAX_PIECES],
+            count: 0,
+            lead_pawn_count: 0,
+        };
+
+        // the leading pawn is the one nearest the edge, and it decides which
+        // of the four files' tables holds the position
+        let mut lead_pawns = 0;
+        if table.has_pawns {
+            let lead_piece = file.get_pairs(&0, &0).pieces[0] ^ colour_flip;
+            let lead_colour = match lead_piece & 8 {
+                0 => Colour::White,
+                _ => Colour::Black,
+            };
+            lead_pawns = position.get_bitboard(&Piece::new(&Class::Pawn, &lead_colour));
+            let mut pawns = lead_pawns;
+            while pawns != 0 {
+                encoding.squares[encoding.count] =
+                    bitboard_to_index(&pop_lsb(&mut pawns)) ^ square_flip;
+                encoding.count += 1;
+            }
+            encoding.lead_pawn_count = encoding.count;
+            let mut lead = 0;
+            for i in 1..encoding.count {
+                if self.indices.pawns[encoding.squares[i]]
+                    > self.indices.pawns[encoding.squares[lead]]
+                {
+                    lead = i;
+                }
+            }
+            encoding.squares.swap(0, lead);
+            let lead_file = encoding.squares[0] % 8;
+            encoding.file = lead_file.min(7 - lead_file);
+        }
+
+        let mut pieces = position.get_occupancy() ^ lead_pawns;
+        while pieces != 0 {
+            let square = pop_lsb(&mut pieces);
+            let piece = position.get_piece_at(&square)?;
+            encoding.squares[encoding.count] = bitboard_to_index(&square) ^ square_flip;
+            encoding.pieces[encoding.count] = get_table_piece(piece) ^ colour_flip;
+            encoding.count += 1;
+        }
+        Some(encoding)
+    }
+
+    fn get_index(&self, table: &Table, pairs: &PairsData, encoding: &mut Encoding) -> u64 {
+        let indices = &self.indices;
+        let (lead_pawn_count, count) = (encoding.lead_pawn_count, encoding.count);
+        let squares = &mut encoding.squares;
+
+        // the pieces go in the order the table was encoded in
+        for i in lead_pawn_count..count - 1 {
+            for j in i + 1..count {
+                if pairs.pieces[i] == encoding.pieces[j] {
+                    encoding.pieces.swap(i, j);
+                    squares.swap(i, j);
+                    break;
+                }
+            }
+        }
+
+        // the leading piece goes on files a to d, and without pawns ranks 1
+        // to 4 and on or below the a1-h8 diagonal
+        if squares[0] % 8 > 3 {
+            for square in squares[..count].iter_mut() {
+                *square ^= 7;
+            }
+        }
+        let mut index = if table.has_pawns {
+            let mut index = indices.lead_pawns[lead_pawn_count][squares[0]];
+            squares[1..lead_pawn_count].sort_by_key(|square| indices.pawns[*square]);
+            for (i, square) in squares.iter().enumerate().take(lead_pawn_count).skip(1) {
+                index += indices.binomial[i][indices.pawns[*square]];
+            }
+            index
+        } else {
+            if squares[0] / 8 > 3 {
+                for square in squares[..count].iter_mut() {
+                    *square ^= 56;
+                }
+            }
+            for i in 0..pairs.group_lengths[0] {
+                let offset = get_diagonal_offset(&squares[i]);
+                if offset == 0 {
+                    continue;
+                }
+                if offset > 0 {
+                    for square in squares[i..count].iter_mut() {
+                        *square = ((*square >> 3) | (*square << 3)) & 63;
+                    }
+                }
+                break;
+            }
+            self.get_leading_group_index(table, squares) as u64
+        };
+        index *= pairs.group_factors[0];
+
+        // each other group is its squares chosen from those left, with the
+        // other side's pawns kept off the first and last ranks
+        let mut group_start = pairs.group_lengths[0];
+        let mut are_remaining_pawns = table.both_sides_have_pawns();
+        let mut group = 1;
+        while pairs.group_lengths[group] != 0 {
+            let length = pairs.group_lengths[group];
+            squares[group_start..group_start + length].sort();
+            let mut group_index = 0;
+            for i in 0..length {
+                let square = squares[group_start + i];
+                let lower_squares = squares[..group_start]
+                    .iter()
+                    .filter(|earlier| square > **earlier)
+                    .count();
+                let rank_adjustment = match are_remaining_pawns {
+                    true => 8,
+                    false => 0,
+                };
+                group_index += indices.binomial[i + 1][square - lower_squares - rank_adjustment];
+            }
+            are_remaining_pawns = false;
+            index += group_index * pairs.group_factors[group];
+            group_start += length;
+            group += 1;
+        }
+        index
+    }
+
+    // the kings and a third unique piece together, or just the kings
+    fn get_leading_group_index(&self, table: &Table, squares: &[usize; MAX_PIECES]) -> usize {
+        let indices = &self.indices;
+        if !table.has_unique_pieces {
+            return indices.kings[indices.triangle[squares[0]]][squares[1]];
+        }
+        let adjust_first = (squares[1] > squares[0]) as usize;
+        let adjust_second = (squares[2] > squares[0]) as usize + (squares[2] > squares[1]) as usize;
+        let rank = |square: &usize| square / 8;
+        if get_diagonal_offset(&squares[0]) != 0 {
+            (indices.triangle[squares[0]] * 63 + squares[1] - adjust_first) * 62 + squares[2]
+                - adjust_second
+        } else if get_diagonal_offset(&squares[1]) != 0 {
+            (6 * 63 + rank(&squares[0]) * 28 + indices.below_diagonal[squares[1]]) * 62 + squares[2]
+                - adjust_second
+        } else if get_diagonal_offset(&squares[2]) != 0 {
+            6 * 63 * 62
+                + 4 * 28 * 62
+                + rank(&squares[0]) * 7 * 28
+                + (rank(&squares[1]) - adjust_first) * 28
+                + indices.below_diagonal[squares[2]]
+        } else {
+            6 * 63 * 62
+                + 4 * 28 * 62
+                + 4 * 7 * 28
+                + rank(&squares[0]) * 7 * 6
+                + (rank(&squares[1]) - adjust_first) * 6
+                + (rank(&squares[2]) - adjust_second)
+        }
+    }
+
+    // the result stored in the wdl table, which may be wrong wherever the
+    // best move is a capture
+    fn probe_wdl_table(&self, position: &Position) -> Option<Wdl> {
+        let material = get_material(position);
+        if material.iter().flatten().sum::<u8>() == 2 {
+            return Some(Wdl::Draw);
+        }
+        let table = &self.tables[*self.table_indices.get(&material)?];
+        let file = self.get_table_file(table, false)?;
+        let mut encoding = self.get_encoding(table, file, position)?;
+        let pairs = file.get_pairs(&encoding.side, &encoding.file);
+        let index = self.get_index(table, pairs, &mut encoding);
+        let value = decompress_pairs(file, pairs, &index)?;
+        Wdl::from_value(&(value as i32 - 2))
+    }
+
+    fn probe_dtz_table(&self, position: &Position, wdl: &Wdl) -> Option<DtzProbe> {
+        let table = &self.tables[*self.table_indices.get(&get_material(position))?];
+        let file = self.get_table_file(table, true)?;
+        let mut encoding = self.get_encoding(table, file, position)?;
+        let pairs = file.get_pairs(&0, &encoding.file);
+        let is_stored_side = (pairs.flags & SIDE_TO_MOVE_FLAG) as usize == encoding.side
+            || (table.is_symmetric() && !table.has_pawns);
+        if !is_stored_side {
+            return Some(DtzProbe::OtherSide);
+        }
+        let index = self.get_index(table, pairs, &mut encoding);
+        let value = decompress_pairs(file, pairs, &index)?;
+        Some(DtzProbe::Found(get_dtz_from_value(
+            file, pairs, &value, wdl,
+        )?))
+    }
+
+    // the tables leave out positions where a capture wins, or at least
+    // draws, since the capture shows the result anyway. so the captures,
+    // and for dtz the pawn moves, are tried first. also returns whether one
+    // of those moves is the best
+    fn search_zeroing_moves(
+        &self,
+        position: &Position,
+        include_pawn_moves: bool,
+    ) -> Option<(Wdl, bool)> {
+        let moves = generate_legal_moves(position);
+        let mut best = Wdl::Loss;
+        let mut zeroing_move_count = 0;
+        for mv in &moves {
+            let is_zeroing =
+                is_capture(position, mv) || (include_pawn_moves && is_pawn_move(position, mv));
+            if !is_zeroing {
+                continue;
+            }
+            zeroing_move_count += 1;
+            let mut next_position = *position;
+            next_position.make_move(mv);
+            let (next_wdl, _) = self.search_zeroing_moves(&next_position, false)?;
+            let wdl = -next_wdl;
+            if wdl > best {
+                best = wdl;
+                if wdl == Wdl::Win {
+                    return Some((wdl, true));
+                }
+            }
+        }
+
+        // with nothing but captures to play the table isn't needed, and may
+        // even hold a value for the wrong result
+        let has_searched_all = zeroing_move_count > 0 && zeroing_move_count == moves.len();
+        let stored = match has_searched_all {
+            true => best,
+            false => self.probe_wdl_table(position)?,
+        };
+        if best >= stored {
+            return Some((best, best > Wdl::Draw || has_searched_all));
+        }
+        Some((stored, false))
+    }
+
+    fn get_dtz(&self, position: &Position) -> Option<i32> {
+        let (wdl, is_zeroing_best) = self.search_zeroing_moves(position, true)?;
+        if wdl == Wdl::Draw {
+            return Some(0);
+        }
+        if is_zeroing_best {
+            return Some(get_dtz_before_zeroing(&wdl));
+        }
+        if let DtzProbe::Found(dtz) = self.probe_dtz_table(position, &wdl)? {
+            let fifty_move_plies = match wdl {
+                Wdl::CursedWin | Wdl::BlessedLoss => 100,
+                _ => 0,
+            };
+            return Some((dtz + fifty_move_plies) * wdl.signum());
+        }
+
+        // the table only has the other side to move, so the dtz is a ply
+        // more than that of the best reply
+        let mut best_dtz = i32::MAX;
+        for mv in generate_legal_moves(position) {
+            let is_zeroing = is_capture(position, &mv) || is_pawn_move(position, &mv);
+            let mut next_position = *position;
+            next_position.make_move(&mv);
+            let mut dtz = match is_zeroing {
+                true => {
+                    let (next_wdl, _) = self.search_zeroing_moves(&next_position, false)?;
+                    -get_dtz_before_zeroing(&next_wdl)
+                }
+                false => -self.get_dtz(&next_position)?,
+            };
+            if dtz == 1 && is_checkmate(&next_position) {
+                best_dtz = 1;
+            }
+            if !is_zeroing {
+                dtz += dtz.signum();
+            }
+            if dtz < best_dtz && dtz.signum() == wdl.signum() {
+                best_dtz = dtz;
+            }
+        }
+        match best_dtz {
+            i32::MAX => Some(-1),
+            dtz => Some(dtz),
+        }
+    }
+
+    // win, draw or loss for the side to move, or None if the position isn't
+    // covered or its table can't be read
+    pub fn probe_wdl(&self, position: &Position) -> Option<Wdl> {
+        if !self.can_probe(position) {
+            return None;
+        }
+        self.search_zeroing_moves(position, false)
+            .map(|(wdl, _)| wdl)
+    }
+
+    // plies to the next capture or pawn move with best play, positive when
+    // winning and negative when losing, and over 100 when the fifty move
+    // rule will save the loser
+    pub fn probe_dtz(&self, position: &Position) -> Option<i32> {
+        if !self.can_probe(position) {
+            return None;
+        }
+        self.get_dtz(position)
+    }
+
+    // the dtz of the position the move leads to, counted from before it
+    fn get_root_dtz(&self, position: &Position, mv: &Move) -> Option<i32> {
+        let mut next_position = *position;
+        next_position.make_move(mv);
+        if is_checkmate(&next_position) {
+            return Some(1);
+        }
+        if next_position.halfmove_clock == 0 {
+            let (next_wdl, _) = self.search_zeroing_moves(&next_position, false)?;
+            return Some(get_dtz_before_zeroing(&-next_wdl));
+        }
+        if next_position.halfmove_clock >= 100 {
+            return Some(0);
+        }
+        let dtz = -self.get_dtz(&next_position)?;
+        Some(dtz + dtz.signum())
+    }
+
+    // wins by how soon they reset the count, then draws, then losses by how
+    // long they put it off
+    fn rank_by_dtz(&self, position: &Position, moves: &[Move]) -> Option<Vec<i32>> {
+        moves
+            .iter()
+            .map(|mv| {
+                let dtz = self.get_root_dtz(position, mv)?;
+                Some(match dtz.signum() {
+                    1 => MAX_DTZ - dtz,
+                    -1 => -MAX_DTZ - dtz,
+                    _ => 0,
+                })
+            })
+            .collect()
+    }
+
+    // without the dtz tables, only by result
+    fn rank_by_wdl(&self, position: &Position, moves: &[Move]) -> Option<Vec<i32>> {
+        moves
+            .iter()
+            .map(|mv| {
+                let mut next_position = *position;
+                next_position.make_move(mv);
+                if is_checkmate(&next_position) {
+                    return Some(Wdl::Win as i32);
+                }
+                if next_position.halfmove_clock >= 100 {
+                    return Some(Wdl::Draw as i32);
+                }
+                let (next_wdl, _) = self.search_zeroing_moves(&next_position, false)?;
+                Some((-next_wdl) as i32)
+            })
+            .collect()
+    }
+
+    // the moves that keep the best result the tables can prove, using the
+    // dtz tables where there are any to pick the quickest win, or None if
+    // the position isn't covered
+    pub fn filter_root_moves(&self, position: &Position, moves: &[Move]) -> Option<Vec<Move>> {
+        if !self.can_probe(position) {
+            return None;
+        }
+        let ranks = self
+            .rank_by_dtz(position, moves)
+            .or_else(|| self.rank_by_wdl(position, moves))?;
+        let best_rank = *ranks.iter().max()?;
+        Some(
+            moves
+                .iter()
+                .zip(ranks)
+                .filter(|(_, rank)| *rank == best_rank)
+                .map(|(mv, _)| *mv)
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::endgame_tables::{generate_table, parse_table_name, Dtm, EndgameTables};
+    use crate::notation::{fen_to_position, move_to_uci};
+    use crate::position::get_empty_position;
+    use crate::utils::index_to_bitboard;
+    use std::cmp::Reverse;
+    use std::collections::BinaryHeap;
+    use std::path::Path;
+
+    // tables of KQvK and KRvK with both files, and the wdl file of KPvK,
+    // compressed from the distance to mate tables of the same endings by the
+    // encoder at the end of this module
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/syzygy");
+    const FIXTURE_NAMES: [&str; 5] = [
+        "KQvK.rtbw",
+        "KQvK.rtbz",
+        "KRvK.rtbw",
+        "KRvK.rtbz",
+        "KPvK.rtbw",
+    ];
+    const FIXTURE_BLOCK_LOG: u8 = 5;
+    const FIXTURE_SPAN_LOG: u8 = 6;
+    // a pair of symbols is only made into a new one if it comes up this
+    // often, and stands for no more than this many values
+    const MIN_PAIR_COUNT: usize = 16;
+    const MAX_SYMBOL_VALUES: usize = 64;
+    const MAX_PAIRINGS: usize = 40;
+
+    fn get_distance_to_mate_tables() -> &'static EndgameTables {
+        static TABLES: OnceLock<EndgameTables> = OnceLock::new();
+        TABLES.get_or_init(|| {
+            let mut tables = EndgameTables::new();
+            for name in ["KQvK", "KRvK", "KBvK", "KNvK", "KPvK"] {
+                let pieces = parse_table_name(name).unwrap();
+                let table = generate_table(&pieces, &tables).unwrap();
+                tables.insert(table);
+            }
+            tables
+        })
+    }
+
+    // every legal placement of the pieces, with either side to move
+    fn get_all_positions(pieces: &[Piece]) -> Vec<Position> {
+        let mut positions = Vec::new();
+        for placement in 0..64usize.pow(pieces.len() as u32) {
+            let squares: Vec<usize> = (0..pieces.len())
+                .map(|i| (placement >> (6 * i)) & 63)
+                .collect();
+            let mut position = get_empty_position();
+            for (piece, square) in pieces.iter().zip(&squares) {
+                position.insert_piece_at_square(piece, &index_to_bitboard(square));
+            }
+            let is_pawn_on_back_rank = pieces
+                .iter()
+                .zip(&squares)
+                .any(|(piece, square)| piece.class() == Class::Pawn && matches!(square / 8, 0 | 7));
+            if position.get_occupancy().count_ones() as usize != pieces.len()
+                || is_pawn_on_back_rank
+            {
+                continue;
+            }
+            for (turn, other) in [
+                (Colour::White, Colour::Black),
+                (Colour::Black, Colour::White),
+            ] {
+                position.turn = turn;
+                if !position.is_in_check(&other) {
+                    positions.push(position);
+                }
+            }
+        }
+        positions
+    }
+
+    fn get_pieces(name: &str, is_flipped: bool) -> Vec<Piece> {
+        let pieces = parse_table_name(name).unwrap();
+        match is_flipped {
+            true => pieces
+                .iter()
+                .map(|piece| match piece.colour() {
+                    Colour::White => Piece::new(&piece.class(), &Colour::Black),
+                    Colour::Black => Piece::new(&piece.class(), &Colour::White),
+                })
+                .collect(),
+            false => pieces,
+        }
+    }
+
+    fn get_temporary_directory(name: &str) -> PathBuf {
+        let directory =
+            env::temp_dir().join(format!("chess_engine_syzygy_{name}_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    fn get_moves(position: &Position, moves: &[Move]) -> Vec<String> {
+        let mut moves: Vec<String> = moves
+            .iter()
+            .map(|mv| move_to_uci(position, mv, &false))
+            .collect();
+        moves.sort();
+        moves
+    }
+
+    #[test]
+    fn probe_wdl_matches_distance_to_mate() {
+        let tablebases = Tablebases::open(FIXTURES);
+        let tables = get_distance_to_mate_tables();
+        for name in ["KQvK", "KRvK", "KPvK"] {
+            for is_flipped in [false, true] {
+                for position in get_all_positions(&get_pieces(name, is_flipped)) {
+                    let expected = match tables.probe(&position).unwrap() {
+                        Dtm::Win(_) => Wdl::Win,
+                        Dtm::Draw => Wdl::Draw,
+                        Dtm::Loss(_) => Wdl::Loss,
+                    };
+                    assert_eq!(
+                        tablebases.probe_wdl(&position),
+                        Some(expected),
+                        "{name} {position:?}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn probe_dtz_matches_distance_to_mate() {
+        // with no captures or pawn moves to win with, the first zeroing move
+        // is the mate. only a sample, since probing the side a table doesn't
+        // hold searches a ply
+        let tablebases = Tablebases::open(FIXTURES);
+        let tables = get_distance_to_mate_tables();
+        for name in ["KQvK", "KRvK"] {
+            for is_flipped in [false, true] {
+                let positions = get_all_positions(&get_pieces(name, is_flipped));
+                for position in positions.iter().step_by(16) {
+                    let expected = match tables.probe(position).unwrap() {
+                        Dtm::Win(plies) => plies as i32,
+                        Dtm::Draw => 0,
+                        Dtm::Loss(0) => -1,
+                        Dtm::Loss(plies) => -(plies as i32),
+                    };
+                    assert_eq!(
+                        tablebases.probe_dtz(position),
+                        Some(expected),
+                        "{name} {position:?}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn probe_known_positions() {
+        let tablebases = Tablebases::open(FIXTURES);
+        for (fen, wdl, dtz) in [
+            ("8/8/8/8/8/2k5/8/KQ6 w - - 0 1", Wdl::Win, Some(11)),
+            ("k7/8/1K6/8/8/8/7Q/8 w - - 0 1", Wdl::Win, Some(1)),
+            ("k6Q/8/1K6/8/8/8/8/8 b - - 0 1", Wdl::Loss, Some(-1)),
+            ("8/8/8/4k3/8/8/8/KR6 b - - 0 1", Wdl::Loss, Some(-30)),
+            ("8/8/8/8/8/1k6/1R6/7K b - - 0 1", Wdl::Draw, Some(0)),
+            ("8/8/8/8/8/4k3/4P3/4K3 w - - 0 1", Wdl::Draw, Some(0)),
+            ("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1", Wdl::Loss, None),
+        ] {
+            let position = fen_to_position(fen).unwrap();
+            assert_eq!(tablebases.probe_wdl(&position), Some(wdl), "{fen}");
+            assert_eq!(tablebases.probe_dtz(&position), dtz, "{fen}");
+        }
+
+        let mut position = fen_to_position("8/8/8/8/8/2k5/8/KQ6 w - - 0 1").unwrap();
+        position.castling_rights = 1;
+        assert_eq!(tablebases.probe_wdl(&position), None);
+        let position = fen_to_position("8/8/8/8/3k4/8/8/KQR5 w - - 0 1").unwrap();
+        assert_eq!(tablebases.probe_wdl(&position), None);
+    }
+
+    #[test]
+    fn filter_root_moves_keeps_the_best_result() {
+        let tablebases = Tablebases::open(FIXTURES);
+        for (fen, expected) in [
+            // the quickest mates, by dtz
+            ("8/8/8/8/8/2k5/8/KQ6 w - - 0 1", vec!["b1e4"]),
+            ("k7/8/1K6/8/8/8/7Q/8 w - - 0 1", vec!["h2h8"]),
+            // the only draw
+            ("8/8/8/8/8/1k6/1R6/7K b - - 0 1", vec!["b3b2"]),
+            // without a dtz table, every move that keeps the draw
+            ("8/8/8/8/8/4k3/4P3/4K3 w - - 0 1", vec!["e1d1", "e1f1"]),
+        ] {
+            let position = fen_to_position(fen).unwrap();
+            let moves = generate_legal_moves(&position);
+            let filtered = tablebases.filter_root_moves(&position, &moves).unwrap();
+            assert_eq!(get_moves(&position, &filtered), expected, "{fen}");
+        }
+
+        let position = fen_to_position("8/8/8/8/3k4/8/8/KQR5 w - - 0 1").unwrap();
+        let moves = generate_legal_moves(&position);
+        assert_eq!(tablebases.filter_root_moves(&position, &moves), None);
+    }
+
+    #[test]
+    fn filter_root_moves_without_dtz_keeps_every_win() {
+        let directory = get_temporary_directory("wdl_only");
+        fs::copy(
+            Path::new(FIXTURES).join("KQvK.rtbw"),
+            directory.join("KQvK.rtbw"),
+        )
+        .unwrap();
+        let tablebases = Tablebases::open(directory.to_str().unwrap());
+        let tables = get_distance_to_mate_tables();
+
+        let position = fen_to_position("8/8/8/8/8/2k5/8/KQ6 w - - 0 1").unwrap();
+        let moves = generate_legal_moves(&position);
+        let winning_moves: Vec<Move> = moves
+            .iter()
+            .filter(|mv| {
+                let mut next_position = position;
+                next_position.make_move(mv);
+                matches!(tables.probe(&next_position), Some(Dtm::Loss(_)))
+            })
+            .copied()
+            .collect();
+        assert!(winning_moves.len() < moves.len());
+        let filtered = tablebases.filter_root_moves(&position, &moves).unwrap();
+        assert_eq!(
+            get_moves(&position, &filtered),
+            get_moves(&position, &winning_moves)
+        );
+        assert_eq!(tablebases.probe_dtz(&position), None);
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn missing_and_damaged_tables_are_not_probed() {
+        let position = fen_to_position("8/8/8/8/8/2k5/8/KQ6 w - - 0 1").unwrap();
+        let tablebases = Tablebases::open("/does/not/exist");
+        assert!(tablebases.is_empty());
+        assert_eq!(tablebases.probe_wdl(&position), None);
+
+        // cut short, and with the wrong magic
+        let directory = get_temporary_directory("damaged");
+        let bytes = fs::read(Path::new(FIXTURES).join("KQvK.rtbw")).unwrap();
+        fs::write(directory.join("KQvK.rtbw"), &bytes[..bytes.len() / 2]).unwrap();
+        let mut bytes = fs::read(Path::new(FIXTURES).join("KQvK.rtbz")).unwrap();
+        bytes[0] ^= 0xFF;
+        fs::write(directory.join("KQvK.rtbz"), bytes).unwrap();
+        fs::write(directory.join("KRvK.rtbw"), b"garbage").unwrap();
+        let tablebases = Tablebases::open(directory.to_str().unwrap());
+        assert_eq!(tablebases.len(), 2);
+        assert_eq!(tablebases.probe_wdl(&position), None);
+        assert_eq!(tablebases.probe_dtz(&position), None);
+        let position = fen_to_position("8/8/8/4k3/8/8/8/KR6 b - - 0 1").unwrap();
+        assert_eq!(tablebases.probe_wdl(&position), None);
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    // the encoder is the reader run backwards: the values are laid out in
+    // index order, the most common neighbouring symbols are paired up into
+    // new ones, and what is left is given canonical huffman codes. there
+    // are no official tables to hand, so it is what the fixtures come from
+
+    // one side and file's codes, sparse index, block lengths and blocks,
+    // which each go in a different part of the file
+    struct EncodedPairs {
+        sizes: Vec<u8>,
+        sparse_index: Vec<u8>,
+        block_lengths: Vec<u8>,
+        blocks: Vec<u8>,
+    }
+
+    // the distinct values as symbols, then the most common pair of
+    // neighbouring symbols made into a new one, over and over. gives the
+    // symbols, how many values each stands for and the values as symbols
+    fn pair_symbols(values: &[u16]) -> (Vec<(usize, usize)>, Vec<usize>, Vec<usize>) {
+        let mut distinct = values.to_vec();
+        distinct.sort();
+        distinct.dedup();
+        let mut symbols: Vec<(usize, usize)> = distinct
+            .iter()
+            .map(|value| (*value as usize, LEAF_SYMBOL))
+            .collect();
+        let mut value_counts = vec![1; symbols.len()];
+        let mut sequence: Vec<usize> = values
+            .iter()
+            .map(|value| distinct.binary_search(value).unwrap())
+            .collect();
+        for _ in 0..MAX_PAIRINGS {
+            let mut pair_counts: HashMap<(usize, usize), usize> = HashMap::new();
+            for pair in sequence.windows(2) {
+                if value_counts[pair[0]] + value_counts[pair[1]] <= MAX_SYMBOL_VALUES {
+                    *pair_counts.entry((pair[0], pair[1])).or_default() += 1;
+                }
+            }
+            // ties go to the lowest pair, so the same values always give the
+            // same file
+            let Some((&(left, right), &count)) = pair_counts
+                .iter()
+                .max_by_key(|(pair, count)| (**count, Reverse(**pair)))
+            else {
+                break;
+            };
+            if count < MIN_PAIR_COUNT {
+                break;
+            }
+            let symbol = symbols.len();
+            symbols.push((left, right));
+            value_counts.push(value_counts[left] + value_counts[right]);
+            let mut paired = Vec::with_capacity(sequence.len());
+            let mut i = 0;
+            while i < sequence.len() {
+                match sequence.get(i..i + 2) {
+                    Some(&[first, second]) if (first, second) == (left, right) => {
+                        paired.push(symbol);
+                        i += 2;
+                    }
+                    _ => {
+                        paired.push(sequence[i]);
+                        i += 1;
+                    }
+                }
+            }
+            sequence = paired;
+        }
+        (symbols, value_counts, sequence)
+    }
+
+    // huffman code lengths of the symbols that are used, and 0 for the rest
+    fn get_code_lengths(frequencies: &[usize]) -> Vec<usize> {
+        let used: Vec<usize> = (0..frequencies.len())
+            .filter(|symbol| frequencies[*symbol] > 0)
+            .collect();
+        let mut lengths = vec![0; frequencies.len()];
+        if used.len() == 1 {
+            lengths[used[0]] = 1;
+            return lengths;
+        }
+        // the used symbols are the first nodes, and each merge adds a parent
+        let mut parents = vec![usize::MAX; used.len()];
+        let mut heap: BinaryHeap<Reverse<(usize, usize)>> = used
+            .iter()
+            .enumerate()
+            .map(|(node, symbol)| Reverse((frequencies[*symbol], node)))
+            .collect();
+        while heap.len() > 1 {
+            let Reverse((first_frequency, first)) = heap.pop().unwrap();
+            let Reverse((second_frequency, second)) = heap.pop().unwrap();
+            let node = parents.len();
+            parents.push(usize::MAX);
+            parents[first] = node;
+            parents[second] = node;
+            heap.push(Reverse((first_frequency + second_frequency, node)));
+        }
+        for (node, symbol) in used.iter().enumerate() {
+            let mut ancestor = node;
+            while parents[ancestor] != usize::MAX {
+                ancestor = parents[ancestor];
+                lengths[*symbol] += 1;
+            }
+        }
+        lengths
+    }
+
+    fn encode_values(values: &[u16], flags: u8) -> EncodedPairs {
+        if values.iter().all(|value| *value == values[0]) {
+            return EncodedPairs {
+                sizes: vec![flags | SINGLE_VALUE_FLAG, values[0] as u8],
+                sparse_index: Vec::new(),
+                block_lengths: Vec::new(),
+                blocks: Vec::new(),
+            };
+        }
+        let (symbols, value_counts, sequence) = pair_symbols(values);
+        let mut frequencies = vec![0; symbols.len()];
+        for symbol in &sequence {
+            frequencies[*symbol] += 1;
+        }
+        let lengths = get_code_lengths(&frequencies);
+        let used: Vec<usize> = (0..symbols.len())
+            .filter(|symbol| lengths[*symbol] > 0)
+            .collect();
+        let min_length = used.iter().map(|symbol| lengths[*symbol]).min().unwrap();
+        let max_length = used.iter().map(|symbol| lengths[*symbol]).max().unwrap();
+
+        // the codes of each length are consecutive numbers, and the symbols
+        // are numbered by code with the longest first, as the reader expects
+        let mut length_counts = vec![0; max_length + 2];
+        for symbol in &used {
+            length_counts[lengths[*symbol]] += 1;
+        }
+        let mut base = vec![0u64; max_length + 2];
+        let mut lowest = vec![0; max_length + 2];
+        for length in (min_length..max_length).rev() {
+            base[length] = (base[length + 1] + length_counts[length + 1] as u64) / 2;
+            lowest[length] = lowest[length + 1] + length_counts[length + 1];
+        }
+        let mut numbers = vec![usize::MAX; symbols.len()];
+        let mut codes = vec![(0u64, 0); symbols.len()];
+        for length in (min_length..=max_length).rev() {
+            let symbols_of_length = used.iter().filter(|symbol| lengths[**symbol] == length);
+            for (i, symbol) in symbols_of_length.enumerate() {
+                numbers[*symbol] = lowest[length] + i;
+                codes[*symbol] = (base[length] + i as u64, length);
+            }
+        }
+        // symbols only used as halves of others come after every code
+        let unused = numbers.iter_mut().filter(|number| **number == usize::MAX);
+        for (next_number, number) in (used.len()..).zip(unused) {
+            *number = next_number;
+        }
+
+        // codes go into blocks most significant bit first, with none split
+        // between two blocks
+        let block_bits = 8 << FIXTURE_BLOCK_LOG;
+        let mut blocks = Vec::new();
+        let mut block_value_counts = Vec::new();
+        let mut bits: Vec<bool> = Vec::new();
+        let mut value_count = 0;
+        let flush = |bits: &mut Vec<bool>, blocks: &mut Vec<u8>| {
+            bits.resize(block_bits, false);
+            for byte in bits.chunks(8) {
+                blocks.push(byte.iter().fold(0, |byte, bit| (byte << 1) | *bit as u8));
+            }
+            bits.clear();
+        };
+        for symbol in &sequence {
+            let (code, length) = codes[*symbol];
+            if bits.len() + length > block_bits {
+                flush(&mut bits, &mut blocks);
+                block_value_counts.push(value_count);
+                value_count = 0;
+            }
+            bits.extend((0..length).rev().map(|bit| (code >> bit) & 1 == 1));
+            value_count += value_counts[*symbol];
+        }
+        flush(&mut bits, &mut blocks);
+        block_value_counts.push(value_count);
+
+        let mut sizes = vec![flags, FIXTURE_BLOCK_LOG, FIXTURE_SPAN_LOG, 0];
+        sizes.extend((block_value_counts.len() as u32).to_le_bytes());
+        sizes.push(max_length as u8);
+        sizes.push(min_length as u8);
+        for lowest_number in &lowest[min_length..=max_length] {
+            sizes.extend((*lowest_number as u16).to_le_bytes());
+        }
+        sizes.extend((symbols.len() as u16).to_le_bytes());
+        let mut tree = vec![(0, 0); symbols.len()];
+        for (symbol, (left, right)) in symbols.iter().enumerate() {
+            tree[numbers[symbol]] = match *right == LEAF_SYMBOL {
+                true => (*left, LEAF_SYMBOL),
+                false => (numbers[*left], numbers[*right]),
+            };
+        }
+        // twelve bits for each half
+        for (left, right) in tree {
+            sizes.push((left & 0xFF) as u8);
+            sizes.push(((left >> 8) & 0xF) as u8 | ((right & 0xF) << 4) as u8);
+            sizes.push((right >> 4) as u8);
+        }
+        if symbols.len() & 1 == 1 {
+            sizes.push(0);
+        }
+
+        let mut block_lengths = Vec::new();
+        let mut block_starts = Vec::new();
+        let mut start = 0;
+        for count in &block_value_counts {
+            block_lengths.extend((*count as u16 - 1).to_le_bytes());
+            block_starts.push(start);
+            start += count;
+        }
+        // each span's entry is the block and offset of its middle value
+        let span = 1 << FIXTURE_SPAN_LOG;
+        let mut sparse_index = Vec::new();
+        for i in 0..values.len().div_ceil(span) {
+            let middle = i * span + span / 2;
+            let block = block_starts
+                .iter()
+                .rposition(|start| *start <= middle)
+                .unwrap();
+            sparse_index.extend((block as u32).to_le_bytes());
+            sparse_index.extend(((middle - block_starts[block]) as u16).to_le_bytes());
+        }
+        EncodedPairs {
+            sizes,
+            sparse_index,
+            block_lengths,
+            blocks,
+        }
+    }
+
+    // a whole file, with a value for each position from its distance to
+    // mate. a position the value leaves out takes the value before it, which
+    // compresses best
+    fn encode_table(
+        name: &str,
+        order: &[u8],
+        is_dtz: bool,
+        flags: u8,
+        maps: &[Vec<u8>; 4],
+        get_value: &dyn Fn(&Dtm) -> Option<u16>,
+    ) -> Vec<u8> {
+        let tablebases = Tablebases {
+            tables: Vec::new(),
+            table_indices: HashMap::new(),
+            max_pieces: 0,
+            indices: Indices::new(),
+        };
+        let table = Table::new(&parse_material(name).unwrap(), PathBuf::new(), None);
+        let sides = match is_dtz || table.is_symmetric() {
+            true => 1,
+            false => 2,
+        };
+        let file_count = match table.has_pawns {
+            true => 4,
+            false => 1,
+        };
+        let mut pairs = vec![vec![PairsData::default(); file_count]; sides];
+        for side_pairs in pairs.iter_mut() {
+            for (file, file_pairs) in side_pairs.iter_mut().enumerate() {
+                file_pairs.pieces[..order.len()].copy_from_slice(order);
+                set_groups(&table, file_pairs, &[0, 0xF], &file, &tablebases.indices);
+            }
+        }
+        // only the pieces' order is looked at, the file is never read
+        let table_file = TableFile {
+            file: Mutex::new(File::open(Path::new(FIXTURES).join(FIXTURE_NAMES[0])).unwrap()),
+            header: Vec::new(),
+            pairs: pairs.clone(),
+            map: 0,
+        };
+
+        let mut values: Vec<Vec<Vec<Option<u16>>>> = pairs
+            .iter()
+            .map(|side_pairs| {
+                side_pairs
+                    .iter()
+                    .map(|file_pairs| {
+                        let group_count = file_pairs
+                            .group_lengths
+                            .iter()
+                            .position(|length| *length == 0)
+                            .unwrap();
+                        vec![None; file_pairs.group_factors[group_count] as usize]
+                    })
+                    .collect()
+            })
+            .collect();
+        let tables = get_distance_to_mate_tables();
+        for position in get_all_positions(&parse_table_name(name).unwrap()) {
+            let mut encoding = tablebases
+                .get_encoding(&table, &table_file, &position)
+                .unwrap();
+            // a dtz table holds only the side to move its flags name
+            if is_dtz && (flags & SIDE_TO_MOVE_FLAG) as usize != encoding.side {
+                continue;
+            }
+            let side = match is_dtz {
+                true => 0,
+                false => encoding.side,
+            };
+            let file = encoding.file;
+            let index = tablebases.get_index(&table, &pairs[side][file], &mut encoding) as usize;
+            let Some(value) = get_value(&tables.probe(&position).unwrap()) else {
+                continue;
+            };
+            let slot = &mut values[side][file][index];
+            if let Some(earlier_value) = slot {
+                assert_eq!(*earlier_value, value, "{name} {position:?}");
+            }
+            *slot = Some(value);
+        }
+
+        let mut encoded = Vec::new();
+        for file in 0..file_count {
+            for side_values in &values {
+                let mut previous = side_values[file].iter().flatten().next().copied();
+                let filled: Vec<u16> = side_values[file]
+                    .iter()
+                    .map(|value| {
+                        previous = value.or(previous);
+                        previous.unwrap_or(0)
+                    })
+                    .collect();
+                encoded.push(encode_values(&filled, flags));
+            }
+        }
+
+        let mut bytes = match is_dtz {
+            true => DTZ_MAGIC.to_vec(),
+            false => WDL_MAGIC.to_vec(),
+        };
+        bytes.push(!table.is_symmetric() as u8 | (table.has_pawns as u8) << 1);
+        for _ in 0..file_count {
+            bytes.push(0);
+            bytes.extend(order.iter().map(|piece| piece | piece << 4));
+        }
+        if bytes.len() & 1 == 1 {
+            bytes.push(0);
+        }
+        for pairs in &encoded {
+            bytes.extend(&pairs.sizes);
+        }
+        if is_dtz {
+            for pairs in &encoded {
+                if pairs.sizes[0] & MAPPED_FLAG != 0 {
+                    for map in maps {
+                        bytes.push(map.len() as u8);
+                        bytes.extend(map);
+                    }
+                }
+            }
+            if bytes.len() & 1 == 1 {
+                bytes.push(0);
+            }
+        }
+        for pairs in &encoded {
+            bytes.extend(&pairs.sparse_index);
+        }
+        for pairs in &encoded {
+            bytes.extend(&pairs.block_lengths);
+        }
+        for pairs in &encoded {
+            // blocks start on a cache line
+            bytes.resize(bytes.len().next_multiple_of(64), 0);
+            bytes.extend(&pairs.blocks);
+        }
+        bytes
+    }
+
+    fn get_wdl_value(dtm: &Dtm) -> Option<u16> {
+        Some(match dtm {
+            Dtm::Win(_) => 4,
+            Dtm::Draw => 2,
+            Dtm::Loss(_) => 0,
+        })
+    }
+
+    fn encode_fixture(file_name: &str) -> Vec<u8> {
+        let [king, queen, rook, pawn] = [Class::King, Class::Queen, Class::Rook, Class::Pawn]
+            .map(|class| get_table_piece(&Piece::new(&class, &Colour::White)));
+        let black_king = get_table_piece(&Piece::new(&Class::King, &Colour::Black));
+        let no_maps: [Vec<u8>; 4] = Default::default();
+        match file_name {
+            "KQvK.rtbw" => encode_table(
+                "KQvK",
+                &[queen, king, black_king],
+                false,
+                0,
+                &no_maps,
+                &get_wdl_value,
+            ),
+            // white to move, in moves rather than plies to the mate
+            "KQvK.rtbz" => encode_table(
+                "KQvK",
+                &[queen, king, black_king],
+                true,
+                0,
+                &no_maps,
+                &|dtm| match dtm {
+                    Dtm::Win(plies) => Some(((plies - 1) / 2) as u16),
+                    _ => None,
+                },
+            ),
+            "KRvK.rtbw" => encode_table(
+                "KRvK",
+                &[king, black_king, rook],
+                false,
+                0,
+                &no_maps,
+                &get_wdl_value,
+            ),
+            // black to move, in plies mapped from how many moves
+            "KRvK.rtbz" => {
+                let mut loss_map = vec![0];
+                loss_map.extend((1..32).step_by(2));
+                encode_table(
+                    "KRvK",
+                    &[king, black_king, rook],
+                    true,
+                    SIDE_TO_MOVE_FLAG | MAPPED_FLAG | LOSS_PLIES_FLAG,
+                    &[Vec::new(), loss_map, Vec::new(), Vec::new()],
+                    &|dtm| match dtm {
+                        Dtm::Loss(0) => Some(0),
+                        Dtm::Loss(plies) => Some((plies / 2) as u16),
+                        _ => None,
+                    },
+                )
+            }
+            "KPvK.rtbw" => encode_table(
+                "KPvK",
+                &[pawn, king, black_king],
+                false,
+                0,
+                &no_maps,
+                &get_wdl_value,
+            ),
+            _ => panic!("no fixture named {file_name}"),
+        }
+    }
+
+    #[test]
+    fn fixtures_match_their_encoder() {
+        for name in FIXTURE_NAMES {
+            let bytes = fs::read(Path::new(FIXTURES).join(name)).unwrap();
+            assert!(encode_fixture(name) == bytes, "{name}");
+        }
+    }
+
+    // 'cargo test write_fixtures -- --ignored' writes the fixtures again
+    #[test]
+    #[ignore]
+    fn write_fixtures() {
+        for name in FIXTURE_NAMES {
+            fs::write(Path::new(FIXTURES).join(name), encode_fixture(name)).unwrap();
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use std::time::Duration;
+
+use crate::moves::Move;
+
+// the clock of the side to move, as a GUI or the terminal reports it
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Clock {
+    pub remaining: Duration,
+    pub increment: Duration,
+    // moves until the next time control, or None if the time is for the rest
+    // of the game
+    pub moves_to_go: Option<u32>,
+    // kept back for the time between the engine sending a move and the GUI
+    // stopping its clock
+    pub move_overhead: Duration,
+}
+
+// how many more moves a game is planned for when the time control does not
+// say, and how much of each increment is spent on the move it comes with
+const DEFAULT_MOVES_TO_GO: u32 = 25;
+const INCREMENT_USAGE: f64 = 0.75;
+// the hard limit lets a difficult move take several times its share, but
+// never more than this much of what is left on the clock
+const HARD_LIMIT_FACTOR: f64 = 4.0;
+const MAX_TIME_USAGE: f64 = 0.8;
+
+// each change of best move adds to the time, fading by half with every
+// iteration it is not repeated
+const BEST_MOVE_CHANGE_DECAY: f64 = 0.5;
+const BEST_MOVE_CHANGE_WEIGHT: f64 = 0.5;
+// a score falling from one iteration to the next adds up to double the time,
+// in proportion to how far it fell
+const MAX_SCORE_DROP: i32 = 200;
+// a best move that has held for this many iterations and taken this share
+// of the nodes is clearly best, and only gets this share of the time
+const CLEAR_BEST_MOVE_ITERATIONS: u32 = 4;
+const CLEAR_BEST_MOVE_EFFORT: f64 = 0.8;
+const CLEAR_BEST_MOVE_FACTOR: f64 = 0.4;
+
+// decides between iterations whether to search deeper, spending more time
+// when the search is unsettled and less when it is sure
+pub struct TimeManager {
+    // no new iteration is started after this, adjusted as the search goes
+    soft_limit: Duration,
+    // the search is stopped part way through an iteration at this
+    hard_limit: Duration,
+    previous_best_move: Option<Move>,
+    previous_score: Option<i32>,
+    best_move_changes: f64,
+    stable_iterations: u32,
+}
+
+impl TimeManager {
+    pub fn new(clock: &Clock) -> TimeManager {
+        let available = clock.remaining.saturating_sub(clock.move_overhead);
+        let moves_to_go = clock.moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+        let share = available / moves_to_go + clock.increment.mul_f64(INCREMENT_USAGE);
+        let hard_limit = share
+            .mul_f64(HARD_LIMIT_FACTOR)
+            .min(available.mul_f64(MAX_TIME_USAGE));
+        TimeManager {
+            soft_limit: share.min(hard_limit),
+            hard_limit,
+            previous_best_move: None,
+            previous_score: None,
+            best_move_changes: 0.0,
+            stable_iterations: 0,
+        }
+    }
+
+    pub fn get_hard_limit(&self) -> Duration {
+        self.hard_limit
+    }
+
+    // called with the result of each completed iteration, and the share of
+    // its nodes spent on the best move
+    pub fn should_stop(
+        &mut self,
+        elapsed: &Duration,
+        best_move: &Option<Move>,
+        score: &i32,
+        best_move_effort: &f64,
+    ) -> bool {
+        let has_changed =
+            self.previous_best_move.is_some() && self.previous_best_move != *best_move;
+        self.best_move_changes = self.best_move_changes * BEST_MOVE_CHANGE_DECAY
+            + match has_changed {
+                true => 1.0,
+                false => 0.0,
+            };
+        self.stable_iterations = match has_changed {
+            true => 0,
+            false => self.stable_iterations + 1,
+        };
+        let score_drop = match self.previous_score {
+            Some(previous_score) => (previous_score - score).clamp(0, MAX_SCORE_DROP),
+            None => 0,
+        };
+        self.previous_best_move = *best_move;
+        self.previous_score = Some(*score);
+
+        let instability = 1.0 + self.best_move_changes * BEST_MOVE_CHANGE_WEIGHT;
+        let falling = 1.0 + score_drop as f64 / MAX_SCORE_DROP as f64;
+        let is_clearly_best = self.stable_iterations >= CLEAR_BEST_MOVE_ITERATIONS
+            && *best_move_effort >= CLEAR_BEST_MOVE_EFFORT;
+        let clarity = match is_clearly_best {
+            true => CLEAR_BEST_MOVE_FACTOR,
+            false => 1.0,
+        };
+        let target = self
+            .soft_limit
+            .mul_f64(instability * falling * clarity)
+            .min(self.hard_limit);
+        *elapsed >= target
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_clock(remaining: &u64, increment: &u64, moves_to_go: &Option<u32>) -> Clock {
+        Clock {
+            remaining: Duration::from_millis(*remaining),
+            increment: Duration::from_millis(*increment),
+            moves_to_go: *moves_to_go,
+            move_overhead: Duration::from_millis(30),
+        }
+    }
+
+    #[test]
+    fn limits_stay_within_the_clock() {
+        for clock in [
+            get_clock(&300_000, &0, &None),
+            get_clock(&60_000, &1_000, &None),
+            get_clock(&60_000, &0, &Some(40)),
+            get_clock(&60_000, &0, &Some(1)),
+            get_clock(&60_000, &0, &Some(0)),
+            get_clock(&5_000, &5_000, &Some(2)),
+            // only the increment is left
+            get_clock(&0, &2_000, &None),
+            get_clock(&100, &2_000, &None),
+            // less on the clock than the move overhead
+            get_clock(&20, &0, &None),
+            get_clock(&31, &0, &Some(1)),
+        ] {
+            let time_manager = TimeManager::new(&clock);
+            let available = clock.remaining.saturating_sub(clock.move_overhead);
+            assert!(
+                time_manager.soft_limit <= time_manager.hard_limit,
+                "{clock:?}"
+            );
+            assert!(time_manager.hard_limit <= available, "{clock:?}");
+            assert_eq!(time_manager.get_hard_limit(), time_manager.hard_limit);
+        }
+    }
+
+    #[test]
+    fn moves_to_go_shares_out_the_time() {
+        let one_move = TimeManager::new(&get_clock(&60_000, &0, &Some(1)));
+        let forty_moves = TimeManager::new(&get_clock(&60_000, &0, &Some(40)));
+        assert!(forty_moves.soft_limit < one_move.soft_limit);
+        assert_eq!(forty_moves.soft_limit, Duration::from_millis(59_970) / 40);
+    }
+
+    #[test]
+    fn stops_at_the_soft_limit_when_settled() {
+        let mut time_manager = TimeManager::new(&get_clock(&60_000, &0, &None));
+        let soft_limit = time_manager.soft_limit;
+        let best_move = Some(Move::new(&(1 << 12), &(1 << 28)));
+        let just_before = soft_limit - Duration::from_millis(1);
+        assert!(!time_manager.should_stop(&just_before, &best_move, &20, &0.5));
+        assert!(time_manager.should_stop(&soft_limit, &best_move, &20, &0.5));
+    }
+
+    #[test]
+    fn unsettled_searches_get_more_time_up_to_the_hard_limit() {
+        let mut time_manager = TimeManager::new(&get_clock(&60_000, &0, &None));
+        let soft_limit = time_manager.soft_limit;
+        let hard_limit = time_manager.hard_limit;
+        let moves = [
+            Some(Move::new(&(1 << 12), &(1 << 28))),
+            Some(Move::new(&(1 << 11), &(1 << 27))),
+        ];
+        assert!(!time_manager.should_stop(&Duration::ZERO, &moves[0], &50, &0.5));
+        // a changed best move and a falling score both stretch the soft limit
+        assert!(!time_manager.should_stop(&soft_limit, &moves[1], &-100, &0.5));
+        for iteration in 0..20 {
+            assert!(time_manager.should_stop(&hard_limit, &moves[iteration % 2], &-300, &0.5));
+        }
+    }
+
+    #[test]
+    fn clear_best_moves_stop_early() {
+        let mut time_manager = TimeManager::new(&get_clock(&60_000, &0, &None));
+        let early = time_manager.soft_limit.mul_f64(CLEAR_BEST_MOVE_FACTOR);
+        let best_move = Some(Move::new(&(1 << 12), &(1 << 28)));
+        for _ in 1..CLEAR_BEST_MOVE_ITERATIONS {
+            assert!(!time_manager.should_stop(&early, &best_move, &20, &0.9));
+        }
+        assert!(time_manager.should_stop(&early, &best_move, &20, &0.9));
+    }
+}
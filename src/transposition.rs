@@ -0,0 +1,201 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::moves::Move;
+use crate::pieces::Class;
+use crate::search::MATE_THRESHOLD;
+use crate::utils::bitboard_to_index;
+
+const BYTES_PER_MEGABYTE: usize = 1024 * 1024;
+
+// how a stored score relates to the true score of the position
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    // the search failed high, so the true score is at least this
+    Lower,
+    // every move failed low, so the true score is at most this
+    Upper,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TableEntry {
+    // from encode_move, or 0 if no move was best
+    pub best_move: u16,
+    pub score: i32,
+    pub depth: u8,
+    pub bound: Bound,
+}
+
+// the packed entry, a slot with no bound being empty:
+//   bits 0-15    best move
+//   bits 16-31   score
+//   bits 32-39   depth
+//   bits 40-41   bound, 1 exact, 2 lower and 3 upper
+// shared by every search thread without locking. the hash is stored xored
+// with the data, so that if two threads write a slot at once and one's hash
+// ends up beside the other's data, the pair no longer matches either hash
+// and is treated as empty
+#[derive(Default)]
+struct Slot {
+    checked_hash: AtomicU64,
+    data: AtomicU64,
+}
+
+pub struct TranspositionTable {
+    slots: Vec<Slot>,
+}
+
+// origin and destination square, and any promotion, which is enough to pick
+// the move out from the legal moves of the position again
+pub fn encode_move(mv: &Move) -> u16 {
+    let promotion = match mv.promotion {
+        Some(Class::Knight) => 1,
+        Some(Class::Bishop) => 2,
+        Some(Class::Rook) => 3,
+        Some(Class::Queen) => 4,
+        _ => 0,
+    };
+    (bitboard_to_index(&mv.origin_square)
+        | bitboard_to_index(&mv.destination_square) << 6
+        | promotion << 12) as u16
+}
+
+// mate scores count plies from the root, but a position can be reached at
+// any ply, so they are stored counting from the position itself
+fn score_to_table(score: &i32, ply: &u8) -> i32 {
+    match *score {
+        score if score >= MATE_THRESHOLD => score + *ply as i32,
+        score if score <= -MATE_THRESHOLD => score - *ply as i32,
+        score => score,
+    }
+}
+
+fn score_from_table(score: &i32, ply: &u8) -> i32 {
+    match *score {
+        score if score >= MATE_THRESHOLD => score - *ply as i32,
+        score if score <= -MATE_THRESHOLD => score + *ply as i32,
+        score => score,
+    }
+}
+
+fn pack_entry(entry: &TableEntry) -> u64 {
+    debug_assert!(
+        i16::try_from(entry.score).is_ok(),
+        "score {} does not fit in the table",
+        entry.score
+    );
+    let bound = match entry.bound {
+        Bound::Exact => 1,
+        Bound::Lower => 2,
+        Bound::Upper => 3,
+    };
+    entry.best_move as u64
+        | (entry.score as i16 as u16 as u64) << 16
+        | (entry.depth as u64) << 32
+        | bound << 40
+}
+
+fn unpack_entry(data: &u64) -> Option<TableEntry> {
+    let bound = match (data >> 40) & 0b11 {
+        1 => Bound::Exact,
+        2 => Bound::Lower,
+        3 => Bound::Upper,
+        _ => return None,
+    };
+    Some(TableEntry {
+        best_move: *data as u16,
+        score: (data >> 16) as u16 as i16 as i32,
+        depth: (data >> 32) as u8,
+        bound,
+    })
+}
+
+impl TranspositionTable {
+    pub fn new(megabytes: &usize) -> TranspositionTable {
+        let slot_count = (megabytes * BYTES_PER_MEGABYTE / size_of::<Slot>()).max(1);
+        TranspositionTable {
+            slots: (0..slot_count).map(|_| Slot::default()).collect(),
+        }
+    }
+
+    // empties every slot, for a new game
+    pub fn clear(&self) {
+        for slot in &self.slots {
+            slot.checked_hash.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+    }
+
+    fn get_index(&self, hash: &u64) -> usize {
+        ((*hash as u128 * self.slots.len() as u128) >> 64) as usize
+    }
+
+    fn load(&self, index: usize, hash: &u64) -> Option<TableEntry> {
+        let slot = &self.slots[index];
+        let data = slot.data.load(Ordering::Relaxed);
+        if slot.checked_hash.load(Ordering::Relaxed) ^ data != *hash {
+            return None;
+        }
+        unpack_entry(&data)
+    }
+
+    pub fn probe(&self, hash: &u64, ply: &u8) -> Option<TableEntry> {
+        let mut entry = self.load(self.get_index(hash), hash)?;
+        entry.score = score_from_table(&entry.score, ply);
+        Some(entry)
+    }
+
+    // a different position always takes the slot, while the same one keeps
+    // a deeper result unless the new one is exact
+    pub fn store(&self, hash: &u64, ply: &u8, entry: &TableEntry) {
+        let index = self.get_index(hash);
+        let existing = self.load(index, hash);
+        let mut entry = *entry;
+        if let Some(existing) = existing {
+            if existing.depth > entry.depth && entry.bound != Bound::Exact {
+                return;
+            }
+            if entry.best_move == 0 {
+                entry.best_move = existing.best_move;
+            }
+        }
+        entry.score = score_to_table(&entry.score, ply);
+        let data = pack_entry(&entry);
+        let slot = &self.slots[index];
+        slot.checked_hash.store(hash ^ data, Ordering::Relaxed);
+        slot.data.store(data, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::{INFINITY, MATE_SCORE, MAX_DEPTH, MAX_EVALUATION};
+
+    #[test]
+    fn entries_round_trip_at_the_score_limits() {
+        let table = TranspositionTable::new(&1);
+        for (score, ply) in [
+            (MAX_EVALUATION, 0),
+            (-MAX_EVALUATION, 0),
+            (MATE_SCORE - MAX_DEPTH as i32, MAX_DEPTH),
+            (-MATE_SCORE + 1, 1),
+            (INFINITY, 0),
+            (-INFINITY, 0),
+        ] {
+            let hash = (score as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+            let entry = TableEntry {
+                best_move: 0x1234,
+                score,
+                depth: 7,
+                bound: Bound::Lower,
+            };
+            table.store(&hash, &ply, &entry);
+            let stored = table.probe(&hash, &ply).unwrap();
+            assert_eq!(stored.score, score);
+            assert_eq!(stored.best_move, 0x1234);
+            assert_eq!(stored.depth, 7);
+            assert_eq!(stored.bound, Bound::Lower);
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use std::thread;
+
+use crate::datagen::{decode_record, RECORD_SIZE};
+use crate::errors::DatasetError;
+use crate::evaluation::evaluate_white_relative;
+use crate::notation::fen_to_position;
+use crate::parameters::Parameters;
+use crate::position::Position;
+
+// the bounds the scaling constant is searched between, and how finely
+const MIN_SCALING: f64 = 0.0;
+const MAX_SCALING: f64 = 5.0;
+const SCALING_ITERATIONS: usize = 50;
+
+pub struct TuningPosition {
+    pub position: Position,
+    // 1 for a white win, 0.5 for a draw and 0 for a black win
+    pub result: f64,
+}
+
+pub struct TuningProgress {
+    pub pass: usize,
+    pub error: f64,
+    pub changed: usize,
+}
+
+fn parse_result(text: &str) -> Option<f64> {
+    let text = text.trim_matches(|c: char| c.is_whitespace() || "[]\"';".contains(c));
+    match text {
+        "1-0" | "1" | "1.0" => Some(1.0),
+        "0-1" | "0" | "0.0" => Some(0.0),
+        "1/2-1/2" | "0.5" | "=" => Some(0.5),
+        _ => None,
+    }
+}
+
+// one position per line, either 'FEN RESULT' with the result wrapped in
+// brackets or quotes if wanted, or fields split by '|' with the FEN first and
+// the result last. results are always from white's side
+pub fn parse_dataset(text: &str) -> Result<Vec<TuningPosition>, DatasetError> {
+    let mut positions = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (fen, result) = match line.split_once('|') {
+            Some((fen, rest)) => (fen, rest.rsplit('|').next().unwrap_or(rest)),
+            None => line
+                .rsplit_once(char::is_whitespace)
+                .ok_or(DatasetError::MissingResult(line_number))?,
+        };
+        let result = parse_result(result).ok_or(DatasetError::InvalidResult(
+            line_number,
+            result.trim().to_string(),
+        ))?;
+        let position = fen_to_position(fen.trim().trim_end_matches(';'))
+            .map_err(|error| DatasetError::InvalidFen(line_number, error))?;
+        positions.push(TuningPosition { position, result });
+    }
+    Ok(positions)
+}
+
+// the binary records written by datagen, which must be whole
+pub fn parse_binary_dataset(bytes: &[u8]) -> Result<Vec<TuningPosition>, DatasetError> {
+    bytes
+        .chunks(RECORD_SIZE)
+        .enumerate()
+        .map(|(index, chunk)| {
+            let record = chunk
+                .try_into()
+                .ok()
+                .and_then(decode_record)
+                .ok_or(DatasetError::InvalidRecord(index + 1))?;
+            Ok(TuningPosition {
+                position: record.position,
+                result: record.result,
+            })
+        })
+        .collect()
+}
+
+// maps a score to the expected result, so 0 gives a draw and the scaling
+// decides how quickly an advantage turns into a win
+pub fn get_expected_result(score: &i32, scaling: &f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-scaling * *score as f64 / 400.0))
+}
+
+// mean squared difference between the results the evaluation predicts and
+// the ones the games actually had, worked out over every core
+pub fn compute_error(positions: &[TuningPosition], parameters: &Parameters, scaling: &f64) -> f64 {
+    if positions.is_empty() {
+        return 0.0;
+    }
+    let threads = thread::available_parallelism().map_or(1, |count| count.get());
+    let chunk_size = positions.len().div_ceil(threads);
+
+    let total: f64 = thread::scope(|scope| {
+        let handles: Vec<_> = positions
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|entry| {
+                            let score = evaluate_white_relative(&entry.position, parameters);
+                            (entry.result - get_expected_result(&score, scaling)).powi(2)
+                        })
+                        .sum::<f64>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap_or(f64::INFINITY))
+            .sum()
+    });
+    total / positions.len() as f64
+}
+
+// the scaling that fits the current evaluation best, found by narrowing in on
+// the lowest error since the error only has the one minimum
+pub fn find_scaling(positions: &[TuningPosition], parameters: &Parameters) -> f64 {
+    let mut low = MIN_SCALING;
+    let mut high = MAX_SCALING;
+    for _ in 0..SCALING_ITERATIONS {
+        let third = (high - low) / 3.0;
+        let lower_error = compute_error(positions, parameters, &(low + third));
+        let upper_error = compute_error(positions, parameters, &(high - third));
+        match lower_error < upper_error {
+            true => high -= third,
+            false => low += third,
+        }
+    }
+    (low + high) / 2.0
+}
+
+// texel's local search: nudge every weight up or down by one, keep whatever
+// lowers the error and carry on in that direction while it keeps helping,
+// until a whole pass changes nothing or max_passes is reached
+pub fn tune(
+    positions: &[TuningPosition],
+    start: &Parameters,
+    scaling: &f64,
+    max_passes: &usize,
+    report: &mut dyn FnMut(&TuningProgress, &Parameters),
+) -> Parameters {
+    let mut parameters = start.clone();
+    let mut values = parameters.get_values();
+    let mut best_error = compute_error(positions, &parameters, scaling);
+
+    for pass in 1..=*max_passes {
+        let mut changed = 0;
+        for index in 0..values.len() {
+            for step in [1, -1] {
+                let mut improved = false;
+                loop {
+                    values[index] += step;
+                    parameters.set_values(&values);
+                    let error = compute_error(positions, &parameters, scaling);
+                    if error >= best_error {
+                        values[index] -= step;
+                        break;
+                    }
+                    best_error = error;
+                    improved = true;
+                }
+                parameters.set_values(&values);
+                if improved {
+                    changed += 1;
+                    // no point trying the other way straight after this one helped
+                    break;
+                }
+            }
+        }
+
+        let progress = TuningProgress {
+            pass,
+            error: best_error,
+            changed,
+        };
+        report(&progress, &parameters);
+        if changed == 0 {
+            break;
+        }
+    }
+    parameters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tune_lowers_the_error() {
+        // results the default weights get wrong, so there is something to fix
+        let positions = parse_dataset(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 [0.5]
+            rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2 [1-0]
+            4k3/8/8/8/8/8/4P3/4K3 w - - 0 1 [1/2-1/2]
+            4k3/4p3/8/8/8/8/8/4K3 b - - 0 1 [1/2-1/2]
+            6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1 [1/2-1/2]
+            3r2k1/5ppp/8/8/8/8/5PPP/6K1 w - - 0 1 [1-0]
+            r3k3/8/8/8/8/8/8/4K2Q b - - 0 1 [0-1]
+            4k3/pppp4/8/8/8/8/4PPPP/4K3 w - - 0 1 [1-0]",
+        )
+        .unwrap();
+        let parameters = Parameters::default();
+        let scaling = 1.0;
+        let start_error = compute_error(&positions, &parameters, &scaling);
+
+        let mut passes = 0;
+        let tuned = tune(&positions, &parameters, &scaling, &2, &mut |progress, _| {
+            passes = progress.pass;
+        });
+        let tuned_error = compute_error(&positions, &tuned, &scaling);
+        assert!(tuned_error < start_error, "{tuned_error} >= {start_error}");
+        assert!(passes >= 1);
+    }
+}
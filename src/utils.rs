@@ -2,25 +2,25 @@ use std::cmp;
 use std::collections::HashSet;
 
 use crate::board::{FILES, RANKS};
+use crate::errors::SquareError;
 
-pub fn algebraic_to_index(algebraic: &String) -> Result<usize, &'static str> {
-    if algebraic.len() != 2 {
-        return Err("Input must be exactly 2 characters long.");
-    }
-
-    let file: char = algebraic.chars().nth(0).unwrap();
-    let rank: char = algebraic.chars().nth(1).unwrap();
+pub fn algebraic_to_index(algebraic: &str) -> Result<usize, SquareError> {
+    let mut chars = algebraic.chars();
+    let (file, rank) = match (chars.next(), chars.next(), chars.next()) {
+        (Some(file), Some(rank), None) => (file, rank),
+        _ => return Err(SquareError::WrongLength),
+    };
 
     let file_index = if FILES.contains(&file) {
         file as u8 - b'a'
     } else {
-        return Err("Invalid file. Must be between 'a' and 'h'.");
+        return Err(SquareError::InvalidFile(file));
     };
 
     let rank_index = if RANKS.contains(&rank) {
         rank as u8 - b'1'
     } else {
-        return Err("Invalid rank. Must be between '1' and '8'.");
+        return Err(SquareError::InvalidRank(rank));
     };
 
     // Calculate the final index: (rank_index * 8 + file_index)
@@ -29,15 +29,15 @@ pub fn algebraic_to_index(algebraic: &String) -> Result<usize, &'static str> {
     Ok(index)
 }
 
-pub fn index_to_algebraic(index: &usize) -> Result<String, &'static str> {
+pub fn index_to_algebraic(index: &usize) -> Result<String, SquareError> {
     if *index > 63 {
-        return Err("Invalid index. Must be less than 64");
+        Err(SquareError::IndexOutOfRange(*index))
     } else {
         let file_index = *index % 8;
         let file = FILES[file_index].to_string();
         let rank = (*index / 8 + 1).to_string();
         let algebraic = format!("{file}{rank}");
-        return Ok(algebraic);
+        Ok(algebraic)
     }
 }
 
@@ -46,7 +46,7 @@ pub fn index_to_bitboard(index: &usize) -> u64 {
 }
 
 pub fn bit_scan(bitboard: &u64) -> HashSet<usize> {
-    let mut mask = bitboard.clone();
+    let mut mask = *bitboard;
     let mut indicies = HashSet::new();
     let mut index: usize = 0;
     while mask > 0 {
@@ -54,8 +54,19 @@ pub fn bit_scan(bitboard: &u64) -> HashSet<usize> {
             indicies.insert(index);
         }
         let shift_by = cmp::max(1, mask.trailing_zeros());
-        mask = mask >> shift_by;
+        mask >>= shift_by;
         index += shift_by as usize;
     }
     indicies
 }
+
+// removes the least significant set bit from the bitboard and returns it as a square
+pub fn pop_lsb(bitboard: &mut u64) -> u64 {
+    let square = *bitboard & bitboard.wrapping_neg();
+    *bitboard &= bitboard.wrapping_sub(1);
+    square
+}
+
+pub fn bitboard_to_index(bitboard: &u64) -> usize {
+    bitboard.trailing_zeros() as usize
+}
@@ -0,0 +1,55 @@
+use crate::pieces::{Colour, Piece};
+use crate::position::Position;
+use crate::utils::{bitboard_to_index, pop_lsb};
+
+// one key for every piece on every square, in Piece::iter order, then one
+// for each rook that can still castle and each en passant square, and one
+// more for black to move
+const PIECE_KEY_COUNT: usize = 12 * 64;
+const KEY_COUNT: usize = PIECE_KEY_COUNT + 64 + 64 + 1;
+const CASTLING_KEYS_START: usize = PIECE_KEY_COUNT;
+const EN_PASSANT_KEYS_START: usize = CASTLING_KEYS_START + 64;
+const BLACK_TO_MOVE_KEY: usize = EN_PASSANT_KEYS_START + 64;
+
+// splitmix64 from a fixed seed, so that hashes are the same from run to run
+const fn generate_keys() -> [u64; KEY_COUNT] {
+    let mut keys = [0; KEY_COUNT];
+    let mut state: u64 = 0x2545_F491_4F6C_DD1D;
+    let mut index = 0;
+    while index < KEY_COUNT {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut key = state;
+        key = (key ^ (key >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        key = (key ^ (key >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        keys[index] = key ^ (key >> 31);
+        index += 1;
+    }
+    keys
+}
+
+const KEYS: [u64; KEY_COUNT] = generate_keys();
+
+// worked out from scratch, which for a copied position is about as quick as
+// keeping it up to date move by move would be
+pub fn get_hash(position: &Position) -> u64 {
+    let mut hash = 0;
+    for (piece_index, piece) in Piece::iter().iter().enumerate() {
+        let mut pieces = position.get_bitboard(piece);
+        while pieces != 0 {
+            let square = pop_lsb(&mut pieces);
+            hash ^= KEYS[piece_index * 64 + bitboard_to_index(&square)];
+        }
+    }
+    let mut castling_rooks = position.castling_rights;
+    while castling_rooks != 0 {
+        let square = pop_lsb(&mut castling_rooks);
+        hash ^= KEYS[CASTLING_KEYS_START + bitboard_to_index(&square)];
+    }
+    if position.en_passant_square != 0 {
+        hash ^= KEYS[EN_PASSANT_KEYS_START + bitboard_to_index(&position.en_passant_square)];
+    }
+    if position.turn == Colour::Black {
+        hash ^= KEYS[BLACK_TO_MOVE_KEY];
+    }
+    hash
+}